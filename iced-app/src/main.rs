@@ -9,7 +9,7 @@ use iced::{
         image::Handle,
         pick_list, row, scrollable, text, text_input, Rule,
     },
-    Color, Element, Length, Point, Sandbox, Settings, Theme,
+    Color, Element, Length, Point, Sandbox, Settings, Size, Theme,
 };
 use prac_2022_11::{
     app::{AppState, ProblemName},
@@ -29,10 +29,82 @@ struct App {
 #[derive(Debug, Clone)]
 pub enum Message {
     SetField { name: String, val: String },
+    SetOutputDir(String),
+    Reset,
     ClearSolution { index: usize },
+    StoreValue { name: String, val: f64 },
     Solve,
     None,
     SelectProblem(ProblemName),
+    SaveFile { filename: String, contents: String },
+    SaveSession,
+    LoadSession,
+}
+
+/// Where the session is kept, relative to the output directory.
+const SESSION_FILE: &str = "session.json";
+
+/// A `SolutionParagraph::Heatmap`, z mapped from blue at its minimum to red at its maximum.
+struct Heatmap<'a> {
+    z: &'a [Vec<f64>],
+    from: f64,
+    to: f64,
+}
+
+impl Program<Message> for Heatmap<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _: &Self::State,
+        _: &Theme,
+        bounds: iced::Rectangle,
+        _: iced::widget::canvas::Cursor,
+    ) -> Vec<iced::widget::canvas::Geometry> {
+        let (min, max) = self
+            .z
+            .iter()
+            .flatten()
+            .filter(|z| z.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), z| {
+                (min.min(*z), max.max(*z))
+            });
+
+        let cells = Cache::default().draw(bounds.size(), |frame| {
+            let rows = self.z.len();
+            for (j, row) in self.z.iter().enumerate() {
+                let height = bounds.height / rows as f32;
+                let width = bounds.width / row.len() as f32;
+                for (i, z) in row.iter().enumerate() {
+                    let color = if !z.is_finite() {
+                        Color::BLACK
+                    } else if max > min {
+                        let t = ((z - min) / (max - min)) as f32;
+                        Color::from_rgb(t, 0.0, 1.0 - t)
+                    } else {
+                        Color::from_rgb(0.5, 0.0, 0.5)
+                    };
+                    // the first row is the bottom one, y grows upwards
+                    frame.fill_rectangle(
+                        Point::new(i as f32 * width, (rows - j - 1) as f32 * height),
+                        Size::new(width, height),
+                        color,
+                    );
+                }
+            }
+
+            frame.fill_text(canvas::Text {
+                content: format!(
+                    "x, s from {:.2} to {:.2}, z from {:.2} to {:.2}",
+                    self.from, self.to, min, max
+                ),
+                color: Color::WHITE,
+                ..Default::default()
+            });
+        });
+
+        vec![cells]
+    }
 }
 
 impl Program<Message> for Graph {
@@ -50,7 +122,7 @@ impl Program<Message> for Graph {
         let funcs = Cache::default().draw(bounds.size(), |frame| {
             for p in &self.paths {
                 let path = Path::new(|path| {
-                    for (x, y) in &p.pts {
+                    for (x, y) in p.pts.iter().filter(|(x, y)| x.is_finite() && y.is_finite()) {
                         let (x, y) = Viewport::convert(&self.viewport, &bounds_viewport, (*x, *y));
 
                         if p.kind == PathKind::Dot {
@@ -73,20 +145,41 @@ impl Program<Message> for Graph {
                     }
                 }
             }
+
+            let legend = self.legend();
+            if !legend.is_empty() {
+                let left = bounds.width - 120.0;
+                frame.fill_rectangle(
+                    Point::new(left, 5.0),
+                    Size::new(115.0, 18.0 * legend.len() as f32 + 5.0),
+                    Color::WHITE,
+                );
+                for (i, (label, color)) in legend.into_iter().enumerate() {
+                    let top = 10.0 + 18.0 * i as f32;
+                    frame.fill_rectangle(
+                        Point::new(left + 5.0, top),
+                        Size::new(12.0, 12.0),
+                        Color::from_rgb(color.0, color.1, color.2),
+                    );
+                    frame.fill_text(canvas::Text {
+                        content: label.to_string(),
+                        position: Point::new(left + 23.0, top),
+                        size: 14.0,
+                        ..Default::default()
+                    });
+                }
+            }
         });
 
         let grid = Cache::default().draw(bounds.size(), |frame| {
-            for i in (self.viewport.left.floor() as i32)..=(self.viewport.right.ceil() as i32) {
+            for x in self.viewport.x_grid() {
                 let path = Path::new(|path| {
-                    let (x0, y0) = Viewport::convert(
-                        &self.viewport,
-                        &bounds_viewport,
-                        (i as f64, self.viewport.top),
-                    );
+                    let (x0, y0) =
+                        Viewport::convert(&self.viewport, &bounds_viewport, (x, self.viewport.top));
                     let (x1, y1) = Viewport::convert(
                         &self.viewport,
                         &bounds_viewport,
-                        (i as f64, self.viewport.bottom),
+                        (x, self.viewport.bottom),
                     );
 
                     path.line_to(Point::new(x0 as f32, y0 as f32));
@@ -97,21 +190,21 @@ impl Program<Message> for Graph {
                     &path,
                     Stroke::default()
                         .with_color(Color::BLACK)
-                        .with_width(if i == 0 { 2.0 } else { 1.0 }),
+                        .with_width(if x == 0.0 { 2.0 } else { 1.0 }),
                 );
             }
 
-            for i in (self.viewport.bottom.floor() as i32)..=(self.viewport.top.ceil() as i32) {
+            for y in self.viewport.y_grid() {
                 let path = Path::new(|path| {
                     let (x0, y0) = Viewport::convert(
                         &self.viewport,
                         &bounds_viewport,
-                        (self.viewport.left, i as f64),
+                        (self.viewport.left, y),
                     );
                     let (x1, y1) = Viewport::convert(
                         &self.viewport,
                         &bounds_viewport,
-                        (self.viewport.right, i as f64),
+                        (self.viewport.right, y),
                     );
 
                     path.line_to(Point::new(x0 as f32, y0 as f32));
@@ -122,7 +215,7 @@ impl Program<Message> for Graph {
                     &path,
                     Stroke::default()
                         .with_color(Color::BLACK)
-                        .with_width(if i == 0 { 2.0 } else { 1.0 }),
+                        .with_width(if y == 0.0 { 2.0 } else { 1.0 }),
                 );
             }
 
@@ -161,37 +254,64 @@ impl Sandbox for App {
                 let cur_solution = self.state.solve();
 
                 if let Some(solution) = cur_solution {
-                    for par in &solution.explanation {
-                        if let SolutionParagraph::Latex(s) = par {
-                            self.image_handles.entry(s.to_string()).or_insert(
-                                if cfg!(target_os = "linux") {
-                                    Command::new("pnglatex")
-                                        .current_dir("images")
-                                        .args(["-f", s, "-d", "400"])
-                                        .output()
-                                        .map_err(|e| format!("{e} - install pnglatex"))
-                                        .and_then(|out| {
-                                            if out.status.success() {
-                                                Ok(out)
-                                            } else {
-                                                Err(format!("pnglatex error {:?}", out))
-                                            }
-                                        })
-                                        .and_then(|out| {
-                                            String::from_utf8(out.stdout).map_err(|e| e.to_string())
-                                        })
-                                        .map(|path| Handle::from_path(path.trim()))
-                                } else {
-                                    Err("can not render latex, unsupported os".to_string())
-                                },
-                            );
-                        }
+                    for s in solution.latex() {
+                        self.image_handles.entry(s.to_string()).or_insert(
+                            if cfg!(target_os = "linux") {
+                                Command::new("pnglatex")
+                                    .current_dir("images")
+                                    .args(["-f", s, "-d", "400"])
+                                    .output()
+                                    .map_err(|e| format!("{e} - install pnglatex"))
+                                    .and_then(|out| {
+                                        if out.status.success() {
+                                            Ok(out)
+                                        } else {
+                                            Err(format!("pnglatex error {:?}", out))
+                                        }
+                                    })
+                                    .and_then(|out| {
+                                        String::from_utf8(out.stdout).map_err(|e| e.to_string())
+                                    })
+                                    .map(|path| Handle::from_path(path.trim()))
+                            } else {
+                                Err("can not render latex, unsupported os".to_string())
+                            },
+                        );
                     }
                 }
             }
+            Message::SetOutputDir(dir) => {
+                self.state.set_output_dir(dir.into());
+                self.state.validate();
+            }
+            Message::Reset => {
+                self.state.reset_fields();
+                self.state.validate();
+            }
             Message::None => {}
             Message::ClearSolution { index } => self.state.rem_solution(index),
+            Message::StoreValue { name, val } => {
+                self.state.store_value(&name, val);
+                self.state.validate();
+            }
             Message::SelectProblem(p) => self.state.set_problem(p),
+            Message::SaveFile { filename, contents } => {
+                if let Err(e) = self.state.save_download(&filename, &contents) {
+                    println!("Could not save {filename}: {e}");
+                }
+            }
+            Message::SaveSession => {
+                let path = self.state.get_output_dir().join(SESSION_FILE);
+                if let Err(e) = self.state.save_session(&path) {
+                    println!("Could not save the session to {}: {e}", path.display());
+                }
+            }
+            Message::LoadSession => {
+                let path = self.state.get_output_dir().join(SESSION_FILE);
+                if let Err(e) = self.state.load_session(&path) {
+                    println!("Could not load the session from {}: {e}", path.display());
+                }
+            }
         }
     }
 
@@ -231,6 +351,35 @@ impl Sandbox for App {
             .collect();
 
         left_column_elems.append(&mut form);
+        left_column_elems.push(
+            row![
+                text("output_dir"),
+                text_input(
+                    "",
+                    &self.state.get_output_dir().to_string_lossy(),
+                    Message::SetOutputDir
+                )
+            ]
+            .into(),
+        );
+        left_column_elems.push(
+            button("Reset")
+                .on_press(Message::Reset)
+                .style(theme::Button::Secondary)
+                .into(),
+        );
+        left_column_elems.push(
+            row![
+                button("Save")
+                    .on_press(Message::SaveSession)
+                    .style(theme::Button::Secondary),
+                button("Load")
+                    .on_press(Message::LoadSession)
+                    .style(theme::Button::Secondary),
+            ]
+            .spacing(5)
+            .into(),
+        );
         left_column_elems.push(
             button("Solve")
                 .on_press(if self.state.get_validation_errors().is_empty() {
@@ -255,6 +404,17 @@ impl Sandbox for App {
             .state
             .get_solutions()
             .map(|s| {
+                let store_buttons = s.values.iter().map(|(name, val)| {
+                    Element::from(
+                        button(text(format!("store {name} = {val:.4}")))
+                            .style(theme::Button::Secondary)
+                            .on_press(Message::StoreValue {
+                                name: name.clone(),
+                                val: *val,
+                            }),
+                    )
+                });
+
                 s.explanation
                     .iter()
                     .map(|e| match e {
@@ -276,7 +436,25 @@ impl Sandbox for App {
                             .map(|handle| image(handle).height(Length::Units(30)))
                             .map_err(|e| text(e).style(Color::from_rgb(1.0, 0.0, 0.0)))
                             .map_or_else(Element::from, Element::from),
+                        SolutionParagraph::Downloadable { filename, contents } => Element::from(
+                            button(text(format!("Save CSV {filename}")))
+                                .style(theme::Button::Secondary)
+                                .on_press(Message::SaveFile {
+                                    filename: filename.clone(),
+                                    contents: contents.clone(),
+                                }),
+                        ),
+                        SolutionParagraph::Heatmap { z, from, to, .. } => Element::from(
+                            canvas(Heatmap {
+                                z,
+                                from: *from,
+                                to: *to,
+                            })
+                            .width(Length::Units(300))
+                            .height(Length::Units(300)),
+                        ),
                     })
+                    .chain(store_buttons)
                     .collect::<Vec<_>>()
             })
             .enumerate()
@@ -1,10 +1,16 @@
-use std::collections::LinkedList;
+use std::{
+    collections::LinkedList,
+    path::{Path, PathBuf},
+};
 
+use crate::mathparse::DefaultRuntime;
 use crate::problems::{
-    area_calc::AreaCalcProblemCreator, fredholm_1st::Fredholm1stProblemCreator,
-    gradients_min::GradientsMinProblemCreator, penalty_min::PenaltyMinProblemCreator,
-    spline::SplineProblemCreator, volterra_2nd::Volterra2ndProblemCreator, Problem, ProblemCreator,
-    Solution, ValidationError,
+    area_calc::AreaCalcProblemCreator, default_output_dir, fredholm_1st::Fredholm1stProblemCreator,
+    gradients_min::GradientsMinProblemCreator, nelder_mead::NelderMeadProblemCreator,
+    ode::OdeProblemCreator, parametric::PlotParametricProblemCreator,
+    penalty_min::PenaltyMinProblemCreator, polyfit::PolyFitProblemCreator, resolve_output_path,
+    root_find::RootFindProblemCreator, spline::SplineProblemCreator,
+    volterra_2nd::Volterra2ndProblemCreator, Problem, ProblemCreator, Solution, ValidationError,
 };
 
 pub struct AppState {
@@ -13,6 +19,8 @@ pub struct AppState {
     prepared_problem: Option<Box<dyn Problem>>,
     validation_errors: Vec<ValidationError>,
     solutions: LinkedList<Solution>,
+    output_dir: PathBuf,
+    stored_values: DefaultRuntime,
 }
 
 impl Default for AppState {
@@ -25,16 +33,33 @@ impl Default for AppState {
                 Box::new(PenaltyMinProblemCreator::default()),
                 Box::new(SplineProblemCreator::default()),
                 Box::new(GradientsMinProblemCreator::default()),
+                Box::new(PlotParametricProblemCreator::default()),
+                Box::new(NelderMeadProblemCreator::default()),
+                Box::new(OdeProblemCreator::default()),
+                Box::new(PolyFitProblemCreator::default()),
+                Box::new(RootFindProblemCreator::default()),
             ],
             cur_problem_creator: 0,
             prepared_problem: None,
             validation_errors: Vec::new(),
             solutions: LinkedList::new(),
+            output_dir: default_output_dir(),
+            stored_values: DefaultRuntime::default(),
         }
     }
 }
 
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Session {
+    problem: ProblemName,
+    output_dir: PathBuf,
+    /// Fields of every problem creator, problems missing here keep their defaults.
+    forms: Vec<(ProblemName, Vec<(String, String)>)>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ProblemName {
     FredholmFirst,
     AreaCalc,
@@ -42,6 +67,11 @@ pub enum ProblemName {
     PenaltyMin,
     Spline,
     GradientsMin,
+    PlotParametric,
+    NelderMead,
+    Ode,
+    PolyFit,
+    RootFind,
 }
 
 impl ProblemName {
@@ -53,6 +83,11 @@ impl ProblemName {
             ProblemName::PenaltyMin => 3,
             ProblemName::Spline => 4,
             ProblemName::GradientsMin => 5,
+            ProblemName::PlotParametric => 6,
+            ProblemName::NelderMead => 7,
+            ProblemName::Ode => 8,
+            ProblemName::PolyFit => 9,
+            ProblemName::RootFind => 10,
         }
     }
     fn from_index(index: usize) -> Option<Self> {
@@ -63,6 +98,11 @@ impl ProblemName {
             3 => Some(ProblemName::PenaltyMin),
             4 => Some(ProblemName::Spline),
             5 => Some(ProblemName::GradientsMin),
+            6 => Some(ProblemName::PlotParametric),
+            7 => Some(ProblemName::NelderMead),
+            8 => Some(ProblemName::Ode),
+            9 => Some(ProblemName::PolyFit),
+            10 => Some(ProblemName::RootFind),
             _ => None,
         }
     }
@@ -77,6 +117,11 @@ impl ToString for ProblemName {
             ProblemName::PenaltyMin => "Constrained minimum".to_string(),
             ProblemName::Spline => "Spline".to_string(),
             ProblemName::GradientsMin => "Gradients minimum".to_string(),
+            ProblemName::PlotParametric => "Parametric curve".to_string(),
+            ProblemName::NelderMead => "Nelder-Mead minimum".to_string(),
+            ProblemName::Ode => "ODE initial value".to_string(),
+            ProblemName::PolyFit => "Polynomial fit".to_string(),
+            ProblemName::RootFind => "Root of f(x)".to_string(),
         }
     }
 }
@@ -97,6 +142,11 @@ impl AppState {
             ProblemName::PenaltyMin,
             ProblemName::Spline,
             ProblemName::GradientsMin,
+            ProblemName::PlotParametric,
+            ProblemName::NelderMead,
+            ProblemName::Ode,
+            ProblemName::PolyFit,
+            ProblemName::RootFind,
         ]
     }
     pub fn set_problem(&mut self, name: ProblemName) {
@@ -112,13 +162,26 @@ impl AppState {
     pub fn set_field(&mut self, name: &str, val: String) {
         self.mut_cur().set_field(name, val);
     }
+    pub fn reset_fields(&mut self) {
+        self.mut_cur().reset();
+    }
+    pub fn get_output_dir(&self) -> &Path {
+        &self.output_dir
+    }
+    pub fn set_output_dir(&mut self, dir: PathBuf) {
+        for creator in &mut self.problem_creators {
+            creator.set_output_dir(&dir);
+        }
+        self.output_dir = dir;
+    }
+
     pub fn get_validation_errors(&self) -> &[ValidationError] {
         &self.validation_errors
     }
 
     pub fn validate(&mut self) {
         self.validation_errors.clear();
-        self.prepared_problem = match self.cur().try_create() {
+        self.prepared_problem = match self.cur().try_create(&self.stored_values) {
             Ok(p) => Some(p),
             Err(e) => {
                 self.validation_errors = e;
@@ -140,9 +203,226 @@ impl AppState {
     pub fn get_solutions(&self) -> impl Iterator<Item = &Solution> {
         self.solutions.iter()
     }
+    /// Makes `val` available as the variable `name` in the expressions of every problem.
+    pub fn store_value(&mut self, name: &str, val: f64) {
+        self.stored_values.set_var(name, val);
+    }
+
+    /// Writes the contents of a `Downloadable` paragraph, returns the path it went to.
+    pub fn save_download(&self, filename: &str, contents: &str) -> std::io::Result<PathBuf> {
+        let path = resolve_output_path(&self.output_dir, filename);
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Saves the selected problem, the output directory and the fields of every problem as json.
+    #[cfg(feature = "serde")]
+    pub fn save_session(&self, path: &Path) -> std::io::Result<()> {
+        let session = Session {
+            problem: self.get_cur_problem().unwrap_or(ProblemName::FredholmFirst),
+            output_dir: self.output_dir.clone(),
+            forms: self
+                .get_problems()
+                .into_iter()
+                .map(|problem| {
+                    let fields = self.problem_creators[problem.to_index()]
+                        .fields()
+                        .map(|(name, val)| (name.to_string(), val.to_string()))
+                        .collect();
+                    (problem, fields)
+                })
+                .collect(),
+        };
+        std::fs::write(path, serde_json::to_string_pretty(&session)?)
+    }
+
+    /// Restores what `save_session` wrote, solutions are not part of a session.
+    #[cfg(feature = "serde")]
+    pub fn load_session(&mut self, path: &Path) -> std::io::Result<()> {
+        let session: Session = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+        for creator in &mut self.problem_creators {
+            creator.reset();
+        }
+        for (problem, fields) in session.forms {
+            let creator = &mut self.problem_creators[problem.to_index()];
+            // fields can add others (e.g. "f" adds the starting point of each variable),
+            // so they are set in the order they were saved in. Setting the trailing
+            // empty constraint would add yet another one, unchanged fields are skipped.
+            for (name, val) in fields {
+                if !creator.fields().any(|(n, v)| n == name && v == val) {
+                    creator.set_field(&name, val);
+                }
+            }
+        }
+        self.set_problem(session.problem);
+        self.set_output_dir(session.output_dir);
+        self.validate();
+        Ok(())
+    }
+
     pub fn rem_solution(&mut self, index: usize) {
         let mut split_list = self.solutions.split_off(index);
         split_list.pop_front();
         self.solutions.append(&mut split_list);
     }
 }
+
+#[test]
+fn reset_fields() {
+    let mut state = AppState::default();
+
+    for problem in state.get_problems() {
+        state.set_problem(problem);
+        let defaults = state
+            .fields()
+            .map(|(name, val)| (name.to_string(), val.to_string()))
+            .collect::<Vec<_>>();
+
+        for (name, _) in &defaults {
+            state.set_field(name, "1".to_string());
+        }
+        state.set_field("constraint1", "x".to_string());
+        assert!(state
+            .fields()
+            .zip(defaults.iter())
+            .any(|((_, val), (_, default))| val != default));
+
+        state.reset_fields();
+        let after_reset = state
+            .fields()
+            .map(|(name, val)| (name.to_string(), val.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(after_reset, defaults);
+    }
+}
+
+#[test]
+fn stored_values() {
+    let mut state = AppState::default();
+
+    state.set_problem(ProblemName::AreaCalc);
+    state.validate();
+    let x12 = state
+        .solve()
+        .and_then(|s| s.values.iter().find(|(name, _)| name == "x12"))
+        .map(|(_, val)| *val)
+        .unwrap();
+
+    state.set_problem(ProblemName::PenaltyMin);
+    state.set_field("f", "pow(x-sin(x12),2)".to_string());
+    state.validate();
+    assert!(state
+        .get_validation_errors()
+        .iter()
+        .any(|e| e.0.contains("x12")));
+
+    state.store_value("x12", x12);
+    state.validate();
+    assert!(state.get_validation_errors().is_empty());
+    let min_x = state
+        .solve()
+        .and_then(|s| s.values.iter().find(|(name, _)| name == "min_x"))
+        .map(|(_, val)| *val)
+        .unwrap();
+    assert!((min_x - x12.sin()).abs() < 0.01);
+}
+
+#[test]
+fn save_download() {
+    let mut state = AppState::default();
+    state.set_output_dir(std::env::temp_dir());
+    state.set_problem(ProblemName::AreaCalc);
+    state.validate();
+
+    let (filename, contents) = state
+        .solve()
+        .and_then(|s| s.downloads().next())
+        .map(|(name, contents)| (name.to_string(), contents.to_string()))
+        .unwrap();
+    let path = state.save_download(&filename, &contents).unwrap();
+
+    assert_eq!(path, std::env::temp_dir().join(&filename));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn session() {
+    let fields = |state: &AppState| {
+        state
+            .problem_creators
+            .iter()
+            .map(|creator| {
+                let mut fields = creator
+                    .fields()
+                    .map(|(name, val)| (name.to_string(), val.to_string()))
+                    .collect::<Vec<_>>();
+                // the variables of an expression come in no particular order
+                fields.sort();
+                fields
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut state = AppState::default();
+    state.set_output_dir(std::env::temp_dir());
+    for problem in state.get_problems() {
+        state.set_problem(problem);
+        let names = state
+            .fields()
+            .map(|(name, _)| name.to_string())
+            .collect::<Vec<_>>();
+        for (i, name) in names.iter().enumerate() {
+            state.set_field(name, format!("{}", i + 2));
+        }
+    }
+    state.set_problem(ProblemName::NelderMead);
+    state.set_field("f", "pow(a-1,2)+pow(b,2)+c".to_string());
+    state.set_field("b0", "0.5".to_string());
+    state.set_problem(ProblemName::GradientsMin);
+
+    let path = std::env::temp_dir().join("prac_session_test.json");
+    state.save_session(&path).unwrap();
+
+    let mut loaded = AppState::default();
+    loaded.load_session(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(fields(&loaded), fields(&state));
+    assert_eq!(loaded.get_cur_problem(), Some(ProblemName::GradientsMin));
+    assert_eq!(loaded.get_output_dir(), std::env::temp_dir());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn session_by_name() {
+    // only some of the problems, in another order than `problem_creators`
+    let json = r#"{
+        "problem": "RootFind",
+        "output_dir": "out",
+        "forms": [
+            ["RootFind", [["f", "x-0.25"], ["method", "newton"]]],
+            ["FredholmFirst", [["n", "7"]]]
+        ]
+    }"#;
+    let path = std::env::temp_dir().join("prac_session_by_name_test.json");
+    std::fs::write(&path, json).unwrap();
+
+    let mut state = AppState::default();
+    state.set_problem(ProblemName::Spline);
+    state.set_field("lambda", "3".to_string());
+    state.load_session(&path).unwrap();
+
+    assert_eq!(state.get_cur_problem(), Some(ProblemName::RootFind));
+    assert!(state.fields().any(|field| field == ("f", "x-0.25")));
+    state.set_problem(ProblemName::FredholmFirst);
+    assert!(state.fields().any(|field| field == ("n", "7")));
+    state.set_problem(ProblemName::Spline);
+    assert!(state.fields().any(|field| field == ("lambda", "0")));
+
+    std::fs::write(&path, json.replace("\"RootFind\",\n", "\"Unknown\",\n")).unwrap();
+    assert!(state.load_session(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
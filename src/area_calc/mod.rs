@@ -1,10 +1,15 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, str::FromStr};
 
+mod midpoint_integrator;
+mod newton_root;
 mod secant_method_root;
 mod simpson_integrator;
 
-use crate::functions::function::Function;
-use secant_method_root::root;
+use crate::{functions::function::Function, iteration_limit::IterationLimit};
+use midpoint_integrator::integrate_open_step;
+pub use newton_root::newton_root;
+use secant_method_root::count_crossings;
+pub use secant_method_root::root;
 use simpson_integrator::integrate_step;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,14 +19,48 @@ pub enum RootError {
     ItersEnded { from: f64, to: f64 },
 }
 
+/// How `RootFindProblem` looks for the root, `Newton` needs the derivative of `f`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootMethod {
+    Secant,
+    Newton,
+}
+
+impl FromStr for RootMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "secant" => Ok(RootMethod::Secant),
+            "newton" => Ok(RootMethod::Newton),
+            _ => Err(format!("{s} - expected secant or newton")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     FunctionError(String),
     RootError(String),
     ItersEnded,
-    RootEpsTooBig,
+    BadBracket(String),
+    /// Curves `f{which.0}` and `f{which.1}` (numbered from 1) do not cross in their bracket.
+    NoIntersection {
+        which: (usize, usize),
+    },
+}
+
+/// `Closed` integrates each side with Simpson's rule,
+/// `Open` uses the midpoint rule, which never evaluates the curves at the roots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewtonCotes {
+    Closed,
+    Open,
 }
 
+/// How many points each root bracket is checked at for the number of intersections.
+const BRACKET_SAMPLES: usize = 100;
+
 pub struct Area<'a, E> {
     pub area: f64,
     pub x12: f64,
@@ -30,6 +69,9 @@ pub struct Area<'a, E> {
     pub f1: &'a dyn Function<Error = E>,
     pub f2: &'a dyn Function<Error = E>,
     pub f3: &'a dyn Function<Error = E>,
+    /// The triangle corners at `x12`, `x13` and `x23`.
+    pub vertices: [(f64, f64); 3],
+    pub warning: Option<String>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -42,187 +84,172 @@ pub fn calc_area<'a, E>(
     bc_root: [f64; 2],
     root_start_eps: f64,
     area_eps: f64,
-    max_iter_count: usize,
+    rule: NewtonCotes,
+    limit: IterationLimit,
 ) -> Result<Area<'a, E>, Error>
 where
     E: Debug,
 {
-    let mut root_eps = root_start_eps;
-
-    for _ in 0..max_iter_count {
-        let (abx, aby) = root(a, b, ab_root[0], ab_root[1], root_eps, max_iter_count)
-            .map_err(|e| Error::RootError(format!("{:?}", e)))?;
-        let (acx, acy) = root(a, c, ac_root[0], ac_root[1], root_eps, max_iter_count)
-            .map_err(|e| Error::RootError(format!("{:?}", e)))?;
-        let (bcx, bcy) = root(b, c, bc_root[0], bc_root[1], root_eps, max_iter_count)
-            .map_err(|e| Error::RootError(format!("{:?}", e)))?;
-
-        let mut sides = [(abx, aby, c), (acx, acy, b), (bcx, bcy, a)];
-        sides.sort_by(|(a, _, _), (b, _, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-
-        let slope1 = (sides[1].1 - sides[0].1) / (sides[1].0 - sides[0].0);
-        let slope2 = (sides[2].1 - sides[0].1) / (sides[2].0 - sides[0].0);
-
-        let res = if slope1 > slope2 {
-            calc_area_top_triangle(sides, root_eps, area_eps, max_iter_count)
-        } else {
-            calc_area_bottom_triangle(sides, root_eps, area_eps, max_iter_count)
-        };
-
-        match res {
-            Ok(area) => {
-                return Ok(Area {
-                    area,
-                    x12: sides[0].0,
-                    x13: sides[1].0,
-                    x23: sides[2].0,
-                    f1: sides[2].2,
-                    f2: sides[1].2,
-                    f3: sides[0].2,
-                })
-            }
-            Err(e) if e == Error::RootEpsTooBig || e == Error::ItersEnded => root_eps *= 0.1,
-            Err(e) => return Err(e),
-        }
-    }
+    let polygon = calc_area_polygon(
+        &[a, b, c],
+        &[ab_root, bc_root, ac_root],
+        root_start_eps,
+        area_eps,
+        rule,
+        limit,
+    )?;
+    let [(abx, aby), (bcx, bcy), (acx, acy)] = [0, 1, 2].map(|i| polygon.vertices[i]);
+
+    let mut sides = [(abx, aby, c), (acx, acy, b), (bcx, bcy, a)];
+    sides.sort_by(|(a, _, _), (b, _, _)| a.total_cmp(b));
+
+    Ok(Area {
+        area: polygon.area,
+        x12: sides[0].0,
+        x13: sides[1].0,
+        x23: sides[2].0,
+        f1: sides[2].2,
+        f2: sides[1].2,
+        f3: sides[0].2,
+        vertices: sides.map(|(x, y, _)| (x, y)),
+        warning: (sides[2].0 - sides[0].0 < 2.0 * root_start_eps).then(|| {
+            format!(
+                "the curves intersect at nearly the same point x = {:.4}",
+                sides[1].0
+            )
+        }),
+    })
+}
 
-    Err(Error::ItersEnded)
+pub struct PolygonArea {
+    pub area: f64,
+    /// `vertices[i]` is where `curves[i]` meets the next curve.
+    pub vertices: Vec<(f64, f64)>,
 }
 
-fn calc_area_top_triangle<E>(
-    sides: [(f64, f64, &dyn Function<Error = E>); 3],
+/// Area of the region bounded by `curves`, going around it in order:
+/// `curves[i]` runs from where it meets `curves[i - 1]` to where it meets `curves[i + 1]`
+/// (the last curve meets the first), and `brackets[i]` holds the latter intersection.
+/// The area is the absolute value of the sum of the integrals along each side,
+/// so it does not matter which way around the curves go.
+pub fn calc_area_polygon<E>(
+    curves: &[&dyn Function<Error = E>],
+    brackets: &[[f64; 2]],
     root_eps: f64,
     area_eps: f64,
-    max_iter_count: usize,
-) -> Result<f64, Error>
+    rule: NewtonCotes,
+    limit: IterationLimit,
+) -> Result<PolygonArea, Error>
 where
     E: Debug,
 {
-    let mut max_cache0 = vec![];
-    let mut max_cache1 = vec![];
-    let mut max_cache2 = vec![];
-    let mut min_cache0 = vec![];
-    let mut min_cache1 = vec![];
-    let mut min_cache2 = vec![];
-
-    let mut max_n0 = 0;
-    let mut max_n1 = 0;
-    let mut max_n2 = 0;
-    let mut min_n0 = 0;
-    let mut min_n1 = 0;
-    let mut min_n2 = 0;
-
-    let a = sides[0].0;
-    let b = sides[1].0;
-    let c = sides[2].0;
-    let f2 = sides[0].2;
-    let f3 = sides[1].2;
-    let f1 = sides[2].2;
-
-    let mut calc_smax = || -> Result<f64, Error> {
-        Ok(
-            integrate_step(f1, a - root_eps, b + root_eps, &mut max_n0, &mut max_cache0)?
-                + integrate_step(f2, b - root_eps, c + root_eps, &mut max_n1, &mut max_cache1)?
-                - integrate_step(f3, a + root_eps, c - root_eps, &mut min_n2, &mut min_cache2)?,
-        )
-    };
-
-    let mut calc_smin = || -> Result<f64, Error> {
-        Ok(
-            integrate_step(f1, a + root_eps, b - root_eps, &mut min_n0, &mut min_cache0)?
-                + integrate_step(f2, b + root_eps, c - root_eps, &mut min_n1, &mut min_cache1)?
-                - integrate_step(f3, a - root_eps, c + root_eps, &mut max_n2, &mut max_cache2)?,
-        )
-    };
+    let n = curves.len();
+    if n < 2 || brackets.len() != n {
+        return Err(Error::BadBracket(format!(
+            "expected a bracket for each of the {n} curves, got {}",
+            brackets.len()
+        )));
+    }
 
-    let mut smax_prev = calc_smax()?;
-    let mut smin_prev = calc_smin()?;
+    let mut vertices = vec![];
+    for (i, bracket) in brackets.iter().enumerate() {
+        let (f, g) = (curves[i], curves[(i + 1) % n]);
+        let which = (
+            usize::min(i + 1, (i + 1) % n + 1),
+            usize::max(i + 1, (i + 1) % n + 1),
+        );
+        let crossings = count_crossings(f, g, bracket[0], bracket[1], BRACKET_SAMPLES)
+            .map_err(|e| Error::RootError(format!("{:?}", e)))?;
+        if crossings == 0 {
+            return Err(Error::NoIntersection { which });
+        }
+        if crossings > 1 {
+            return Err(Error::BadBracket(format!(
+                "f{}, f{} cross {crossings} times on [{}, {}], expected exactly once",
+                which.0, which.1, bracket[0], bracket[1]
+            )));
+        }
 
-    for _ in 0..max_iter_count {
-        let smax = calc_smax()?;
-        let smin = calc_smin()?;
+        let vertex = root(f, g, bracket[0], bracket[1], root_eps, limit)
+            .map_err(|e| Error::RootError(format!("{:?}", e)))?;
+        vertices.push(vertex);
+    }
 
-        if (smax - smin).abs() > area_eps {
-            return Err(Error::RootEpsTooBig);
+    // side i goes from vertex i - 1 to vertex i
+    let sides = (0..n)
+        .map(|i| (curves[i], vertices[(i + n - 1) % n].0, vertices[i].0))
+        .collect::<Vec<_>>();
+    let mut steps = vec![0; n];
+    let mut sums = vec![0.0; n];
+    let mut caches = vec![vec![]; n];
+    let mut calc_s = || -> Result<f64, Error> {
+        let mut s = 0.0;
+        for (i, (f, start, end)) in sides.iter().enumerate() {
+            let (from, to) = (start.min(*end), start.max(*end));
+            let integral = match rule {
+                NewtonCotes::Closed => integrate_step(*f, from, to, &mut steps[i], &mut caches[i])?,
+                NewtonCotes::Open => {
+                    integrate_open_step(*f, from, to, &mut steps[i], &mut sums[i])?
+                }
+            };
+            s += if start <= end { integral } else { -integral };
         }
+        Ok(s.abs())
+    };
 
-        if (smax - smax_prev).abs() < area_eps && (smin - smin_prev).abs() < area_eps {
-            return Ok((smax + smin) / 2.0);
+    let mut s_prev = calc_s()?;
+    for _ in 0..limit.max {
+        let s = calc_s()?;
+        if (s - s_prev).abs() < area_eps {
+            return Ok(PolygonArea { area: s, vertices });
         }
-
-        smax_prev = smax;
-        smin_prev = smin;
+        s_prev = s;
     }
 
-    Err(Error::ItersEnded)
+    limit.exceeded(
+        PolygonArea {
+            area: s_prev,
+            vertices,
+        },
+        Error::ItersEnded,
+    )
 }
 
-fn calc_area_bottom_triangle<E>(
-    sides: [(f64, f64, &dyn Function<Error = E>); 3],
-    root_eps: f64,
-    area_eps: f64,
-    max_iter_count: usize,
-) -> Result<f64, Error>
-where
-    E: Debug,
-{
-    let mut max_cache0 = vec![];
-    let mut max_cache1 = vec![];
-    let mut max_cache2 = vec![];
-    let mut min_cache0 = vec![];
-    let mut min_cache1 = vec![];
-    let mut min_cache2 = vec![];
-
-    let mut max_n0 = 0;
-    let mut max_n1 = 0;
-    let mut max_n2 = 0;
-    let mut min_n0 = 0;
-    let mut min_n1 = 0;
-    let mut min_n2 = 0;
-
-    let a = sides[0].0;
-    let b = sides[1].0;
-    let c = sides[2].0;
-    let f3 = sides[0].2;
-    let f1 = sides[1].2;
-    let f2 = sides[2].2;
-
-    let mut calc_smax = || -> Result<f64, Error> {
-        Ok(
-            integrate_step(f1, a - root_eps, c + root_eps, &mut max_n0, &mut max_cache0)?
-                - integrate_step(f2, a + root_eps, b - root_eps, &mut min_n1, &mut min_cache1)?
-                - integrate_step(f3, b + root_eps, c - root_eps, &mut min_n2, &mut min_cache2)?,
-        )
-    };
-
-    let mut calc_smin = || -> Result<f64, Error> {
-        Ok(
-            integrate_step(f1, a + root_eps, c - root_eps, &mut min_n0, &mut min_cache0)?
-                - integrate_step(f2, a - root_eps, b + root_eps, &mut max_n1, &mut max_cache1)?
-                - integrate_step(f3, b - root_eps, c + root_eps, &mut max_n2, &mut max_cache2)?,
+#[test]
+fn area_no_intersection() {
+    let f = |x: f64| -> Result<f64, RootError> { Ok(f64::exp(x) + 2.0) };
+    let g = |x: f64| -> Result<f64, RootError> { Ok(-2.0 * x + 8.0) };
+    let h = |x: f64| -> Result<f64, RootError> { Ok(-5.0 / x) };
+    let calc = |x12, x13, x23| {
+        calc_area(
+            &f,
+            &g,
+            &h,
+            x12,
+            x13,
+            x23,
+            0.001,
+            0.0001,
+            NewtonCotes::Closed,
+            1000.into(),
         )
+        .err()
     };
 
-    let mut smax_prev = calc_smax()?;
-    let mut smin_prev = calc_smin()?;
-
-    for _ in 0..max_iter_count {
-        let smax = calc_smax()?;
-        let smin = calc_smin()?;
-
-        if (smax - smin).abs() > area_eps {
-            return Err(Error::RootEpsTooBig);
-        }
-
-        if (smax - smax_prev).abs() < area_eps && (smin - smin_prev).abs() < area_eps {
-            return Ok((smax + smin) / 2.0);
-        }
-
-        smax_prev = smax;
-        smin_prev = smin;
-    }
-
-    Err(Error::ItersEnded)
+    // f and g cross at about 1.1, well to the right of [-3, -2]
+    assert_eq!(
+        calc([-3.0, -2.0], [-4.0, -1.0], [-2.0, -0.1]),
+        Some(Error::NoIntersection { which: (1, 2) })
+    );
+    assert_eq!(
+        calc([0.0, 2.0], [-4.0, -1.0], [1.0, 2.0]),
+        Some(Error::NoIntersection { which: (2, 3) })
+    );
+    assert_eq!(
+        calc([0.0, 2.0], [-0.9, -0.1], [-2.0, -0.1]),
+        Some(Error::NoIntersection { which: (1, 3) })
+    );
+    assert!(calc([0.0, 2.0], [-4.0, -1.0], [-2.0, -0.1]).is_none());
 }
 
 #[test]
@@ -231,44 +258,221 @@ fn area_bottom() -> Result<(), Error> {
     let g = |x: f64| -> Result<f64, RootError> { Ok(2.0f64.powf(-x)) };
     let h = |x: f64| -> Result<f64, RootError> { Ok(x * x * x) };
 
+    let actual = 6.5910711;
+    for rule in [NewtonCotes::Closed, NewtonCotes::Open] {
+        let res = calc_area(
+            &f,
+            &g,
+            &h,
+            [-2.0, -1.0],
+            [0.5, 1.5],
+            [0.5, 1.5],
+            0.001,
+            0.001,
+            rule,
+            1000.into(),
+        )?;
+        assert!((res.area - actual).abs() < 0.001);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn area_top() -> Result<(), Error> {
+    let f = |x: f64| -> Result<f64, RootError> { Ok(f64::exp(x) + 2.0) };
+    let g = |x: f64| -> Result<f64, RootError> { Ok(-2.0 * x + 8.0) };
+    let h = |x: f64| -> Result<f64, RootError> { Ok(-5.0 / x) };
+
     let res = calc_area(
         &f,
         &g,
         &h,
-        [-2.0, -1.0],
-        [0.5, 1.5],
-        [0.5, 1.5],
-        0.001,
+        [0.0, 2.0],
+        [-4.0, -1.0],
+        [-2.0, -0.1],
         0.001,
-        1000,
+        0.0001,
+        NewtonCotes::Closed,
+        1000.into(),
     )?;
 
-    let actual = 6.5910711;
+    let actual = 9.807;
     assert!((res.area - actual).abs() < 0.001);
 
+    // each corner lies on both curves that meet there
+    let [(x12, y12), (x13, y13), (x23, y23)] = res.vertices;
+    assert_eq!([x12, x13, x23], [res.x12, res.x13, res.x23]);
+    for (y, expected) in [
+        (y12, res.f1.apply(x12).unwrap()),
+        (y12, res.f2.apply(x12).unwrap()),
+        (y13, res.f1.apply(x13).unwrap()),
+        (y13, res.f3.apply(x13).unwrap()),
+        (y23, res.f2.apply(x23).unwrap()),
+        (y23, res.f3.apply(x23).unwrap()),
+    ] {
+        assert!((y - expected).abs() < 1e-3, "{:?}", res.vertices);
+    }
+
     Ok(())
 }
 
 #[test]
-fn area_top() -> Result<(), Error> {
+fn area_limit() -> Result<(), Error> {
+    let f = |x: f64| -> Result<f64, RootError> { Ok(f64::exp(x) + 2.0) };
+    let g = |x: f64| -> Result<f64, RootError> { Ok(-2.0 * x + 8.0) };
+    let h = |x: f64| -> Result<f64, RootError> { Ok(-5.0 / x) };
+
+    let calc = |limit| {
+        calc_area(
+            &f,
+            &g,
+            &h,
+            [0.0, 2.0],
+            [-4.0, -1.0],
+            [-2.0, -0.1],
+            0.001,
+            0.0001,
+            NewtonCotes::Closed,
+            limit,
+        )
+    };
+
+    // the -5/x crossing needs more than 5 root iterations, but less than 20
+    assert!(calc(IterationLimit::new(5)).is_err());
+    assert!(calc(IterationLimit::new(20)).is_ok());
+
+    let res = calc(IterationLimit::return_best(20))?;
+    let actual = 9.807;
+    assert!((res.area - actual).abs() < 0.01);
+
+    Ok(())
+}
+
+#[test]
+fn area_open_rule() -> Result<(), Error> {
     let f = |x: f64| -> Result<f64, RootError> { Ok(f64::exp(x) + 2.0) };
     let g = |x: f64| -> Result<f64, RootError> { Ok(-2.0 * x + 8.0) };
     let h = |x: f64| -> Result<f64, RootError> { Ok(-5.0 / x) };
 
+    let calc = |rule| {
+        calc_area(
+            &f,
+            &g,
+            &h,
+            [0.0, 2.0],
+            [-4.0, -1.0],
+            [-2.0, -0.1],
+            0.001,
+            0.0001,
+            rule,
+            1000.into(),
+        )
+    };
+
+    let open = calc(NewtonCotes::Open)?.area;
+    let closed = calc(NewtonCotes::Closed)?.area;
+
+    assert!((open - 9.807).abs() < 0.01);
+    assert!((open - closed).abs() < 0.01);
+
+    Ok(())
+}
+
+#[test]
+fn area_concurrent() -> Result<(), Error> {
+    let f = |x: f64| -> Result<f64, RootError> { Ok(x) };
+    let g = |x: f64| -> Result<f64, RootError> { Ok(2.0 - x) };
+    let h = |x: f64| -> Result<f64, RootError> { Ok(0.5 * x + 0.5) };
+
+    for rule in [NewtonCotes::Closed, NewtonCotes::Open] {
+        let res = calc_area(
+            &f,
+            &g,
+            &h,
+            [0.0, 2.0],
+            [0.0, 2.0],
+            [0.0, 2.0],
+            0.001,
+            0.0001,
+            rule,
+            1000.into(),
+        )?;
+
+        assert!(res.area.is_finite() && res.area < 1e-6);
+        assert!(res.warning.is_some());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn area_bad_bracket() {
+    let f = |x: f64| -> Result<f64, RootError> { Ok(x * x) };
+    let g = |_: f64| -> Result<f64, RootError> { Ok(1.0) };
+    let h = |x: f64| -> Result<f64, RootError> { Ok(x + 5.0) };
+
     let res = calc_area(
         &f,
         &g,
         &h,
-        [0.0, 2.0],
-        [-4.0, -1.0],
-        [-2.0, -0.1],
+        [-2.0, 2.0],
+        [-3.0, 0.0],
+        [-5.0, -3.0],
         0.001,
         0.0001,
-        1000,
+        NewtonCotes::Closed,
+        1000.into(),
+    );
+
+    match res.err() {
+        Some(Error::BadBracket(msg)) => assert!(msg.starts_with("f1, f2 cross 2 times")),
+        e => panic!("expected BadBracket, got {:?}", e),
+    }
+}
+
+#[test]
+fn area_polygon() -> Result<(), Error> {
+    // area_bottom
+    let f = |x: f64| -> Result<f64, RootError> { Ok(1.0 + 4.0 / (x * x + 1.0)) };
+    let g = |x: f64| -> Result<f64, RootError> { Ok(2.0f64.powf(-x)) };
+    let h = |x: f64| -> Result<f64, RootError> { Ok(x * x * x) };
+    let res = calc_area_polygon(
+        &[&f, &g, &h],
+        &[[-2.0, -1.0], [0.5, 1.5], [0.5, 1.5]],
+        0.001,
+        0.001,
+        NewtonCotes::Closed,
+        1000.into(),
     )?;
+    assert!((res.area - 6.5910711).abs() < 0.001);
+    assert_eq!(res.vertices.len(), 3);
 
-    let actual = 9.807;
-    assert!((res.area - actual).abs() < 0.001);
+    // area_top, going around the other way
+    let f = |x: f64| -> Result<f64, RootError> { Ok(f64::exp(x) + 2.0) };
+    let g = |x: f64| -> Result<f64, RootError> { Ok(-2.0 * x + 8.0) };
+    let h = |x: f64| -> Result<f64, RootError> { Ok(-5.0 / x) };
+    let res = calc_area_polygon(
+        &[&f, &h, &g],
+        &[[-4.0, -1.0], [-2.0, -0.1], [0.0, 2.0]],
+        0.001,
+        0.0001,
+        NewtonCotes::Closed,
+        1000.into(),
+    )?;
+    assert!((res.area - 9.807).abs() < 0.001);
+
+    assert!(matches!(
+        calc_area_polygon(
+            &[&f, &h, &g],
+            &[[-4.0, -1.0]],
+            0.001,
+            0.0001,
+            NewtonCotes::Closed,
+            1000.into()
+        ),
+        Err(Error::BadBracket(_))
+    ));
 
     Ok(())
 }
@@ -0,0 +1,74 @@
+use std::fmt::Debug;
+
+use crate::{functions::function::Function, iteration_limit::IterationLimit};
+
+use super::{secant_method_root::root, RootError};
+
+/// Newton's method for `f(x) = 0` starting at `x0`, `df` is the derivative of `f`.
+/// When `df` vanishes, a step leaves `bracket` or the iterations run out,
+/// the root is searched for with the secant method on `bracket` instead.
+pub fn newton_root<E>(
+    f: &dyn Function<Error = E>,
+    df: &dyn Function<Error = E>,
+    x0: f64,
+    bracket: [f64; 2],
+    eps: f64,
+    limit: IterationLimit,
+) -> Result<f64, RootError>
+where
+    E: Debug,
+{
+    let (from, to) = (bracket[0].min(bracket[1]), bracket[0].max(bracket[1]));
+    let mut x = x0;
+
+    for _ in 0..limit.max {
+        let y = f
+            .apply(x)
+            .map_err(|e| RootError::FunctionError(format!("{:?}", e)))?;
+        if y == 0.0 {
+            return Ok(x);
+        }
+        let dy = df
+            .apply(x)
+            .map_err(|e| RootError::FunctionError(format!("{:?}", e)))?;
+        if dy == 0.0 || !dy.is_finite() {
+            break;
+        }
+
+        let next = x - y / dy;
+        if !(from..=to).contains(&next) {
+            break;
+        }
+        if (next - x).abs() < eps {
+            return Ok(next);
+        }
+        x = next;
+    }
+
+    let zero = |_: f64| -> Result<f64, E> { Ok(0.0) };
+    root(f, &zero, from, to, eps, limit).map(|(x, _)| x)
+}
+
+#[test]
+fn newton() -> Result<(), RootError> {
+    use crate::mathparse::{parse, DefaultRuntime};
+
+    let f = |x: f64| -> Result<f64, RootError> { Ok(x * x - 2.0) };
+    let df = |x: f64| -> Result<f64, RootError> { Ok(2.0 * x) };
+    let x = newton_root(&f, &df, 1.0, [0.0, 2.0], 1e-12, 100.into())?;
+    assert!((x - 2.0f64.sqrt()).abs() < 1e-12);
+
+    // df(0) = 0, so the secant method has to take over
+    let x = newton_root(&f, &df, 0.0, [0.0, 2.0], 1e-12, 100.into())?;
+    assert!((x - 2.0f64.sqrt()).abs() < 1e-9);
+
+    let runtime = DefaultRuntime::default();
+    let expr = parse("cos(x)-x", &runtime).unwrap();
+    let derivative = expr.derivative("x").unwrap();
+    let f = |x| expr.eval(&DefaultRuntime::new(&[("x", x)]));
+    let df = |x| derivative.eval(&DefaultRuntime::new(&[("x", x)]));
+    let x = newton_root(&f, &df, 0.5, [0.0, 1.0], 1e-12, 100.into()).unwrap();
+    assert!((x - 0.739085133215161).abs() < 1e-12);
+
+    Ok(())
+}
@@ -1,16 +1,45 @@
 use std::fmt::Debug;
 
-use crate::functions::function::Function;
+use crate::{functions::function::Function, iteration_limit::IterationLimit};
 
 use super::RootError;
 
+/// Counts where `f - g` crosses zero on `[from, to]` by sampling it at `n + 1` points.
+pub fn count_crossings<E>(
+    f: &dyn Function<Error = E>,
+    g: &dyn Function<Error = E>,
+    from: f64,
+    to: f64,
+    n: usize,
+) -> Result<usize, RootError>
+where
+    E: Debug,
+{
+    let diff = |x| f.apply(x).and_then(|f| g.apply(x).map(|g| f - g));
+    let diff = diff
+        .sample(from, to, n)
+        .map_err(|e| RootError::FunctionError(format!("{:?}", e)))?;
+
+    let ends = [diff[0].1, diff[n].1].iter().filter(|y| **y == 0.0).count();
+    let changes = diff
+        .iter()
+        .map(|(_, y)| *y)
+        .filter(|y| *y != 0.0)
+        .collect::<Vec<_>>()
+        .windows(2)
+        .filter(|w| (w[0] > 0.0) != (w[1] > 0.0))
+        .count();
+
+    Ok(ends + changes)
+}
+
 pub fn root<E>(
     f: &dyn Function<Error = E>,
     g: &dyn Function<Error = E>,
     from: f64,
     to: f64,
     eps: f64,
-    max_iter_count: usize,
+    limit: IterationLimit,
 ) -> Result<(f64, f64), RootError>
 where
     E: Debug,
@@ -46,14 +75,33 @@ where
         return Err(RootError::BadRange(a, b));
     }
 
-    for _ in 0..max_iter_count {
+    // the last two iterates, secant steps go through them
+    let (mut prev, mut f_prev) = (a, f_a);
+    let (mut cur, mut f_cur) = (b, f_b);
+    let mut width = (b - a).abs();
+    let mut steps_since_halved = 0;
+
+    for _ in 0..limit.max {
         if a == b || f_a * f_b > 0.0 {
             return Err(RootError::BadRange(a, b));
         }
 
-        let c = (a * f_b - b * f_a) / (f_b - f_a);
+        // A secant step that leaves the bracket or divides by almost zero is replaced
+        // with bisection, as is every step once the bracket stops halving.
+        let denom = f_cur - f_prev;
+        let secant = cur - f_cur * (cur - prev) / denom;
+        let c = if denom.abs() > f64::EPSILON * f_cur.abs().max(f_prev.abs())
+            && secant > a.min(b)
+            && secant < a.max(b)
+            && steps_since_halved < 3
+        {
+            secant
+        } else {
+            (a + b) * 0.5
+        };
+
         let f_c = f(c).map_err(|e| RootError::FunctionError(format!("{:?}", e)))?;
-        if f_c == 0.0 {
+        if f_c == 0.0 || (f_c.abs() < eps && ((c - cur).abs() < eps || (b - a).abs() < eps)) {
             return Ok((
                 c,
                 g.apply(c)
@@ -62,29 +110,32 @@ where
         }
 
         if f_c > 0.0 {
-            if (c - b).abs() < eps && f_c.abs() < eps {
-                return Ok((
-                    c,
-                    g.apply(c)
-                        .map_err(|e| RootError::FunctionError(format!("{:?}", e)))?,
-                ));
-            }
             b = c;
             f_b = f_c;
         } else {
-            if (a - c).abs() < eps && f_c.abs() < eps {
-                return Ok((
-                    c,
-                    g.apply(c)
-                        .map_err(|e| RootError::FunctionError(format!("{:?}", e)))?,
-                ));
-            }
             a = c;
             f_a = f_c;
         }
+        (prev, f_prev) = (cur, f_cur);
+        (cur, f_cur) = (c, f_c);
+
+        if (b - a).abs() <= width * 0.5 {
+            width = (b - a).abs();
+            steps_since_halved = 0;
+        } else {
+            steps_since_halved += 1;
+        }
     }
 
-    Err(RootError::ItersEnded { from: a, to: b })
+    let best = if f_a.abs() < f_b.abs() { a } else { b };
+    limit.exceeded(
+        (
+            best,
+            g.apply(best)
+                .map_err(|e| RootError::FunctionError(format!("{:?}", e)))?,
+        ),
+        RootError::ItersEnded { from: a, to: b },
+    )
 }
 
 #[test]
@@ -97,10 +148,33 @@ fn secant() -> Result<(), RootError> {
         ))
     };
 
-    let (x, _) = root(&f, &g, 0.0, 2.0, 0.0001, 10000)?;
+    let (x, _) = root(&f, &g, 0.0, 2.0, 0.0001, 10000.into())?;
     let actual_x = 1.182;
 
     assert!((x - actual_x).abs() < 0.001);
 
     Ok(())
 }
+
+#[test]
+fn secant_falls_back_to_bisection() -> Result<(), RootError> {
+    let f = |x: f64| -> Result<f64, RootError> { Ok(x.atan()) };
+    let zero = |_: f64| -> Result<f64, RootError> { Ok(0.0) };
+
+    // plain secant steps from the ends of the range run away from the root
+    let (mut x0, mut x1) = (-5.0f64, 20.0f64);
+    for _ in 0..5 {
+        (x0, x1) = (x1, x1 - x1.atan() * (x1 - x0) / (x1.atan() - x0.atan()));
+    }
+    assert!(x1.abs() > 20.0, "{x1}");
+
+    let (x, _) = root(&f, &zero, -5.0, 20.0, 1e-12, 100.into())?;
+    assert!(x.abs() < 1e-12, "{x}");
+
+    // a slowly bending function that keeps plain regula falsi at one end
+    let f = |x: f64| -> Result<f64, RootError> { Ok(x.powi(9) - 1e-3) };
+    let (x, _) = root(&f, &zero, 0.0, 2.0, 1e-12, 200.into())?;
+    assert!((x - 1e-3f64.powf(1.0 / 9.0)).abs() < 1e-9, "{x}");
+
+    Ok(())
+}
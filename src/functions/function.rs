@@ -1,4 +1,11 @@
-use std::fmt::Write;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fmt::Write,
+    fs::File,
+    io::BufWriter,
+    path::Path,
+};
 
 pub trait Function {
     type Error;
@@ -16,17 +23,399 @@ pub trait Function {
     }
 
     fn sample(&self, from: f64, to: f64, n: usize) -> Result<Vec<(f64, f64)>, Self::Error> {
+        let mut pts = Vec::with_capacity(n + 1);
+        self.sample_into(from, to, n, &mut pts)?;
+        Ok(pts)
+    }
+
+    /// Like `sample`, but refills `out` so its allocation can be reused.
+    /// On error `out` holds the points before the failing one.
+    fn sample_into(
+        &self,
+        from: f64,
+        to: f64,
+        n: usize,
+        out: &mut Vec<(f64, f64)>,
+    ) -> Result<(), Self::Error> {
+        out.clear();
         let step = (to - from) / (n as f64);
+        for x in (0..=n).map(|i| (i as f64) * step + from) {
+            out.push((x, self.apply(x)?));
+        }
+        Ok(())
+    }
+
+    /// Starts from `sample(from, to, 8)` and halves the segments whose midpoint
+    /// is further than `tol` from the chord, widest first, until there are `max_points` points.
+    /// The points are sorted by `x`.
+    fn sample_adaptive(
+        &self,
+        from: f64,
+        to: f64,
+        tol: f64,
+        max_points: usize,
+    ) -> Result<Vec<(f64, f64)>, Self::Error> {
+        let mut pts = self.sample(from, to, 8)?;
+        let mut segments = pts
+            .windows(2)
+            .map(|w| (w[0], w[1]))
+            .collect::<VecDeque<_>>();
+
+        while let Some(((x0, y0), (x1, y1))) = segments.pop_front() {
+            if pts.len() >= max_points {
+                break;
+            }
+            let x = (x0 + x1) / 2.0;
+            let y = self.apply(x)?;
+            pts.push((x, y));
+            if (y - (y0 + y1) / 2.0).abs() > tol {
+                segments.push_back(((x0, y0), (x, y)));
+                segments.push_back(((x, y), (x1, y1)));
+            }
+        }
+
+        pts.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(pts)
+    }
+
+    /// Like `sample`, but the points are evenly spaced in `ln(x)`, so there are more of them near `from`.
+    fn sample_log(
+        &self,
+        from: f64,
+        to: f64,
+        n: usize,
+    ) -> Result<Vec<(f64, f64)>, SampleError<Self::Error>> {
+        if from <= 0.0 || to <= 0.0 {
+            return Err(SampleError::NonPositiveRange);
+        }
+
+        let step = (to / from).ln() / (n as f64);
         (0..=n)
-            .map(|i| (i as f64) * step + from)
+            .map(|i| from * ((i as f64) * step).exp())
+            .map(|x| {
+                self.apply(x)
+                    .map(|y| (x, y))
+                    .map_err(SampleError::FunctionError)
+            })
+            .collect()
+    }
+
+    /// Like `sample`, but at `chebyshev_nodes(from, to, n)`.
+    fn sample_chebyshev(
+        &self,
+        from: f64,
+        to: f64,
+        n: usize,
+    ) -> Result<Vec<(f64, f64)>, Self::Error> {
+        chebyshev_nodes(from, to, n)
+            .into_iter()
             .map(|x| self.apply(x).map(|y| (x, y)))
             .collect()
     }
+
+    /// Writes `sample(from, to, n)` to `path` as `x,y` rows.
+    fn save_csv(&self, path: &Path, from: f64, to: f64, n: usize) -> Result<(), Self::Error>
+    where
+        Self::Error: From<std::io::Error>,
+    {
+        let pts = self.sample(from, to, n)?;
+        write_csv(path, &pts)?;
+        Ok(())
+    }
+
+    /// Central difference, the error is `O(h^2)`.
+    fn derivative(&self, x: f64, h: f64) -> Result<f64, Self::Error> {
+        Ok((self.apply(x + h)? - self.apply(x - h)?) / (2.0 * h))
+    }
+
+    /// Combines `derivative` at `h` and `h / 2`, the error is `O(h^4)`.
+    fn derivative_richardson(&self, x: f64, h: f64) -> Result<f64, Self::Error> {
+        let coarse = self.derivative(x, h)?;
+        let fine = self.derivative(x, h / 2.0)?;
+        Ok((4.0 * fine - coarse) / 3.0)
+    }
+
+    /// Central difference of the second order, the error is `O(h^2)`.
+    fn second_derivative(&self, x: f64, h: f64) -> Result<f64, Self::Error> {
+        Ok((self.apply(x + h)? - 2.0 * self.apply(x)? + self.apply(x - h)?) / (h * h))
+    }
+
+    /// Composite Simpson's rule on `n` intervals, an odd `n` is rounded up.
+    fn integrate(&self, from: f64, to: f64, n: usize) -> Result<f64, Self::Error> {
+        let n = n.max(2).div_ceil(2) * 2;
+        let step = (to - from) / (n as f64);
+        let inner = (1..n).try_fold(0.0, |acc, i| {
+            let weight = if i % 2 == 1 { 4.0 } else { 2.0 };
+            self.apply((i as f64) * step + from)
+                .map(|y| acc + weight * y)
+        })?;
+        Ok((self.apply(from)? + inner + self.apply(to)?) * step / 3.0)
+    }
+
+    /// Remembers every value computed, see `Cached`.
+    fn cached(self) -> Cached<Self>
+    where
+        Self: Sized,
+    {
+        Cached::new(self)
+    }
+
+    /// `derivative` with step `h` as a function of its own.
+    fn deriv(&self, h: f64) -> Derivative<'_, Self>
+    where
+        Self: Sized,
+    {
+        Derivative { f: self, h }
+    }
+
+    /// `self(g(x))`.
+    fn compose<G>(self, g: G) -> Compose<Self, G>
+    where
+        Self: Sized,
+        G: Function,
+    {
+        Compose { f: self, g }
+    }
+
+    /// `self(x) + g(x)`.
+    fn add<G>(self, g: G) -> Sum<Self, G>
+    where
+        Self: Sized,
+        G: Function,
+    {
+        Sum { f: self, g }
+    }
+
+    /// `self(x) * g(x)`.
+    fn mul<G>(self, g: G) -> Product<Self, G>
+    where
+        Self: Sized,
+        G: Function,
+    {
+        Product { f: self, g }
+    }
+
+    /// `k * self(x)`.
+    fn scale(self, k: f64) -> Scaled<Self>
+    where
+        Self: Sized,
+    {
+        Scaled { f: self, k }
+    }
+}
+
+/// Calls `f` at most once per argument, keyed on its bit pattern. Errors are not cached.
+pub struct Cached<F> {
+    f: F,
+    cache: RefCell<HashMap<u64, f64>>,
+}
+
+impl<F> Cached<F> {
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<F> Function for Cached<F>
+where
+    F: Function,
+{
+    type Error = F::Error;
+
+    fn apply(&self, x: f64) -> Result<f64, Self::Error> {
+        if let Some(y) = self.cache.borrow().get(&x.to_bits()) {
+            return Ok(*y);
+        }
+        let y = self.f.apply(x)?;
+        self.cache.borrow_mut().insert(x.to_bits(), y);
+        Ok(y)
+    }
+}
+
+/// `f'` through the central difference with step `h`, see `Function::deriv`.
+pub struct Derivative<'a, F: ?Sized> {
+    pub f: &'a F,
+    pub h: f64,
+}
+
+impl<'a, F> Function for Derivative<'a, F>
+where
+    F: Function + ?Sized,
+{
+    type Error = F::Error;
+
+    fn apply(&self, x: f64) -> Result<f64, Self::Error> {
+        self.f.derivative(x, self.h)
+    }
+}
+
+/// The error of a combinator over two functions: `Left` comes from the first one
+/// (the outer one for `Compose`), `Right` from the second.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+/// `f(g(x))`, see `Function::compose`.
+pub struct Compose<F, G> {
+    pub f: F,
+    pub g: G,
+}
+
+impl<F, G> Function for Compose<F, G>
+where
+    F: Function,
+    G: Function,
+{
+    type Error = Either<F::Error, G::Error>;
+
+    fn apply(&self, x: f64) -> Result<f64, Self::Error> {
+        let inner = self.g.apply(x).map_err(Either::Right)?;
+        self.f.apply(inner).map_err(Either::Left)
+    }
+}
+
+/// `f(x) + g(x)`, see `Function::add`.
+pub struct Sum<F, G> {
+    pub f: F,
+    pub g: G,
+}
+
+impl<F, G> Function for Sum<F, G>
+where
+    F: Function,
+    G: Function,
+{
+    type Error = Either<F::Error, G::Error>;
+
+    fn apply(&self, x: f64) -> Result<f64, Self::Error> {
+        Ok(self.f.apply(x).map_err(Either::Left)? + self.g.apply(x).map_err(Either::Right)?)
+    }
+}
+
+/// `f(x) * g(x)`, see `Function::mul`.
+pub struct Product<F, G> {
+    pub f: F,
+    pub g: G,
+}
+
+impl<F, G> Function for Product<F, G>
+where
+    F: Function,
+    G: Function,
+{
+    type Error = Either<F::Error, G::Error>;
+
+    fn apply(&self, x: f64) -> Result<f64, Self::Error> {
+        Ok(self.f.apply(x).map_err(Either::Left)? * self.g.apply(x).map_err(Either::Right)?)
+    }
+}
+
+/// `k * f(x)`, see `Function::scale`.
+pub struct Scaled<F> {
+    pub f: F,
+    pub k: f64,
+}
+
+impl<F> Function for Scaled<F>
+where
+    F: Function,
+{
+    type Error = F::Error;
+
+    fn apply(&self, x: f64) -> Result<f64, Self::Error> {
+        Ok(self.k * self.f.apply(x)?)
+    }
+}
+
+/// Writes `pts` to `path` as `x,y` rows, the format `TableFunction::from_file` reads.
+pub fn write_csv(path: &Path, pts: &[(f64, f64)]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut file = BufWriter::new(File::create(path)?);
+    for (x, y) in pts {
+        writeln!(file, "{},{}", x, y)?;
+    }
+    file.flush()
+}
+
+/// Samples the curve `(x(t), y(t))` at `n + 1` evenly spaced `t` from `from` to `to`.
+pub fn sample_parametric<E>(
+    x: &dyn Function<Error = E>,
+    y: &dyn Function<Error = E>,
+    from: f64,
+    to: f64,
+    n: usize,
+) -> Result<Vec<(f64, f64)>, E> {
+    let step = (to - from) / (n as f64);
+    (0..=n)
+        .map(|i| (i as f64) * step + from)
+        .map(|t| x.apply(t).and_then(|x| y.apply(t).map(|y| (x, y))))
+        .collect()
+}
+
+/// `n` Chebyshev nodes on `[from, to]`, in ascending order.
+/// They cluster towards the ends, which keeps high degree interpolation from oscillating there.
+pub fn chebyshev_nodes(from: f64, to: f64, n: usize) -> Vec<f64> {
+    (0..n)
+        .rev()
+        .map(|i| {
+            let t = ((2 * i + 1) as f64) * std::f64::consts::PI / ((2 * n) as f64);
+            (from + to) * 0.5 + (to - from) * 0.5 * t.cos()
+        })
+        .collect()
+}
+
+/// Central difference Jacobian of the map `x -> (f_0(x), .., f_m(x))` with step `h`.
+/// Row-major, `m` rows of `x.len()` entries, same layout the linear solvers take.
+pub fn jacobian<E>(map: &[&dyn FunctionNd<Error = E>], x: &[f64], h: f64) -> Result<Vec<f64>, E> {
+    let mut res = Vec::with_capacity(map.len() * x.len());
+    let mut pt = x.to_vec();
+
+    for f in map {
+        for j in 0..x.len() {
+            pt[j] = x[j] + h;
+            let right = f.apply(&pt)?;
+            pt[j] = x[j] - h;
+            let left = f.apply(&pt)?;
+            pt[j] = x[j];
+
+            res.push((right - left) / (2.0 * h));
+        }
+    }
+
+    Ok(res)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SampleError<E> {
+    TooFewPoints { x_n: usize, y_n: usize },
+    NonPositiveRange,
+    FunctionError(E),
 }
 
 pub trait Function2d {
     type Error;
     fn apply(&self, x: f64, y: f64) -> Result<f64, Self::Error>;
+
+    /// Central difference along x, the error is `O(h^2)`.
+    fn partial_x(&self, x: f64, y: f64, h: f64) -> Result<f64, Self::Error> {
+        Ok((self.apply(x + h, y)? - self.apply(x - h, y)?) / (2.0 * h))
+    }
+
+    /// Central difference along y, the error is `O(h^2)`.
+    fn partial_y(&self, x: f64, y: f64, h: f64) -> Result<f64, Self::Error> {
+        Ok((self.apply(x, y + h)? - self.apply(x, y - h)?) / (2.0 * h))
+    }
+
+    /// Samples the function on a `x_n` by `y_n` grid, both ends included.
+    /// At least 2 points are needed along each axis. If `from == to` along an axis,
+    /// every sample on that axis has the same coordinate.
+    #[allow(clippy::type_complexity)]
     fn sample(
         &self,
         from_x: f64,
@@ -35,7 +424,11 @@ pub trait Function2d {
         to_y: f64,
         x_n: usize,
         y_n: usize,
-    ) -> Result<Vec<(f64, f64, f64)>, Self::Error> {
+    ) -> Result<Vec<(f64, f64, f64)>, SampleError<Self::Error>> {
+        if x_n < 2 || y_n < 2 {
+            return Err(SampleError::TooFewPoints { x_n, y_n });
+        }
+
         let x_step = (to_x - from_x) / (x_n as f64 - 1.0);
         let y_step = (to_y - from_y) / (y_n as f64 - 1.0);
 
@@ -44,10 +437,48 @@ pub trait Function2d {
                 let x = ((i % x_n) as f64) * x_step + from_x;
                 let y = ((i / x_n) as f64) * y_step + from_y;
 
-                self.apply(x, y).map(|z| (x, y, z))
+                self.apply(x, y)
+                    .map(|z| (x, y, z))
+                    .map_err(SampleError::FunctionError)
             })
             .collect()
     }
+
+    /// Same grid as `sample`, but as the x axis, the y axis and a matrix
+    /// of `y_n` rows with `x_n` values each, `z[j][i] = f(x[i], y[j])`.
+    #[allow(clippy::type_complexity)]
+    fn sample_grid(
+        &self,
+        from_x: f64,
+        to_x: f64,
+        from_y: f64,
+        to_y: f64,
+        x_n: usize,
+        y_n: usize,
+    ) -> Result<(Vec<f64>, Vec<f64>, Vec<Vec<f64>>), SampleError<Self::Error>> {
+        if x_n < 2 || y_n < 2 {
+            return Err(SampleError::TooFewPoints { x_n, y_n });
+        }
+
+        let x_step = (to_x - from_x) / (x_n as f64 - 1.0);
+        let y_step = (to_y - from_y) / (y_n as f64 - 1.0);
+        let xs = (0..x_n)
+            .map(|i| (i as f64) * x_step + from_x)
+            .collect::<Vec<_>>();
+        let ys = (0..y_n)
+            .map(|j| (j as f64) * y_step + from_y)
+            .collect::<Vec<_>>();
+
+        let z = ys
+            .iter()
+            .map(|y| {
+                xs.iter()
+                    .map(|x| self.apply(*x, *y).map_err(SampleError::FunctionError))
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+        Ok((xs, ys, z))
+    }
 }
 
 pub trait FunctionNd {
@@ -136,3 +567,263 @@ impl Function2d for f64 {
         Ok(*self)
     }
 }
+
+#[test]
+fn sample_into_buffer() {
+    let f = |x: f64| -> Result<f64, NoError> { Ok(x * x - 1.0) };
+
+    let mut buf = vec![(100.0, 100.0); 50];
+    f.sample_into(-1.0, 2.0, 12, &mut buf).unwrap();
+    assert_eq!(buf, f.sample(-1.0, 2.0, 12).unwrap());
+
+    let capacity = buf.capacity();
+    f.sample_into(0.0, 1.0, 4, &mut buf).unwrap();
+    assert_eq!(buf, f.sample(0.0, 1.0, 4).unwrap());
+    assert_eq!(buf.capacity(), capacity);
+
+    let g = |x: f64| if x < 0.5 { Ok(x) } else { Err(x) };
+    assert_eq!(g.sample_into(0.0, 1.0, 4, &mut buf), Err(0.5));
+    assert_eq!(buf, vec![(0.0, 0.0), (0.25, 0.25)]);
+}
+
+#[test]
+fn partial_derivatives() {
+    let f = |x: f64, y: f64| -> Result<f64, NoError> { Ok(x * y) };
+    for (x, y) in [(0.0, 0.0), (1.5, -2.0), (-3.0, 0.25)] {
+        assert!((f.partial_x(x, y, 1e-4).unwrap() - y).abs() < 1e-9);
+        assert!((f.partial_y(x, y, 1e-4).unwrap() - x).abs() < 1e-9);
+    }
+
+    let g = |x: f64, y: f64| -> Result<f64, NoError> { Ok(x.sin() * y.exp()) };
+    assert!((g.partial_x(0.5, 1.0, 1e-5).unwrap() - 0.5f64.cos() * 1f64.exp()).abs() < 1e-8);
+    assert!((g.partial_y(0.5, 1.0, 1e-5).unwrap() - 0.5f64.sin() * 1f64.exp()).abs() < 1e-8);
+}
+
+#[test]
+fn sample_grid_matrix() {
+    let f = |x: f64, y: f64| -> Result<f64, NoError> { Ok(x + y) };
+
+    let (xs, ys, z) = f.sample_grid(0.0, 1.0, 10.0, 12.0, 3, 2).unwrap();
+    assert_eq!(xs, vec![0.0, 0.5, 1.0]);
+    assert_eq!(ys, vec![10.0, 12.0]);
+    assert_eq!(z.len(), 2);
+    assert!(z.iter().all(|row| row.len() == 3));
+    assert_eq!(z[0][0], 10.0);
+    assert_eq!(z[0][2], 11.0);
+    assert_eq!(z[1][0], 12.0);
+    assert_eq!(z[1][2], 13.0);
+
+    assert_eq!(
+        f.sample_grid(0.0, 1.0, 0.0, 1.0, 1, 5),
+        Err(SampleError::TooFewPoints { x_n: 1, y_n: 5 })
+    );
+}
+
+#[test]
+fn sample_2d_degenerate() {
+    let f = |x: f64, y: f64| -> Result<f64, NoError> { Ok(x + y) };
+
+    assert_eq!(
+        f.sample(0.0, 1.0, 0.0, 1.0, 1, 5),
+        Err(SampleError::TooFewPoints { x_n: 1, y_n: 5 })
+    );
+    assert_eq!(
+        f.sample(0.0, 1.0, 0.0, 1.0, 5, 0),
+        Err(SampleError::TooFewPoints { x_n: 5, y_n: 0 })
+    );
+
+    let pts = f.sample(2.0, 2.0, 0.0, 1.0, 3, 2).unwrap();
+    assert_eq!(pts.len(), 6);
+    assert!(pts.iter().all(|(x, _, _)| *x == 2.0));
+    assert_eq!(pts[5], (2.0, 1.0, 3.0));
+}
+
+#[test]
+fn sample_log_spacing() {
+    let f = |x: f64| -> Result<f64, NoError> { Ok(x) };
+
+    let pts = f.sample_log(0.001, 10.0, 40).unwrap();
+    assert_eq!(pts.len(), 41);
+    assert!((pts[0].0 - 0.001).abs() < 1e-12 && (pts[40].0 - 10.0).abs() < 1e-9);
+    assert!(pts.windows(3).all(|w| w[2].0 - w[1].0 > w[1].0 - w[0].0));
+    assert!(pts.iter().filter(|(x, _)| *x < 1.0).count() > 3 * pts.len() / 4 - 1);
+
+    assert_eq!(
+        f.sample_log(0.0, 1.0, 10),
+        Err(SampleError::NonPositiveRange)
+    );
+    assert_eq!(
+        f.sample_log(-1.0, 1.0, 10),
+        Err(SampleError::NonPositiveRange)
+    );
+}
+
+#[test]
+fn richardson_derivative() {
+    let f = |x: f64| -> Result<f64, NoError> { Ok(x.sin()) };
+
+    let (x, h) = (0.7, 0.1);
+    let central_err = (f.derivative(x, h).unwrap() - x.cos()).abs();
+    let richardson_err = (f.derivative_richardson(x, h).unwrap() - x.cos()).abs();
+
+    assert!(central_err > 1e-4);
+    assert!(richardson_err < 1e-6);
+    assert!(richardson_err * 100.0 < central_err);
+}
+
+#[test]
+fn numeric_derivatives() {
+    let f = |x: f64| -> Result<f64, NoError> { Ok(x.sin()) };
+
+    assert!((f.derivative(0.0, 1e-5).unwrap() - 1.0).abs() < 1e-9);
+    assert!(
+        (f.second_derivative(std::f64::consts::FRAC_PI_2, 1e-4)
+            .unwrap()
+            + 1.0)
+            .abs()
+            < 1e-6
+    );
+
+    // cos, and through another adapter -sin
+    let df = f.deriv(1e-5);
+    assert!((df.apply(0.0).unwrap() - 1.0).abs() < 1e-9);
+    let ddf = df.deriv(1e-3);
+    let pts = ddf.sample(0.0, 3.0, 6).unwrap();
+    assert!(pts.iter().all(|(x, y)| (y + x.sin()).abs() < 1e-5));
+
+    let dyn_f: &dyn Function<Error = NoError> = &f;
+    let df = Derivative { f: dyn_f, h: 1e-5 };
+    assert!((df.apply(0.0).unwrap() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn simpson_integral() {
+    let square = |x: f64| -> Result<f64, NoError> { Ok(x * x) };
+    assert!((square.integrate(0.0, 1.0, 2).unwrap() - 1.0 / 3.0).abs() < 1e-12);
+    assert!((square.integrate(1.0, 0.0, 7).unwrap() + 1.0 / 3.0).abs() < 1e-12);
+
+    let exp = |x: f64| -> Result<f64, NoError> { Ok(x.exp()) };
+    let exact = std::f64::consts::E - 1.0;
+    let coarse = (exp.integrate(0.0, 1.0, 4).unwrap() - exact).abs();
+    let fine = (exp.integrate(0.0, 1.0, 8).unwrap() - exact).abs();
+    assert!(coarse < 1e-4);
+    // the error is O(h^4)
+    assert!(fine * 12.0 < coarse && fine * 20.0 > coarse);
+}
+
+#[test]
+fn cached_calls() {
+    let calls = std::cell::Cell::new(0);
+    let f = |x: f64| -> Result<f64, NoError> {
+        calls.set(calls.get() + 1);
+        Ok(x * x)
+    };
+
+    let cached = f.cached();
+    for _ in 0..3 {
+        for x in [0.0, 1.0, 2.5, -1.0] {
+            assert_eq!(cached.apply(x).unwrap(), x * x);
+        }
+    }
+    assert_eq!(calls.get(), 4);
+
+    // -0.0 has a different bit pattern
+    cached.apply(-0.0).unwrap();
+    assert_eq!(calls.get(), 5);
+}
+
+#[test]
+fn adaptive_sampling() {
+    let almost_line = |x: f64| -> Result<f64, NoError> { Ok(2.0 * x + 1e-4 * x * x) };
+    let wiggly = |x: f64| -> Result<f64, NoError> { Ok((20.0 * x).sin()) };
+
+    let line_pts = almost_line.sample_adaptive(0.0, 1.0, 1e-3, 500).unwrap();
+    let wiggly_pts = wiggly.sample_adaptive(0.0, 1.0, 1e-3, 500).unwrap();
+    assert!(line_pts.len() < 20, "{}", line_pts.len());
+    assert!(wiggly_pts.len() > 100, "{}", wiggly_pts.len());
+    assert!(wiggly_pts.len() <= 500);
+
+    for pts in [&line_pts, &wiggly_pts] {
+        assert!(pts.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!((pts[0].0, pts[pts.len() - 1].0), (0.0, 1.0));
+    }
+}
+
+#[test]
+fn chebyshev_runge() {
+    let runge = |x: f64| -> Result<f64, NoError> { Ok(1.0 / (1.0 + 25.0 * x * x)) };
+    let lagrange = |pts: &[(f64, f64)], x: f64| {
+        pts.iter()
+            .enumerate()
+            .map(|(i, (xi, yi))| {
+                pts.iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .fold(*yi, |acc, (_, (xj, _))| acc * (x - xj) / (xi - xj))
+            })
+            .sum::<f64>()
+    };
+
+    let n = 15;
+    let uniform = runge.sample(-1.0, 1.0, n - 1).unwrap();
+    let chebyshev = runge.sample_chebyshev(-1.0, 1.0, n).unwrap();
+    assert_eq!(uniform.len(), chebyshev.len());
+    assert!(chebyshev.windows(2).all(|w| w[0].0 < w[1].0));
+
+    let max_err = |pts: &[(f64, f64)]| {
+        runge
+            .sample(-1.0, 1.0, 1000)
+            .unwrap()
+            .iter()
+            .map(|(x, y)| (lagrange(pts, *x) - y).abs())
+            .fold(0.0, f64::max)
+    };
+
+    let uniform_err = max_err(&uniform);
+    let chebyshev_err = max_err(&chebyshev);
+    assert!(chebyshev_err < 0.1);
+    assert!(chebyshev_err * 10.0 < uniform_err);
+}
+
+#[test]
+fn jacobian_2d() {
+    let f1 = |x: &[f64]| -> Result<f64, NoError> { Ok(x[0] * x[0] + x[1]) };
+    let f2 = |x: &[f64]| -> Result<f64, NoError> { Ok(x[0] + x[1] * x[1]) };
+
+    let (x, y) = (1.5, -2.0);
+    let j = jacobian(&[&f1, &f2], &[x, y], 1e-5).unwrap();
+    let actual = [2.0 * x, 1.0, 1.0, 2.0 * y];
+
+    assert_eq!(j.len(), actual.len());
+    assert!(j
+        .iter()
+        .zip(actual.iter())
+        .all(|(a, b)| (a - b).abs() < 1e-6));
+}
+
+#[test]
+fn combinators() {
+    let sqrt = |x: f64| -> Result<f64, &str> {
+        if x < 0.0 {
+            Err("negative")
+        } else {
+            Ok(x.sqrt())
+        }
+    };
+    let square = |x: f64| -> Result<f64, NoError> { Ok(x * x) };
+
+    let abs = sqrt.compose(square);
+    for x in [-2.5, -1.0, 0.0, 0.5, 3.0] {
+        assert!((abs.apply(x).unwrap() - x.abs()).abs() < 1e-12);
+    }
+    // the inner function fails first
+    assert_eq!(
+        square.compose(sqrt).apply(-1.0),
+        Err(Either::Right("negative"))
+    );
+    assert_eq!(sqrt.compose(-1.0).apply(0.0), Err(Either::Left("negative")));
+
+    let poly = square.add(1.0).mul(square).scale(2.0);
+    assert_eq!(poly.apply(2.0), Ok(40.0));
+    assert_eq!(sqrt.add(square).apply(-4.0), Err(Either::Left("negative")));
+}
@@ -0,0 +1,105 @@
+use std::fmt::Debug;
+
+use super::function::Function;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    FunctionError(String),
+    /// Only orders 2 to 8 are tabulated.
+    UnsupportedOrder(usize),
+}
+
+/// Non-negative Legendre roots on `[-1, 1]` and their weights, the rest are mirrored.
+const NODES: [&[(f64, f64)]; 7] = [
+    &[(0.5773502691896257, 1.0)],
+    &[
+        (0.0, 0.8888888888888888),
+        (0.7745966692414834, 0.5555555555555556),
+    ],
+    &[
+        (0.3399810435848563, 0.6521451548625461),
+        (0.8611363115940526, 0.3478548451374538),
+    ],
+    &[
+        (0.0, 0.5688888888888889),
+        (0.5384693101056831, 0.4786286704993665),
+        (0.906179845938664, 0.2369268850561891),
+    ],
+    &[
+        (0.2386191860831969, 0.467913934572691),
+        (0.6612093864662645, 0.3607615730481386),
+        (0.932469514203152, 0.1713244923791704),
+    ],
+    &[
+        (0.0, 0.4179591836734694),
+        (0.4058451513773972, 0.3818300505051189),
+        (0.7415311855993945, 0.2797053914892766),
+        (0.9491079123427585, 0.1294849661688697),
+    ],
+    &[
+        (0.1834346424956498, 0.362683783378362),
+        (0.525532409916329, 0.3137066458778873),
+        (0.7966664774136267, 0.2223810344533745),
+        (0.9602898564975363, 0.1012285362903763),
+    ],
+];
+
+/// The `order` nodes of the Gauss-Legendre rule mapped to `[from, to]`, left to right,
+/// each with its weight. The rule is exact for polynomials of degree up to `2 * order - 1`.
+pub fn gauss_nodes(from: f64, to: f64, order: usize) -> Result<Vec<(f64, f64)>, Error> {
+    if !(2..=8).contains(&order) {
+        return Err(Error::UnsupportedOrder(order));
+    }
+
+    let half = NODES[order - 2];
+    let (mid, radius) = ((from + to) / 2.0, (to - from) / 2.0);
+    let mirrored = half
+        .iter()
+        .rev()
+        .filter(|(t, _)| *t != 0.0)
+        .map(|(t, w)| (-t, *w));
+    Ok(mirrored
+        .chain(half.iter().copied())
+        .map(|(t, w)| (mid + radius * t, radius * w))
+        .collect())
+}
+
+pub fn gauss_legendre<E>(
+    f: &dyn Function<Error = E>,
+    from: f64,
+    to: f64,
+    order: usize,
+) -> Result<f64, Error>
+where
+    E: Debug,
+{
+    gauss_nodes(from, to, order)?
+        .into_iter()
+        .try_fold(0.0, |acc, (x, w)| {
+            f.apply(x)
+                .map(|y| acc + w * y)
+                .map_err(|e| Error::FunctionError(format!("{:?}", e)))
+        })
+}
+
+#[test]
+fn gauss_exact_for_degree_7() -> Result<(), Error> {
+    let p = |x: f64| -> Result<f64, Error> { Ok(x.powi(7) - 3.0 * x.powi(4) + 2.0 * x + 1.0) };
+    let antiderivative = |x: f64| x.powi(8) / 8.0 - 0.6 * x.powi(5) + x * x + x;
+    let exact = antiderivative(2.5) - antiderivative(-1.0);
+
+    let s = gauss_legendre(&p, -1.0, 2.5, 4)?;
+    assert!((s - exact).abs() < 1e-10 * exact.abs(), "{s} vs {exact}");
+    // one node short is no longer exact
+    assert!((gauss_legendre(&p, -1.0, 2.5, 3)? - exact).abs() > 1e-3);
+
+    for order in 2..=8 {
+        let nodes = gauss_nodes(0.0, 1.0, order)?;
+        assert_eq!(nodes.len(), order);
+        assert!(nodes.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!((nodes.iter().map(|(_, w)| w).sum::<f64>() - 1.0).abs() < 1e-14);
+    }
+    assert_eq!(gauss_nodes(0.0, 1.0, 9), Err(Error::UnsupportedOrder(9)));
+
+    Ok(())
+}
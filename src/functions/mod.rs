@@ -1,2 +1,5 @@
 pub mod function;
+pub mod gauss;
+pub mod polynomial;
+pub mod quadrature;
 pub mod table_function;
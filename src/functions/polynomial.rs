@@ -0,0 +1,158 @@
+use super::function::Function;
+use crate::{
+    iteration_limit::IterationLimit,
+    linalg::{conjugate_gradient, dot},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A polynomial of degree `degree` needs more than `degree` distinct x.
+    TooFewPoints {
+        distinct: usize,
+        degree: usize,
+    },
+    NonFinite,
+}
+
+/// `coefs[i]` is the coefficient of `x^i`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    pub coefs: Vec<f64>,
+}
+
+impl Polynomial {
+    pub fn new(coefs: Vec<f64>) -> Self {
+        Self { coefs }
+    }
+
+    /// Least squares fit, see `polyfit`.
+    pub fn fit(points: &[(f64, f64)], degree: usize) -> Result<Self, Error> {
+        polyfit(points, degree).map(Self::new)
+    }
+}
+
+impl Function for Polynomial {
+    type Error = Error;
+
+    fn apply(&self, x: f64) -> Result<f64, Self::Error> {
+        Ok(self.coefs.iter().rev().fold(0.0, |acc, c| acc * x + c))
+    }
+}
+
+/// Coefficients of the least squares polynomial of `degree`, from `x^0` up.
+/// The normal equations are solved with conjugate gradients after mapping the
+/// x range to `[-1, 1]`, which keeps them reasonably conditioned for small degrees.
+pub fn polyfit(points: &[(f64, f64)], degree: usize) -> Result<Vec<f64>, Error> {
+    if points.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+        return Err(Error::NonFinite);
+    }
+    let mut xs = points.iter().map(|(x, _)| *x).collect::<Vec<_>>();
+    xs.sort_by(f64::total_cmp);
+    xs.dedup();
+    if xs.len() <= degree {
+        return Err(Error::TooFewPoints {
+            distinct: xs.len(),
+            degree,
+        });
+    }
+
+    let (min, max) = (xs[0], xs[xs.len() - 1]);
+    let center = (min + max) / 2.0;
+    let scale = if max > min { (max - min) / 2.0 } else { 1.0 };
+
+    let n = degree + 1;
+    let mut a = vec![0.0; n * n];
+    let mut f = vec![0.0; n];
+    for (x, y) in points {
+        let t = (x - center) / scale;
+        let powers = (0..2 * n - 1)
+            .scan(1.0, |p, _| {
+                let cur = *p;
+                *p *= t;
+                Some(cur)
+            })
+            .collect::<Vec<_>>();
+        for j in 0..n {
+            f[j] += y * powers[j];
+            for k in 0..n {
+                a[j * n + k] += powers[j + k];
+            }
+        }
+    }
+
+    // Jacobi preconditioner
+    let mut inv_b = vec![0.0; n * n];
+    for i in 0..n {
+        inv_b[i * n + i] = 1.0 / a[i * n + i];
+    }
+    let mut scaled_coefs = vec![0.0; n];
+    let eps = 1e-13 * dot(&f, &f, n).sqrt().max(f64::MIN_POSITIVE);
+    // rounding can keep the residual just above eps, the best x is still the answer
+    conjugate_gradient(
+        &a,
+        &inv_b,
+        &mut scaled_coefs,
+        &f,
+        n,
+        eps,
+        IterationLimit::return_best(10 * n),
+    )
+    .map_err(|_| Error::NonFinite)?;
+
+    // p(t) with t = u * x + v, expanded by Horner's scheme on polynomials
+    let (u, v) = (1.0 / scale, -center / scale);
+    let mut coefs = vec![0.0; n];
+    for c in scaled_coefs.iter().rev() {
+        let mut next = vec![0.0; n];
+        for (i, p) in coefs.iter().enumerate() {
+            next[i] += p * v;
+            if i + 1 < n {
+                next[i + 1] += p * u;
+            }
+        }
+        next[0] += c;
+        coefs = next;
+    }
+
+    if coefs.iter().all(|c| c.is_finite()) {
+        Ok(coefs)
+    } else {
+        Err(Error::NonFinite)
+    }
+}
+
+#[test]
+fn fit_quadratic() -> Result<(), Error> {
+    let p = |x: f64| 2.0 * x * x - 3.0 * x + 1.0;
+    let pts = (0..=20)
+        .map(|i| 5.0 + i as f64 * 0.5)
+        .map(|x| (x, p(x)))
+        .collect::<Vec<_>>();
+
+    let coefs = polyfit(&pts, 2)?;
+    for (c, expected) in coefs.iter().zip([1.0, -3.0, 2.0]) {
+        assert!((c - expected).abs() < 1e-8, "{coefs:?}");
+    }
+
+    // a least squares line goes through the mean of the points
+    let noisy = (0..10usize)
+        .map(|i| {
+            (
+                i as f64,
+                i as f64 + if i.is_multiple_of(2) { 0.5 } else { -0.5 },
+            )
+        })
+        .collect::<Vec<_>>();
+    let line = Polynomial::fit(&noisy, 1)?;
+    assert!((line.apply(4.5)? - 4.5).abs() < 1e-9);
+
+    assert_eq!(
+        polyfit(&[(1.0, 1.0), (1.0, 2.0), (2.0, 0.0)], 2),
+        Err(Error::TooFewPoints {
+            distinct: 2,
+            degree: 2
+        })
+    );
+
+    Ok(())
+}
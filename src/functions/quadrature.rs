@@ -0,0 +1,131 @@
+use std::fmt::Debug;
+
+use super::function::Function;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    FunctionError(String),
+    /// `[from, to]` still missed its share of `tol` after `max_depth` bisections.
+    MaxDepthReached {
+        from: f64,
+        to: f64,
+    },
+}
+
+/// Adaptive Simpson's rule: an interval is split in half until
+/// `|S(whole) - S(left) - S(right)| / 15 < tol`, each half getting `tol / 2`.
+pub fn integrate_adaptive<E>(
+    f: &dyn Function<Error = E>,
+    from: f64,
+    to: f64,
+    tol: f64,
+    max_depth: usize,
+) -> Result<f64, Error>
+where
+    E: Debug,
+{
+    integrate_adaptive_counted(f, from, to, tol, max_depth).map(|(s, _)| s)
+}
+
+/// Same as `integrate_adaptive`, but also returns how many times `f` was evaluated.
+pub fn integrate_adaptive_counted<E>(
+    f: &dyn Function<Error = E>,
+    from: f64,
+    to: f64,
+    tol: f64,
+    max_depth: usize,
+) -> Result<(f64, usize), Error>
+where
+    E: Debug,
+{
+    let mut evaluations = 0;
+    let mut eval = |x| {
+        evaluations += 1;
+        f.apply(x)
+            .map_err(|e| Error::FunctionError(format!("{:?}", e)))
+    };
+
+    let mid = (from + to) / 2.0;
+    let (f_from, f_mid, f_to) = (eval(from)?, eval(mid)?, eval(to)?);
+    let whole = simpson(from, to, f_from, f_mid, f_to);
+    let s = bisect(
+        &mut eval,
+        [from, mid, to],
+        [f_from, f_mid, f_to],
+        whole,
+        tol,
+        max_depth,
+    )?;
+
+    Ok((s, evaluations))
+}
+
+fn simpson(from: f64, to: f64, f_from: f64, f_mid: f64, f_to: f64) -> f64 {
+    (to - from) / 6.0 * (f_from + 4.0 * f_mid + f_to)
+}
+
+fn bisect(
+    eval: &mut dyn FnMut(f64) -> Result<f64, Error>,
+    [from, mid, to]: [f64; 3],
+    [f_from, f_mid, f_to]: [f64; 3],
+    whole: f64,
+    tol: f64,
+    depth: usize,
+) -> Result<f64, Error> {
+    let (left_mid, right_mid) = ((from + mid) / 2.0, (mid + to) / 2.0);
+    let (f_left_mid, f_right_mid) = (eval(left_mid)?, eval(right_mid)?);
+    let left = simpson(from, mid, f_from, f_left_mid, f_mid);
+    let right = simpson(mid, to, f_mid, f_right_mid, f_to);
+
+    let diff = left + right - whole;
+    if diff.abs() / 15.0 < tol {
+        // Richardson extrapolation of the two estimates
+        return Ok(left + right + diff / 15.0);
+    }
+    if depth == 0 {
+        return Err(Error::MaxDepthReached { from, to });
+    }
+
+    Ok(bisect(
+        eval,
+        [from, left_mid, mid],
+        [f_from, f_left_mid, f_mid],
+        left,
+        tol / 2.0,
+        depth - 1,
+    )? + bisect(
+        eval,
+        [mid, right_mid, to],
+        [f_mid, f_right_mid, f_to],
+        right,
+        tol / 2.0,
+        depth - 1,
+    )?)
+}
+
+#[test]
+fn adaptive_peak() -> Result<(), Error> {
+    let f = |x: f64| -> Result<f64, Error> { Ok(1.0 / (x * x + 0.001)) };
+    // int 1 / (x^2 + a^2) = atan(x / a) / a
+    let a = 0.001f64.sqrt();
+    let exact = 2.0 * (1.0 / a).atan() / a;
+
+    let s = integrate_adaptive(&f, -1.0, 1.0, 1e-8, 50)?;
+    assert!((s - exact).abs() < 1e-7, "{s} vs {exact}");
+
+    // a fixed grid with as many points is an order of magnitude worse
+    let (s, evaluations) = integrate_adaptive_counted(&f, -1.0, 1.0, 1e-4, 50)?;
+    let fixed = f.integrate(-1.0, 1.0, evaluations).unwrap();
+    assert!((s - exact).abs() < 1e-4);
+    assert!(
+        (fixed - exact).abs() > 10.0 * (s - exact).abs(),
+        "{fixed} vs {s} with {evaluations} points"
+    );
+
+    assert!(matches!(
+        integrate_adaptive(&f, -1.0, 1.0, 1e-8, 3),
+        Err(Error::MaxDepthReached { .. })
+    ));
+
+    Ok(())
+}
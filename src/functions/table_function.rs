@@ -1,17 +1,36 @@
 use std::{
     fs::File,
     io::{BufRead, BufReader, Read},
+    ops::{Add, Mul, Sub},
     path::Path,
 };
 
-use super::function::Function;
+use super::function::{write_csv, Function};
+use crate::spline::{Pchip, Spline};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     TableEmpty,
-    PointOutOfBounds { x: f64, min: f64, max: f64 },
+    PointOutOfBounds {
+        x: f64,
+        min: f64,
+        max: f64,
+    },
     Io(String),
-    InvalidCsv { line: usize },
+    InvalidCsv {
+        line: usize,
+    },
+    ColumnOutOfRange {
+        line: usize,
+        column: usize,
+        available: usize,
+    },
+    Spline(String),
+    NonFinite {
+        line: usize,
+    },
+    /// A deserialized table that could not have been built by its constructors.
+    InvalidParts(String),
 }
 
 impl From<std::io::Error> for Error {
@@ -26,16 +45,98 @@ impl From<std::fmt::Error> for Error {
     }
 }
 
+/// How `apply` fills the gaps between the points of the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InterpolationMode {
+    Linear,
+    /// Natural cubic spline through all of the points.
+    CubicSpline,
+    /// The y of the closest x, halfway between two points the right one wins.
+    NearestNeighbor,
+    /// Monotone cubic that does not overshoot the points, see `Pchip`.
+    Pchip,
+}
+
+/// How `TableFunction::from_read_with` reads a csv file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub skip_header: bool,
+    /// Lines starting with this are ignored.
+    pub comment_prefix: Option<String>,
+    /// Indices of the x and y columns.
+    pub columns: (usize, usize),
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            skip_header: false,
+            comment_prefix: None,
+            columns: (0, 1),
+        }
+    }
+}
+
+/// What `apply` returns left of the first and right of the last point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Extrapolation {
+    Error,
+    /// The y of the nearest end of the table.
+    Clamp,
+    /// Continues the first or the last segment.
+    LinearExtend,
+}
+
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "TableFunctionParts")
+)]
 pub struct TableFunction {
     sorted_table: Vec<(f64, f64)>,
     eps: f64,
+    mode: InterpolationMode,
+    spline: Option<Spline>,
+    pchip: Option<Pchip>,
+    extrapolation: Extrapolation,
 }
 
 impl TableFunction {
     pub fn from_table(mut table: Vec<(f64, f64)>) -> Self {
-        table.sort_by(|(x1, _), (x2, _)| x1.partial_cmp(x2).unwrap_or(std::cmp::Ordering::Equal));
+        table.sort_by(|(x1, _), (x2, _)| x1.total_cmp(x2));
+        Self::from_sorted_unchecked(table)
+    }
+
+    /// Fails if the mode is `CubicSpline` or `Pchip` and no spline goes through `table`,
+    /// e.g. when an x repeats.
+    pub fn with_mode(table: Vec<(f64, f64)>, mode: InterpolationMode) -> Result<Self, Error> {
+        let mut res = Self::from_table(table);
+        match mode {
+            InterpolationMode::CubicSpline => {
+                res.spline = Some(
+                    Spline::new(res.sorted_table.clone())
+                        .map_err(|e| Error::Spline(format!("{:?}", e)))?,
+                );
+            }
+            InterpolationMode::Pchip => {
+                res.pchip = Some(
+                    Pchip::new(res.sorted_table.clone())
+                        .map_err(|e| Error::Spline(format!("{:?}", e)))?,
+                );
+            }
+            _ => {}
+        }
+        res.mode = mode;
+        Ok(res)
+    }
 
+    /// Like `from_table`, but trusts `table` to already be sorted by x.
+    pub fn from_sorted_unchecked(table: Vec<(f64, f64)>) -> Self {
         Self {
             eps: table
                 .first()
@@ -54,32 +155,81 @@ impl TableFunction {
                 })
                 .unwrap_or(0.0),
             sorted_table: table,
+            mode: InterpolationMode::Linear,
+            spline: None,
+            pchip: None,
+            extrapolation: Extrapolation::Error,
         }
     }
 
+    pub fn with_extrapolation(mut self, extrapolation: Extrapolation) -> Self {
+        self.extrapolation = extrapolation;
+        self
+    }
+
     pub fn from_read<R>(src: R) -> Result<Self, Error>
+    where
+        R: Read,
+    {
+        Self::from_read_columns(src, (0, 1))
+    }
+
+    /// `columns` are the indices of the x and y columns.
+    pub fn from_read_columns<R>(src: R, columns: (usize, usize)) -> Result<Self, Error>
+    where
+        R: Read,
+    {
+        Self::from_read_with(
+            src,
+            CsvOptions {
+                columns,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Blank lines are skipped, `line` in the errors counts them too.
+    pub fn from_read_with<R>(src: R, opts: CsvOptions) -> Result<Self, Error>
     where
         R: Read,
     {
         let f = BufReader::new(src);
+        let columns = opts.columns;
 
         let mut table = vec![];
 
         for (line, l) in f.lines().enumerate() {
             let l = l?;
-            let mut split = l.split(',').take(2);
-            let x = split
-                .next()
-                .ok_or(Error::InvalidCsv { line })?
-                .parse::<f64>()
-                .map_err(|_| Error::InvalidCsv { line })?;
-            let y = split
-                .next()
-                .ok_or(Error::InvalidCsv { line })?
-                .parse::<f64>()
-                .map_err(|_| Error::InvalidCsv { line })?;
+            let is_comment = matches!(
+                &opts.comment_prefix,
+                Some(prefix) if l.trim_start().starts_with(prefix.as_str())
+            );
+            if (line == 0 && opts.skip_header) || is_comment || l.trim().is_empty() {
+                continue;
+            }
 
-            table.push((x, y))
+            let split = l.split(opts.delimiter).collect::<Vec<_>>();
+            let column = |column: usize| {
+                split
+                    .get(column)
+                    .ok_or(Error::ColumnOutOfRange {
+                        line,
+                        column,
+                        available: split.len(),
+                    })?
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| Error::InvalidCsv { line })
+                    .and_then(|v| {
+                        if v.is_finite() {
+                            Ok(v)
+                        } else {
+                            Err(Error::NonFinite { line })
+                        }
+                    })
+            };
+
+            table.push((column(columns.0)?, column(columns.1)?))
         }
 
         Ok(Self::from_table(table))
@@ -90,10 +240,19 @@ impl TableFunction {
         Self::from_read(f)
     }
 
+    pub fn to_file(&self, path: &Path) -> Result<(), Error> {
+        write_csv(path, &self.sorted_table)?;
+        Ok(())
+    }
+
     pub fn to_table(&self) -> Vec<(f64, f64)> {
         self.sorted_table.clone()
     }
 
+    pub fn into_table(self) -> Vec<(f64, f64)> {
+        self.sorted_table
+    }
+
     pub fn min_x(&self) -> Option<f64> {
         self.sorted_table.first().cloned().map(|(x, _)| x)
     }
@@ -101,6 +260,137 @@ impl TableFunction {
     pub fn max_x(&self) -> Option<f64> {
         self.sorted_table.last().cloned().map(|(x, _)| x)
     }
+
+    /// Trapezoidal integral of the data from `from` to `to`, the ends are interpolated.
+    /// Ends outside of the table are handled by the extrapolation, same as in `apply`.
+    pub fn integral(&self, from: f64, to: f64) -> Result<f64, Error> {
+        if from > to {
+            return self.integral(to, from).map(|s| -s);
+        }
+
+        let mut pts = vec![(from, self.apply(from)?)];
+        pts.extend(
+            self.sorted_table
+                .iter()
+                .filter(|(x, _)| *x > from && *x < to),
+        );
+        pts.push((to, self.apply(to)?));
+
+        Ok(pts
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0) * (w[0].1 + w[1].1) / 2.0)
+            .sum())
+    }
+
+    /// Combines two tables pointwise on the union of their grids,
+    /// limited to the range where both are defined.
+    pub fn zip_with<F>(&self, other: &TableFunction, op: F) -> TableFunction
+    where
+        F: Fn(f64, f64) -> f64,
+    {
+        let (from, to) = match (self.min_x(), self.max_x(), other.min_x(), other.max_x()) {
+            (Some(a_min), Some(a_max), Some(b_min), Some(b_max)) => {
+                (f64::max(a_min, b_min), f64::min(a_max, b_max))
+            }
+            _ => return TableFunction::from_table(vec![]),
+        };
+
+        let mut xs = self
+            .sorted_table
+            .iter()
+            .chain(other.sorted_table.iter())
+            .map(|(x, _)| *x)
+            .filter(|x| *x >= from && *x <= to)
+            .collect::<Vec<_>>();
+        xs.sort_by(f64::total_cmp);
+        xs.dedup();
+
+        TableFunction::from_table(
+            xs.into_iter()
+                .filter_map(|x| {
+                    self.apply(x)
+                        .and_then(|a| other.apply(x).map(|b| (x, op(a, b))))
+                        .ok()
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TableFunctionParts {
+    sorted_table: Vec<(f64, f64)>,
+    eps: f64,
+    mode: InterpolationMode,
+    spline: Option<Spline>,
+    #[serde(default)]
+    pchip: Option<Pchip>,
+    extrapolation: Extrapolation,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<TableFunctionParts> for TableFunction {
+    type Error = String;
+
+    fn try_from(parts: TableFunctionParts) -> Result<Self, Self::Error> {
+        let invalid = |msg: String| format!("{:?}", Error::InvalidParts(msg));
+
+        if let Some(w) = parts.sorted_table.windows(2).find(|w| w[1].0 < w[0].0) {
+            return Err(invalid(format!(
+                "x = {} comes after x = {}",
+                w[1].0, w[0].0
+            )));
+        }
+        if !(parts.eps.is_finite() && parts.eps >= 0.0) {
+            return Err(invalid(format!("eps = {}", parts.eps)));
+        }
+        if (parts.mode == InterpolationMode::CubicSpline) != parts.spline.is_some() {
+            return Err(invalid(format!(
+                "mode {:?} does not match the spline",
+                parts.mode
+            )));
+        }
+        if (parts.mode == InterpolationMode::Pchip) != parts.pchip.is_some() {
+            return Err(invalid(format!(
+                "mode {:?} does not match the pchip",
+                parts.mode
+            )));
+        }
+
+        Ok(Self {
+            sorted_table: parts.sorted_table,
+            eps: parts.eps,
+            mode: parts.mode,
+            spline: parts.spline,
+            pchip: parts.pchip,
+            extrapolation: parts.extrapolation,
+        })
+    }
+}
+
+impl Add for &TableFunction {
+    type Output = TableFunction;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.zip_with(rhs, |a, b| a + b)
+    }
+}
+
+impl Sub for &TableFunction {
+    type Output = TableFunction;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.zip_with(rhs, |a, b| a - b)
+    }
+}
+
+impl Mul for &TableFunction {
+    type Output = TableFunction;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.zip_with(rhs, |a, b| a * b)
+    }
 }
 
 fn larp(min_x: f64, max_x: f64, x: f64, from_y: f64, to_y: f64) -> f64 {
@@ -115,13 +405,25 @@ impl Function for TableFunction {
             return Err(Error::TableEmpty);
         }
 
-        for i in 1..self.sorted_table.len() {
+        // the first segment with `prev_x <= arg <= x`
+        let i = self.sorted_table.partition_point(|(x, _)| *x < arg).max(1);
+        if i < self.sorted_table.len() && self.sorted_table[i - 1].0 <= arg {
             let (x, y) = self.sorted_table[i];
             let (prev_x, prev_y) = self.sorted_table[i - 1];
-
-            if prev_x <= arg && x >= arg {
-                return Ok(larp(prev_x, x, arg, prev_y, y));
-            }
+            return match (self.mode, &self.spline) {
+                (InterpolationMode::CubicSpline, Some(spline)) => spline
+                    .apply(arg)
+                    .map_err(|e| Error::Spline(format!("{:?}", e))),
+                (InterpolationMode::Pchip, _) => match &self.pchip {
+                    Some(pchip) => pchip
+                        .apply(arg)
+                        .map_err(|e| Error::Spline(format!("{:?}", e))),
+                    None => Ok(larp(prev_x, x, arg, prev_y, y)),
+                },
+                (InterpolationMode::NearestNeighbor, _) if arg - prev_x < x - arg => Ok(prev_y),
+                (InterpolationMode::NearestNeighbor, _) => Ok(y),
+                _ => Ok(larp(prev_x, x, arg, prev_y, y)),
+            };
         }
 
         if (arg - self.sorted_table[0].0).abs() < self.eps {
@@ -131,11 +433,31 @@ impl Function for TableFunction {
             return Ok(self.sorted_table[self.sorted_table.len() - 1].1);
         }
 
-        Err(Error::PointOutOfBounds {
+        let out_of_bounds = Error::PointOutOfBounds {
             x: arg,
             min: self.sorted_table.first().cloned().unwrap_or((0.0, 0.0)).0,
             max: self.sorted_table.last().cloned().unwrap_or((0.0, 0.0)).0,
-        })
+        };
+        if arg.is_nan() {
+            return Err(out_of_bounds);
+        }
+
+        let n = self.sorted_table.len();
+        let left = arg < self.sorted_table[0].0;
+        match self.extrapolation {
+            Extrapolation::Error => Err(out_of_bounds),
+            Extrapolation::Clamp if left => Ok(self.sorted_table[0].1),
+            Extrapolation::Clamp => Ok(self.sorted_table[n - 1].1),
+            Extrapolation::LinearExtend if n == 1 => Ok(self.sorted_table[0].1),
+            Extrapolation::LinearExtend => {
+                let ((x0, y0), (x1, y1)) = if left {
+                    (self.sorted_table[0], self.sorted_table[1])
+                } else {
+                    (self.sorted_table[n - 2], self.sorted_table[n - 1])
+                };
+                Ok(larp(x0, x1, arg, y0, y1))
+            }
+        }
     }
 }
 
@@ -168,3 +490,239 @@ fn table_function() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn table_lookup_speed() {
+    let n = 5000;
+    let func = TableFunction::from_table((0..n).map(|i| (i as f64, 2.0 * i as f64)).collect());
+
+    let start = std::time::Instant::now();
+    for i in 0..n {
+        let x = i as f64 * 0.9997 + 0.1;
+        assert!((func.apply(x).unwrap() - 2.0 * x).abs() < 1e-9);
+    }
+    assert!(start.elapsed() < std::time::Duration::from_millis(200));
+
+    assert_eq!(func.apply(0.0), Ok(0.0));
+    assert_eq!(func.apply(4999.0), Ok(9998.0));
+    assert!(func.apply(-0.5).is_err());
+    assert!(func.apply(f64::NAN).is_err());
+}
+
+#[test]
+fn table_modes() -> Result<(), Error> {
+    let table = vec![(0.0, 0.0), (1.0, 10.0), (2.0, 5.0), (3.0, 20.0)];
+
+    let cubic = TableFunction::with_mode(table.clone(), InterpolationMode::CubicSpline)?;
+    let spline = Spline::new(table.clone()).unwrap();
+    for x in [0.0, 0.3, 1.5, 2.9, 3.0] {
+        assert_eq!(cubic.apply(x), Ok(spline.apply(x).unwrap()));
+    }
+    assert!(matches!(
+        cubic.apply(3.5),
+        Err(Error::PointOutOfBounds { .. })
+    ));
+
+    let nearest = TableFunction::with_mode(table.clone(), InterpolationMode::NearestNeighbor)?;
+    assert_eq!(nearest.apply(0.4), Ok(0.0));
+    assert_eq!(nearest.apply(0.5), Ok(10.0));
+    assert_eq!(nearest.apply(1.49), Ok(10.0));
+    assert_eq!(nearest.apply(2.5), Ok(20.0));
+    assert_eq!(nearest.apply(3.0), Ok(20.0));
+
+    let linear = TableFunction::with_mode(table.clone(), InterpolationMode::Linear)?;
+    assert_eq!(linear, TableFunction::from_table(table.clone()));
+    assert!(matches!(
+        TableFunction::with_mode(vec![(1.0, 1.0)], InterpolationMode::CubicSpline),
+        Err(Error::Spline(_))
+    ));
+
+    let pchip = TableFunction::with_mode(table, InterpolationMode::Pchip)?;
+    assert_eq!(pchip.apply(1.0), Ok(10.0));
+    assert!((5.0..=10.0).contains(&pchip.apply(1.5)?));
+
+    Ok(())
+}
+
+#[test]
+fn table_extrapolation() {
+    let table = vec![(0.0, 1.0), (1.0, 3.0), (2.0, 2.0)];
+    let func =
+        |extrapolation| TableFunction::from_table(table.clone()).with_extrapolation(extrapolation);
+
+    assert!(matches!(
+        func(Extrapolation::Error).apply(-1.0),
+        Err(Error::PointOutOfBounds { .. })
+    ));
+    assert_eq!(func(Extrapolation::Clamp).apply(-1.0), Ok(1.0));
+    assert_eq!(func(Extrapolation::Clamp).apply(5.0), Ok(2.0));
+    assert_eq!(func(Extrapolation::LinearExtend).apply(-1.0), Ok(-1.0));
+    assert_eq!(func(Extrapolation::LinearExtend).apply(4.0), Ok(0.0));
+    assert_eq!(func(Extrapolation::LinearExtend).apply(1.5), Ok(2.5));
+    assert!(func(Extrapolation::Clamp).apply(f64::NAN).is_err());
+}
+
+#[test]
+fn table_non_finite() {
+    assert_eq!(
+        TableFunction::from_read("nan,1\n0.2,2".as_bytes()),
+        Err(Error::NonFinite { line: 0 })
+    );
+    assert_eq!(
+        TableFunction::from_read("0.1,1\n0.2,inf".as_bytes()),
+        Err(Error::NonFinite { line: 1 })
+    );
+
+    let func = TableFunction::from_table(vec![(1.0, 1.0), (f64::NAN, 2.0), (0.0, 0.0)]);
+    assert_eq!(func.min_x(), Some(0.0));
+}
+
+#[test]
+fn table_csv_options() -> Result<(), Error> {
+    let src = "time\tvalue\n# measured by hand\n0.1\t1\n\n  # repeated\n0.2\t2\n0.3\t3\n";
+    let opts = CsvOptions {
+        delimiter: '\t',
+        skip_header: true,
+        comment_prefix: Some("#".to_string()),
+        ..Default::default()
+    };
+    let func = TableFunction::from_read_with(src.as_bytes(), opts.clone())?;
+    assert_eq!(func.to_table(), vec![(0.1, 1.0), (0.2, 2.0), (0.3, 3.0)]);
+
+    assert_eq!(
+        TableFunction::from_read_with(
+            src.as_bytes(),
+            CsvOptions {
+                skip_header: false,
+                ..opts
+            }
+        ),
+        Err(Error::InvalidCsv { line: 0 })
+    );
+    assert_eq!(
+        TableFunction::from_read("0.1,1\n\n0.2,2\n".as_bytes())?.to_table(),
+        vec![(0.1, 1.0), (0.2, 2.0)]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn table_file_round_trip() -> Result<(), Error> {
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("table_round_trip_{}.csv", std::process::id()));
+
+    let func = TableFunction::from_table(vec![(0.1, 1.0 / 3.0), (0.2, -2.5e-7), (0.3, 1e20)]);
+    func.to_file(&path)?;
+    let read = TableFunction::from_file(&path);
+
+    let sampled = dir.join(format!("table_sampled_{}.csv", std::process::id()));
+    (|x: f64| -> Result<f64, Error> { Ok(x * x) }).save_csv(&sampled, 0.0, 1.0, 4)?;
+    let read_sampled = TableFunction::from_file(&sampled);
+
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&sampled);
+    assert_eq!(read?, func);
+    assert_eq!(read_sampled?.apply(0.5), Ok(0.25));
+
+    Ok(())
+}
+
+#[test]
+fn table_ownership() {
+    let table = vec![(0.0, 3.0), (1.0, 1.0), (2.0, 2.0)];
+    let func = TableFunction::from_sorted_unchecked(table.clone());
+    assert_eq!(func.to_table(), table);
+    assert_eq!(func.clone().into_table(), func.to_table());
+    assert_eq!(
+        func,
+        TableFunction::from_table(vec![(2.0, 2.0), (0.0, 3.0), (1.0, 1.0)])
+    );
+}
+
+#[test]
+fn table_integral() -> Result<(), Error> {
+    let func =
+        TableFunction::from_table((0..=10).map(|i| (i as f64 * 0.1, i as f64 * 0.1)).collect());
+
+    assert!((func.integral(0.0, 1.0)? - 0.5).abs() < 1e-12);
+    assert!((func.integral(0.25, 0.75)? - 0.25).abs() < 1e-12);
+    assert!((func.integral(1.0, 0.0)? + 0.5).abs() < 1e-12);
+    assert_eq!(func.integral(0.3, 0.3), Ok(0.0));
+    assert!(matches!(
+        func.integral(-1.0, 0.5),
+        Err(Error::PointOutOfBounds { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn table_columns() -> Result<(), Error> {
+    let src = "1,10,0.1\n2,20,0.2\n3,30,0.3";
+    let func = TableFunction::from_read_columns(src.as_bytes(), (2, 0))?;
+    assert_eq!(func.to_table(), vec![(0.1, 1.0), (0.2, 2.0), (0.3, 3.0)]);
+
+    assert_eq!(
+        TableFunction::from_read_columns("1,10,0.1\n2,20".as_bytes(), (2, 0)),
+        Err(Error::ColumnOutOfRange {
+            line: 1,
+            column: 2,
+            available: 2
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn table_arithmetic() -> Result<(), Error> {
+    let a = TableFunction::from_table(vec![(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]);
+    let b = TableFunction::from_table(vec![(0.5, 1.0), (1.5, 3.0), (2.5, 1.0)]);
+
+    let diff = &a - &b;
+    assert_eq!(diff.min_x(), Some(0.5));
+    assert_eq!(diff.max_x(), Some(2.0));
+    assert_eq!(diff.to_table().len(), 4);
+
+    assert_eq!(diff.apply(0.5), Ok(-0.5));
+    assert_eq!(diff.apply(1.0), Ok(-1.0));
+    assert_eq!(diff.apply(1.5), Ok(-1.5));
+    assert_eq!(diff.apply(2.0), Ok(0.0));
+
+    assert_eq!((&a + &b).apply(1.5), Ok(4.5));
+    assert_eq!((&a * &b).apply(1.0), Ok(2.0));
+
+    let disjoint = TableFunction::from_table(vec![(5.0, 1.0), (6.0, 1.0)]);
+    assert_eq!((&a - &disjoint).apply(0.0), Err(Error::TableEmpty));
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn table_serde() -> Result<(), Error> {
+    let pts = vec![(0.0, 1.0), (1.0, 3.0), (2.5, 2.0), (4.0, 0.0)];
+    for mode in [
+        InterpolationMode::Linear,
+        InterpolationMode::CubicSpline,
+        InterpolationMode::NearestNeighbor,
+        InterpolationMode::Pchip,
+    ] {
+        let f = TableFunction::with_mode(pts.clone(), mode)?
+            .with_extrapolation(Extrapolation::LinearExtend);
+        let json = serde_json::to_string(&f).unwrap();
+        let loaded: TableFunction = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, f);
+        for x in [-1.0, 0.0, 0.7, 2.0, 3.9, 5.0] {
+            assert_eq!(loaded.apply(x), f.apply(x));
+        }
+    }
+
+    let unsorted = r#"{"sorted_table":[[1.0,0.0],[0.0,1.0]],"eps":0.1,"mode":"Linear","spline":null,"extrapolation":"Error"}"#;
+    assert!(serde_json::from_str::<TableFunction>(unsorted).is_err());
+    let no_spline = r#"{"sorted_table":[[0.0,0.0],[1.0,1.0]],"eps":0.1,"mode":"CubicSpline","spline":null,"extrapolation":"Error"}"#;
+    assert!(serde_json::from_str::<TableFunction>(no_spline).is_err());
+
+    Ok(())
+}
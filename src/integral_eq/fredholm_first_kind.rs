@@ -1,8 +1,76 @@
-use crate::functions::{function::*, table_function::TableFunction};
+use crate::{
+    functions::{function::*, table_function::TableFunction},
+    iteration_limit::IterationLimit,
+    linalg::{apply, conjugate_gradient, mult_mat, CgResult},
+};
 use std::fmt::Debug;
 
-use super::{conjugate_gradients::*, Error};
+use super::{checked_kernel, checked_right_side, Error};
 
+/// `K(x_i, s_j)` on `n` evenly spaced points, row-major, each cell evaluated once
+/// and checked with `checked_kernel`.
+pub fn tabulate_kernel<E>(
+    kernel: &dyn Function2d<Error = E>,
+    from: f64,
+    to: f64,
+    n: usize,
+) -> Result<Vec<f64>, Error>
+where
+    E: Debug,
+{
+    let step = (to - from) / (n as f64 - 1.0);
+    let mut table = Vec::with_capacity(n * n);
+
+    for i in 0..n {
+        for j in 0..n {
+            let x = (i as f64) * step + from;
+            let y = (j as f64) * step + from;
+            table.push(checked_kernel(kernel, x, y)?);
+        }
+    }
+
+    Ok(table)
+}
+
+/// `K(x_i, s_j) * step` on `n` evenly spaced points, row-major.
+/// With `symmetrize` the kernel is replaced by `(K(x, s) + K(s, x)) / 2`,
+/// which keeps the matrix symmetric when the kernel is only symmetric on paper.
+pub fn kernel_matrix<E>(
+    kernel: &dyn Function2d<Error = E>,
+    from: f64,
+    to: f64,
+    n: usize,
+    symmetrize: bool,
+) -> Result<Vec<f64>, Error>
+where
+    E: Debug,
+{
+    let step = (to - from) / (n as f64 - 1.0);
+    let table = tabulate_kernel(kernel, from, to, n)?;
+
+    Ok(kernel_matrix_from_table(&table, n, step, symmetrize))
+}
+
+/// Same as `kernel_matrix`, but from values already computed by `tabulate_kernel`.
+pub fn kernel_matrix_from_table(table: &[f64], n: usize, step: f64, symmetrize: bool) -> Vec<f64> {
+    let mut mat = (0..n * n).map(|_| 0.0).collect::<Vec<_>>();
+
+    for i in 0..n {
+        for j in 0..n {
+            let k = if symmetrize {
+                (table[i * n + j] + table[j * n + i]) / 2.0
+            } else {
+                table[i * n + j]
+            };
+            mat[i * n + j] = k * step;
+        }
+    }
+
+    mat
+}
+
+/// Also returns how the conjugate gradient solve of the normal equations went.
+#[allow(clippy::too_many_arguments)]
 pub fn fredholm_1st_system<E1, E2>(
     kernel: &dyn Function2d<Error = E1>,
     right_side: &dyn Function<Error = E2>,
@@ -11,26 +79,20 @@ pub fn fredholm_1st_system<E1, E2>(
     n: usize,
     eps: f64,
     max_iter_count: usize,
-) -> Result<TableFunction, Error>
+    symmetrize: bool,
+) -> Result<(TableFunction, CgResult), Error>
 where
     E1: Debug,
     E2: Debug,
 {
     let step = (to - from) / (n as f64 - 1.0);
 
-    let mut mat = (0..n * n).map(|_| 0.0).collect::<Vec<_>>();
+    let mat = kernel_matrix(kernel, from, to, n, symmetrize)?;
     let mut mat_transpozed = (0..n * n).map(|_| 0.0).collect::<Vec<_>>();
     let mut identity = (0..n * n).map(|_| 0.0).collect::<Vec<_>>();
 
     for i in 0..n {
         for j in 0..n {
-            let x = (i as f64) * step + from;
-            let y = (j as f64) * step + from;
-
-            mat[i * n + j] = kernel
-                .apply(x, y)
-                .map(|res| res * step)
-                .map_err(|e| Error::FunctionError(format!("{:?}", e)))?;
             mat_transpozed[j * n + i] = mat[i * n + j];
         }
         identity[i * n + i] = 1.0;
@@ -43,25 +105,103 @@ where
     apply(
         &mat_transpozed,
         (0..n)
-            .map(|i| right_side.apply((i as f64) * step + from))
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| Error::FunctionError(format!("{:?}", e)))?
+            .map(|i| checked_right_side(right_side, (i as f64) * step + from))
+            .collect::<Result<Vec<_>, _>>()?
             .as_ref(),
         &mut f,
         n,
     );
 
     let mut res = (0..n).map(|_| 0.0).collect::<Vec<_>>();
-    conjugate_gradient_method(&a, &identity, &mut res, &f, n, eps, max_iter_count);
+    let cg = conjugate_gradient(
+        &a,
+        &identity,
+        &mut res,
+        &f,
+        n,
+        eps,
+        IterationLimit::return_best(max_iter_count),
+    )?;
 
-    Ok(TableFunction::from_table(
-        res.iter()
-            .enumerate()
-            .map(|(i, y)| ((i as f64) * step + from, *y))
-            .collect(),
+    Ok((
+        TableFunction::from_sorted_unchecked(
+            res.iter()
+                .enumerate()
+                .map(|(i, y)| ((i as f64) * step + from, *y))
+                .collect(),
+        ),
+        cg,
     ))
 }
 
+/// Solves on grids of `n`, `2n - 1`, `4n - 3`, ... points, until two successive solutions
+/// differ by less than `tol` at the points they share.
+/// Returns the solution, its `n` and the conjugate gradient result of the last solve.
+#[allow(clippy::too_many_arguments)]
+pub fn fredholm_1st_system_adaptive<E1, E2>(
+    kernel: &dyn Function2d<Error = E1>,
+    right_side: &dyn Function<Error = E2>,
+    from: f64,
+    to: f64,
+    start_n: usize,
+    max_n: usize,
+    tol: f64,
+    eps: f64,
+    max_iter_count: usize,
+    symmetrize: bool,
+) -> Result<(TableFunction, usize, CgResult), Error>
+where
+    E1: Debug,
+    E2: Debug,
+{
+    if start_n < 2 {
+        return Err(Error::InvalidInput(format!(
+            "need at least 2 points, got {start_n}"
+        )));
+    }
+
+    let mut n = start_n;
+    let (mut prev, _) = fredholm_1st_system(
+        kernel,
+        right_side,
+        from,
+        to,
+        n,
+        eps,
+        max_iter_count,
+        symmetrize,
+    )?;
+
+    while 2 * n - 1 <= max_n {
+        n = 2 * n - 1;
+        let (cur, cg) = fredholm_1st_system(
+            kernel,
+            right_side,
+            from,
+            to,
+            n,
+            eps,
+            max_iter_count,
+            symmetrize,
+        )?;
+
+        let prev_pts = prev.to_table();
+        let cur_pts = cur.to_table();
+        let diff = prev_pts
+            .iter()
+            .enumerate()
+            .map(|(i, (_, y))| (y - cur_pts[2 * i].1).abs())
+            .fold(0.0, f64::max);
+
+        if diff < tol {
+            return Ok((cur, n, cg));
+        }
+        prev = cur;
+    }
+
+    Err(Error::ItersEnded)
+}
+
 #[test]
 fn fredholm_1st() -> Result<(), Error> {
     #[derive(Debug, Clone, PartialEq)]
@@ -73,7 +213,9 @@ fn fredholm_1st() -> Result<(), Error> {
     let to = 1.0;
     let n = 50;
 
-    let res = fredholm_1st_system(&kernel, &right_side, from, to, n, 1e-8, 10000)?
+    let (res, cg) = fredholm_1st_system(&kernel, &right_side, from, to, n, 1e-8, 10000, false)?;
+    assert!(cg.iterations > 0);
+    let res = res
         .sample(from, to, n)
         .map_err(|e| Error::FunctionError(format!("{:?}", e)))?;
 
@@ -85,3 +227,134 @@ fn fredholm_1st() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn fredholm_1st_adaptive() -> Result<(), Error> {
+    #[derive(Debug, Clone, PartialEq)]
+    enum DummyError {}
+
+    let kernel = |x: f64, y: f64| -> Result<f64, DummyError> { Ok((x - y).abs()) };
+    let right_side = |x: f64| -> Result<f64, DummyError> { Ok(1.0 + x * x) };
+
+    let (res, n, _) = fredholm_1st_system_adaptive(
+        &kernel,
+        &right_side,
+        -1.0,
+        1.0,
+        6,
+        200,
+        1e-6,
+        1e-10,
+        10000,
+        false,
+    )?;
+    assert!(n > 6 && n <= 41);
+
+    let pts = res.to_table();
+    assert!(pts[1..pts.len() - 1]
+        .iter()
+        .all(|(_, y)| (y - 1.0).abs() < 0.01));
+
+    assert_eq!(
+        fredholm_1st_system_adaptive(
+            &kernel,
+            &right_side,
+            -1.0,
+            1.0,
+            6,
+            10,
+            1e-6,
+            1e-10,
+            10000,
+            false
+        )
+        .err(),
+        Some(Error::ItersEnded)
+    );
+
+    // a single point never grows, and no points at all would underflow
+    let constant = |_: f64, _: f64| -> Result<f64, DummyError> { Ok(1.0) };
+    for start_n in [0, 1] {
+        assert_eq!(
+            fredholm_1st_system_adaptive(
+                &constant,
+                &right_side,
+                -1.0,
+                1.0,
+                start_n,
+                200,
+                1e-6,
+                1e-10,
+                10000,
+                false
+            )
+            .err(),
+            Some(Error::InvalidInput(format!(
+                "need at least 2 points, got {start_n}"
+            )))
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn kernel_symmetrize() -> Result<(), Error> {
+    #[derive(Debug, Clone, PartialEq)]
+    enum DummyError {}
+
+    let antisymmetric = |x: f64, s: f64| -> Result<f64, DummyError> { Ok(x - s) };
+    let mat = kernel_matrix(&antisymmetric, -1.0, 1.0, 5, true)?;
+    assert!(mat.iter().all(|k| k.abs() < 1e-12));
+
+    let symmetric = |x: f64, s: f64| -> Result<f64, DummyError> { Ok((x - s).abs() + x * s) };
+    assert_eq!(
+        kernel_matrix(&symmetric, -1.0, 1.0, 5, true)?,
+        kernel_matrix(&symmetric, -1.0, 1.0, 5, false)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn kernel_tabulated() -> Result<(), Error> {
+    use crate::mathparse::{parse_opt, DefaultRuntime, PositionalRuntime};
+    use std::cell::Cell;
+
+    let expr = parse_opt("sin(x*s)+x-s/2", &DefaultRuntime::default()).unwrap();
+    let (from, to, n) = (0.0, 2.0, 12);
+    let step = (to - from) / (n as f64 - 1.0);
+
+    let runtimes_built = Cell::new(0);
+    let direct = |x: f64, s: f64| {
+        runtimes_built.set(runtimes_built.get() + 1);
+        expr.eval(&DefaultRuntime::new(&[("x", x), ("s", s)]))
+    };
+    let mut expected = vec![];
+    for i in 0..n {
+        for j in 0..n {
+            let x = (i as f64) * step + from;
+            let s = (j as f64) * step + from;
+            let k = (direct(x, s).unwrap() + direct(s, x).unwrap()) / 2.0;
+            expected.push(k * step);
+        }
+    }
+    assert_eq!(runtimes_built.get(), 2 * n * n);
+
+    let runtime = PositionalRuntime::new(&["x", "s"]);
+    let evals = Cell::new(0);
+    let positional = |x: f64, s: f64| {
+        evals.set(evals.get() + 1);
+        runtime.set(&[x, s]);
+        expr.eval(&runtime)
+    };
+    assert_eq!(kernel_matrix(&positional, from, to, n, true)?, expected);
+    assert_eq!(evals.get(), n * n);
+
+    // the inputs are checked while tabulating, not in a pass of their own
+    evals.set(0);
+    fredholm_1st_system(&positional, &1.0, from, to, n, 1e-8, 100, false)?;
+    assert_eq!(evals.get(), n * n);
+
+    Ok(())
+}
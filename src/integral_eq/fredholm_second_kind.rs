@@ -0,0 +1,373 @@
+use crate::{
+    functions::{function::*, gauss::gauss_nodes, table_function::TableFunction},
+    iteration_limit::IterationLimit,
+    linalg::{apply, conjugate_gradient, mult_mat, CgResult},
+};
+use std::fmt::Debug;
+
+use super::{checked_kernel, checked_right_side, fredholm_first_kind::tabulate_kernel, Error};
+
+pub trait FredholmSecondKind {
+    fn solve<E1, E2>(
+        &self,
+        kernel: &dyn Function2d<Error = E1>,
+        right_side: &dyn Function<Error = E2>,
+        from: f64,
+        to: f64,
+        lambda: f64,
+    ) -> Result<TableFunction, Error>
+    where
+        E1: Debug,
+        E2: Debug;
+}
+
+/// Composite rule used to approximate the integral over the `n` grid points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quadrature {
+    Rectangle,
+    Trapezoid,
+    /// Needs an odd `n`.
+    Simpson,
+    /// The solution is found on the `n` Gauss-Legendre nodes instead of a uniform grid,
+    /// `n` from 2 to 8.
+    GaussLegendre,
+}
+
+impl Quadrature {
+    /// Weights `w_j` such that `int f(s) ds ~ sum w_j f(s_j)`.
+    pub fn weights(&self, n: usize, step: f64) -> Result<Vec<f64>, Error> {
+        match self {
+            Quadrature::Rectangle => Ok(vec![step; n]),
+            Quadrature::Trapezoid => Ok((0..n)
+                .map(|j| {
+                    if j == 0 || j == n - 1 {
+                        step / 2.0
+                    } else {
+                        step
+                    }
+                })
+                .collect()),
+            Quadrature::Simpson if n.is_multiple_of(2) => Err(Error::MethodError(format!(
+                "Simpson's rule needs an odd number of points, got {n}"
+            ))),
+            Quadrature::Simpson => Ok((0..n)
+                .map(|j| {
+                    if j == 0 || j == n - 1 {
+                        step / 3.0
+                    } else if j % 2 == 1 {
+                        step * 4.0 / 3.0
+                    } else {
+                        step * 2.0 / 3.0
+                    }
+                })
+                .collect()),
+            Quadrature::GaussLegendre => Err(Error::MethodError(
+                "Gauss-Legendre nodes are not evenly spaced".to_string(),
+            )),
+        }
+    }
+
+    /// Points `s_j` paired with their weights `w_j`.
+    pub fn grid(&self, n: usize, from: f64, to: f64) -> Result<Vec<(f64, f64)>, Error> {
+        match self {
+            Quadrature::GaussLegendre => {
+                gauss_nodes(from, to, n).map_err(|e| Error::MethodError(format!("{:?}", e)))
+            }
+            _ => {
+                let step = (to - from) / (n as f64 - 1.0);
+                Ok(self
+                    .weights(n, step)?
+                    .into_iter()
+                    .enumerate()
+                    .map(|(j, w)| ((j as f64) * step + from, w))
+                    .collect())
+            }
+        }
+    }
+}
+
+pub struct FredholmSecondKindSystemOfEquations {
+    pub n: usize,
+    pub eps: f64,
+    pub max_iter_count: usize,
+    pub quadrature: Quadrature,
+}
+
+impl FredholmSecondKind for FredholmSecondKindSystemOfEquations {
+    fn solve<E1, E2>(
+        &self,
+        kernel: &dyn Function2d<Error = E1>,
+        right_side: &dyn Function<Error = E2>,
+        from: f64,
+        to: f64,
+        lambda: f64,
+    ) -> Result<TableFunction, Error>
+    where
+        E1: Debug,
+        E2: Debug,
+    {
+        self.solve_with_info(kernel, right_side, from, to, lambda)
+            .map(|(res, _)| res)
+    }
+}
+
+impl FredholmSecondKindSystemOfEquations {
+    /// Like `solve`, but also returns how the conjugate gradient solve went.
+    pub fn solve_with_info<E1, E2>(
+        &self,
+        kernel: &dyn Function2d<Error = E1>,
+        right_side: &dyn Function<Error = E2>,
+        from: f64,
+        to: f64,
+        lambda: f64,
+    ) -> Result<(TableFunction, CgResult), Error>
+    where
+        E1: Debug,
+        E2: Debug,
+    {
+        let n = self.n;
+        let grid = self.quadrature.grid(n, from, to)?;
+
+        let mut mat = (0..n * n).map(|_| 0.0).collect::<Vec<_>>();
+        let mut mat_transpozed = (0..n * n).map(|_| 0.0).collect::<Vec<_>>();
+        let mut identity = (0..n * n).map(|_| 0.0).collect::<Vec<_>>();
+
+        for i in 0..n {
+            for j in 0..n {
+                let (x, s, w) = (grid[i].0, grid[j].0, grid[j].1);
+
+                let k = checked_kernel(kernel, x, s)?;
+                mat[i * n + j] = if i == j { 1.0 } else { 0.0 } - lambda * k * w;
+                mat_transpozed[j * n + i] = mat[i * n + j];
+            }
+            identity[i * n + i] = 1.0;
+        }
+
+        let mut a = (0..n * n).map(|_| 0.0).collect::<Vec<_>>();
+        let mut f = (0..n).map(|_| 0.0).collect::<Vec<_>>();
+
+        mult_mat(&mat_transpozed, &mat, &mut a, n);
+        apply(
+            &mat_transpozed,
+            grid.iter()
+                .map(|(x, _)| checked_right_side(right_side, *x))
+                .collect::<Result<Vec<_>, _>>()?
+                .as_ref(),
+            &mut f,
+            n,
+        );
+
+        let mut res = (0..n).map(|_| 0.0).collect::<Vec<_>>();
+        let cg = conjugate_gradient(
+            &a,
+            &identity,
+            &mut res,
+            &f,
+            n,
+            self.eps,
+            IterationLimit::return_best(self.max_iter_count),
+        )?;
+
+        Ok((
+            TableFunction::from_sorted_unchecked(
+                grid.iter().zip(&res).map(|((x, _), y)| (*x, *y)).collect(),
+            ),
+            cg,
+        ))
+    }
+}
+
+/// Iterates `y_{k+1}(x) = f(x) + lambda * int K(x,s) y_k(s) ds`, starting from `y_0 = f`.
+/// Converges only when `|lambda| * ||K||` is small enough.
+pub struct FredholmSecondKindNeumann {
+    pub n: usize,
+    pub eps: f64,
+    pub max_iter_count: usize,
+}
+
+impl FredholmSecondKind for FredholmSecondKindNeumann {
+    fn solve<E1, E2>(
+        &self,
+        kernel: &dyn Function2d<Error = E1>,
+        right_side: &dyn Function<Error = E2>,
+        from: f64,
+        to: f64,
+        lambda: f64,
+    ) -> Result<TableFunction, Error>
+    where
+        E1: Debug,
+        E2: Debug,
+    {
+        let n = self.n;
+        let step = (to - from) / (n as f64 - 1.0);
+
+        let table = tabulate_kernel(kernel, from, to, n)?;
+        let f = (0..n)
+            .map(|i| checked_right_side(right_side, (i as f64) * step + from))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut y = f.clone();
+        let mut next_y = f.clone();
+
+        for _ in 0..self.max_iter_count {
+            let mut diff: f64 = 0.0;
+            for i in 0..n {
+                let integral = (0..n).fold(0.0, |acc, j| acc + table[i * n + j] * y[j] * step);
+
+                next_y[i] = f[i] + lambda * integral;
+                diff = diff.max((next_y[i] - y[i]).abs());
+            }
+
+            std::mem::swap(&mut y, &mut next_y);
+            if !diff.is_finite() {
+                return Err(Error::Diverged);
+            }
+            if diff < self.eps {
+                return Ok(TableFunction::from_sorted_unchecked(
+                    y.iter()
+                        .enumerate()
+                        .map(|(i, y)| ((i as f64) * step + from, *y))
+                        .collect(),
+                ));
+            }
+        }
+
+        Err(Error::ItersEnded)
+    }
+}
+
+#[test]
+fn fredholm_2nd_neumann() -> Result<(), Error> {
+    #[derive(Debug, Clone, PartialEq)]
+    enum DummyError {}
+
+    let kernel = |x: f64, s: f64| -> Result<f64, DummyError> { Ok(x - s) };
+    let right_side = |x: f64| -> Result<f64, DummyError> { Ok(1.0 + x) };
+    let from = 0.0;
+    let to = 1.0;
+    let lambda = 0.5;
+    let n = 30;
+
+    let neumann = FredholmSecondKindNeumann {
+        n,
+        eps: 1e-10,
+        max_iter_count: 1000,
+    }
+    .solve(&kernel, &right_side, from, to, lambda)?;
+    let system = FredholmSecondKindSystemOfEquations {
+        n,
+        eps: 1e-10,
+        max_iter_count: 10000,
+        quadrature: Quadrature::Rectangle,
+    }
+    .solve(&kernel, &right_side, from, to, lambda)?;
+
+    assert!(neumann
+        .to_table()
+        .iter()
+        .zip(system.to_table().iter())
+        .all(|((_, a), (_, b))| (a - b).abs() < 1e-6));
+
+    let diverging = FredholmSecondKindNeumann {
+        n,
+        eps: 1e-10,
+        max_iter_count: 1000,
+    }
+    .solve(&kernel, &right_side, from, to, 100.0);
+    assert!(diverging.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn fredholm_2nd_quadrature() -> Result<(), Error> {
+    #[derive(Debug, Clone, PartialEq)]
+    enum DummyError {}
+
+    // y(x) = 1 solves y(x) - lambda * int_0^1 x * s * y(s) ds = 1 - lambda * x / 2
+    let lambda = 0.5;
+    let kernel = |x: f64, s: f64| -> Result<f64, DummyError> { Ok(x * s) };
+    let right_side = |x: f64| -> Result<f64, DummyError> { Ok(1.0 - lambda * x / 2.0) };
+    let max_error = |quadrature, n| -> Result<f64, Error> {
+        Ok(FredholmSecondKindSystemOfEquations {
+            n,
+            eps: 1e-12,
+            max_iter_count: 10000,
+            quadrature,
+        }
+        .solve(&kernel, &right_side, 0.0, 1.0, lambda)?
+        .to_table()
+        .iter()
+        .fold(0.0, |acc: f64, (_, y)| acc.max((y - 1.0).abs())))
+    };
+
+    assert!(max_error(Quadrature::Trapezoid, 5)? < 1e-6);
+    assert!(max_error(Quadrature::Simpson, 5)? < 1e-6);
+    assert!(max_error(Quadrature::Rectangle, 5)? > 1e-2);
+    assert!(max_error(Quadrature::Rectangle, 101)? > 1e-4);
+    assert!(max_error(Quadrature::GaussLegendre, 2)? < 1e-9);
+
+    assert!(matches!(
+        max_error(Quadrature::Simpson, 4),
+        Err(Error::MethodError(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn fredholm_2nd_gauss() -> Result<(), Error> {
+    use crate::functions::quadrature::integrate_adaptive;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum DummyError {}
+
+    // y(x) = cos(x) with f(x) = cos(x) - lambda * int_0^1 e^(xs) cos(s) ds
+    let lambda = 0.5;
+    let kernel = |x: f64, s: f64| -> Result<f64, DummyError> { Ok((x * s).exp()) };
+    let right_side = |x: f64| -> Result<f64, DummyError> {
+        let integrand = |s: f64| -> Result<f64, DummyError> { Ok((x * s).exp() * s.cos()) };
+        let integral = integrate_adaptive(&integrand, 0.0, 1.0, 1e-13, 50).unwrap();
+        Ok(x.cos() - lambda * integral)
+    };
+    let max_error = |quadrature, n| -> Result<f64, Error> {
+        Ok(FredholmSecondKindSystemOfEquations {
+            n,
+            eps: 1e-13,
+            max_iter_count: 10000,
+            quadrature,
+        }
+        .solve(&kernel, &right_side, 0.0, 1.0, lambda)?
+        .to_table()
+        .iter()
+        .fold(0.0, |acc: f64, (x, y)| acc.max((y - x.cos()).abs())))
+    };
+
+    let gauss = max_error(Quadrature::GaussLegendre, 6)?;
+    let simpson = max_error(Quadrature::Simpson, 7)?;
+    assert!(gauss < 1e-8, "{gauss}");
+    assert!(gauss < simpson / 100.0, "{gauss} vs {simpson}");
+
+    assert!(matches!(
+        max_error(Quadrature::GaussLegendre, 9),
+        Err(Error::MethodError(_))
+    ));
+
+    // ln(s) is only singular at s = 0, which is not a Gauss node
+    let log_kernel = |_: f64, s: f64| -> Result<f64, DummyError> { Ok(s.ln()) };
+    let solve = |quadrature| {
+        FredholmSecondKindSystemOfEquations {
+            n: 5,
+            eps: 1e-13,
+            max_iter_count: 10000,
+            quadrature,
+        }
+        .solve(&log_kernel, &1.0, 0.0, 1.0, lambda)
+    };
+    assert!(solve(Quadrature::GaussLegendre).is_ok());
+    assert_eq!(
+        solve(Quadrature::Simpson).err(),
+        Some(Error::InvalidInput("kernel(0, 0) = -inf".to_string()))
+    );
+
+    Ok(())
+}
@@ -1,16 +1,111 @@
-mod conjugate_gradients;
+use std::fmt::Debug;
+
+use crate::functions::function::{Function, Function2d};
+
 pub mod fredholm_first_kind;
+pub mod fredholm_second_kind;
+pub mod ode;
 pub mod volterra_second_kind;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     FunctionError(String),
+    ItersEnded,
+    Diverged,
+    InvalidInput(String),
+    MethodError(String),
+}
+
+/// `kernel(x, s)`, with an error or a non-finite value reported at the point it happens
+/// instead of ending up in the matrix.
+pub fn checked_kernel<E>(kernel: &dyn Function2d<Error = E>, x: f64, s: f64) -> Result<f64, Error>
+where
+    E: Debug,
+{
+    match kernel.apply(x, s) {
+        Ok(k) if k.is_finite() => Ok(k),
+        Ok(k) => Err(Error::InvalidInput(format!("kernel({x}, {s}) = {k}"))),
+        Err(e) => Err(Error::InvalidInput(format!("kernel({x}, {s}) - {:?}", e))),
+    }
+}
+
+/// `right_side(x)`, checked the same way as in `checked_kernel`.
+pub fn checked_right_side<E>(right_side: &dyn Function<Error = E>, x: f64) -> Result<f64, Error>
+where
+    E: Debug,
+{
+    match right_side.apply(x) {
+        Ok(y) if y.is_finite() => Ok(y),
+        Ok(y) => Err(Error::InvalidInput(format!("right_side({x}) = {y}"))),
+        Err(e) => Err(Error::InvalidInput(format!("right_side({x}) - {:?}", e))),
+    }
 }
 
-use crate::functions::table_function::Error as TableFunctionError;
+use crate::{functions::table_function::Error as TableFunctionError, linalg};
+
+impl From<linalg::Error> for Error {
+    fn from(e: linalg::Error) -> Self {
+        match e {
+            linalg::Error::ItersEnded => Self::ItersEnded,
+            linalg::Error::Singular => Self::FunctionError("singular matrix".to_string()),
+        }
+    }
+}
 
 impl From<TableFunctionError> for Error {
     fn from(e: TableFunctionError) -> Self {
         Self::FunctionError(format!("{:?}", e))
     }
 }
+
+#[test]
+fn inputs_reported_at_point() {
+    let kernel = |x: f64, s: f64| -> Result<f64, String> {
+        if x == 0.5 && s == 0.0 {
+            Err("bad point".to_string())
+        } else {
+            Ok(x * s)
+        }
+    };
+    let right_side = |x: f64| -> Result<f64, String> { Ok(x) };
+
+    assert_eq!(
+        fredholm_first_kind::fredholm_1st_system(
+            &kernel,
+            &right_side,
+            -1.0,
+            1.0,
+            5,
+            1e-8,
+            100,
+            false
+        )
+        .err(),
+        Some(Error::InvalidInput(
+            "kernel(0.5, 0) - \"bad point\"".to_string()
+        ))
+    );
+    assert_eq!(
+        volterra_second_kind::volterra_2nd_system(&kernel, &right_side, -1.0, 1.0, 1.0, 5).err(),
+        Some(Error::InvalidInput(
+            "kernel(0.5, 0) - \"bad point\"".to_string()
+        ))
+    );
+
+    let right_side = |x: f64| -> Result<f64, String> { Ok(1.0 / x) };
+    let kernel = |_: f64, _: f64| -> Result<f64, String> { Ok(1.0) };
+    assert_eq!(
+        fredholm_first_kind::fredholm_1st_system(
+            &kernel,
+            &right_side,
+            -1.0,
+            1.0,
+            3,
+            1e-8,
+            100,
+            false
+        )
+        .err(),
+        Some(Error::InvalidInput("right_side(0) = inf".to_string()))
+    );
+}
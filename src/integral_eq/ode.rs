@@ -0,0 +1,142 @@
+use std::{fmt::Debug, str::FromStr};
+
+use crate::functions::{function::Function2d, table_function::TableFunction};
+
+use super::Error;
+
+/// One step schemes for `y' = f(x, y)`, in the order of their accuracy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OdeMethod {
+    Euler,
+    /// Explicit trapezoid, second order.
+    Heun,
+    /// Classic fourth order Runge-Kutta.
+    Rk4,
+}
+
+impl FromStr for OdeMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "euler" => Ok(OdeMethod::Euler),
+            "heun" => Ok(OdeMethod::Heun),
+            "rk4" => Ok(OdeMethod::Rk4),
+            _ => Err(format!("{s} - expected euler, heun or rk4")),
+        }
+    }
+}
+
+/// Solves `y' = f(x, y)`, `y(x0) = y0` on `n` evenly spaced points from `x0` to `to`.
+/// `to` can be left of `x0`, the table is sorted by x either way.
+pub fn solve_ivp<E>(
+    f: &dyn Function2d<Error = E>,
+    x0: f64,
+    y0: f64,
+    to: f64,
+    n: usize,
+    method: OdeMethod,
+) -> Result<TableFunction, Error>
+where
+    E: Debug,
+{
+    if n < 2 {
+        return Err(Error::InvalidInput(format!(
+            "need at least 2 points, got {n}"
+        )));
+    }
+    if !x0.is_finite() || !to.is_finite() {
+        return Err(Error::InvalidInput(format!(
+            "x0 and to must be finite, got {x0} and {to}"
+        )));
+    }
+    if to == x0 {
+        return Err(Error::InvalidInput(format!(
+            "to must differ from x0 = {x0}"
+        )));
+    }
+
+    let f = |x, y| {
+        f.apply(x, y)
+            .map_err(|e| Error::FunctionError(format!("{:?}", e)))
+    };
+    let h = (to - x0) / (n as f64 - 1.0);
+
+    let mut pts = vec![(x0, y0)];
+    let mut y = y0;
+    for i in 0..n - 1 {
+        let x = x0 + h * (i as f64);
+        y += match method {
+            OdeMethod::Euler => h * f(x, y)?,
+            OdeMethod::Heun => {
+                let k1 = f(x, y)?;
+                let k2 = f(x + h, y + h * k1)?;
+                h * 0.5 * (k1 + k2)
+            }
+            OdeMethod::Rk4 => {
+                let k1 = f(x, y)?;
+                let k2 = f(x + h * 0.5, y + h * 0.5 * k1)?;
+                let k3 = f(x + h * 0.5, y + h * 0.5 * k2)?;
+                let k4 = f(x + h, y + h * k3)?;
+                h / 6.0 * (k1 + 2.0 * k2 + 2.0 * k3 + k4)
+            }
+        };
+        if !y.is_finite() {
+            return Err(Error::Diverged);
+        }
+        pts.push((x0 + h * ((i + 1) as f64), y));
+    }
+
+    if h < 0.0 {
+        pts.reverse();
+    }
+    Ok(TableFunction::from_sorted_unchecked(pts))
+}
+
+#[test]
+fn exponent() -> Result<(), Error> {
+    use crate::functions::function::Function;
+
+    let f = |_: f64, y: f64| -> Result<f64, Error> { Ok(y) };
+    let max_error = |method| -> Result<f64, Error> {
+        let res = solve_ivp(&f, 0.0, 1.0, 1.0, 11, method)?;
+        Ok(res
+            .to_table()
+            .iter()
+            .map(|(x, y)| (y - x.exp()).abs())
+            .fold(0.0, f64::max))
+    };
+
+    let euler = max_error(OdeMethod::Euler)?;
+    let heun = max_error(OdeMethod::Heun)?;
+    let rk4 = max_error(OdeMethod::Rk4)?;
+    assert!(rk4 < 1e-5, "{rk4}");
+    assert!(rk4 < heun && heun < euler, "{euler} {heun} {rk4}");
+
+    // backwards from y(1) = e to y(0) = 1
+    let res = solve_ivp(&f, 1.0, std::f64::consts::E, 0.0, 101, OdeMethod::Rk4)?;
+    assert_eq!(res.min_x(), Some(0.0));
+    assert!((res.apply(0.0)? - 1.0).abs() < 1e-9);
+
+    // y = 1 / (1 - x) goes to infinity at x = 1
+    let blow_up = |_: f64, y: f64| -> Result<f64, Error> { Ok(y * y) };
+    assert_eq!(
+        solve_ivp(&blow_up, 0.0, 1.0, 2.0, 1000, OdeMethod::Rk4),
+        Err(Error::Diverged)
+    );
+
+    assert!(matches!(
+        solve_ivp(&f, 1.0, 1.0, 1.0, 11, OdeMethod::Rk4),
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+        solve_ivp(&f, f64::NAN, 1.0, 1.0, 11, OdeMethod::Rk4),
+        Err(Error::InvalidInput(_))
+    ));
+    assert!(matches!(
+        solve_ivp(&f, 0.0, 1.0, f64::INFINITY, 11, OdeMethod::Rk4),
+        Err(Error::InvalidInput(_))
+    ));
+
+    Ok(())
+}
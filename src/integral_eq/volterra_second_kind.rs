@@ -1,7 +1,7 @@
 use crate::functions::{function::*, table_function::TableFunction};
 use std::fmt::Debug;
 
-use super::Error;
+use super::{checked_kernel, checked_right_side, Error};
 
 pub fn volterra_2nd_system<E1, E2>(
     kernel: &dyn Function2d<Error = E1>,
@@ -21,41 +21,21 @@ where
         .map(|x| (x, 0.0))
         .collect();
 
-    y[0].1 = right_side
-        .apply(from)
-        .map_err(|e| Error::FunctionError(format!("{:?}", e)))?;
+    y[0].1 = checked_right_side(right_side, from)?;
 
     for i in 1..n {
-        let div = 1.0
-            - lambda
-                * kernel
-                    .apply(from + step * (i as f64), from + step * (i as f64))
-                    .map_err(|e| Error::FunctionError(format!("{:?}", e)))?
-                * step
-                * 0.5;
-        let sum = 0.5
-            * kernel
-                .apply(from + step * (i as f64), from)
-                .map_err(|e| Error::FunctionError(format!("{:?}", e)))?
-            * step
-            * lambda
+        let x = from + step * (i as f64);
+        let div = 1.0 - lambda * checked_kernel(kernel, x, x)? * step * 0.5;
+        let sum = 0.5 * checked_kernel(kernel, x, from)? * step * lambda
             + step
                 * (1..i).try_fold(0.0, |acc, j| -> Result<f64, Error> {
-                    Ok(kernel
-                        .apply(from + step * (i as f64), from + step * (j as f64))
-                        .map_err(|e| Error::FunctionError(format!("{:?}", e)))?
-                        * y[j].1
-                        + acc)
+                    Ok(checked_kernel(kernel, x, from + step * (j as f64))? * y[j].1 + acc)
                 })?;
 
-        y[i].1 = (right_side
-            .apply(from + step * (i as f64))
-            .map_err(|e| Error::FunctionError(format!("{:?}", e)))?
-            + lambda * sum)
-            / div;
+        y[i].1 = (checked_right_side(right_side, x)? + lambda * sum) / div;
     }
 
-    Ok(TableFunction::from_table(y))
+    Ok(TableFunction::from_sorted_unchecked(y))
 }
 
 #[test]
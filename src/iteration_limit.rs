@@ -0,0 +1,41 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    Error,
+    ReturnBest,
+}
+
+/// How many iterations a solver may do, and what it does once they run out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IterationLimit {
+    pub max: usize,
+    pub on_exceed: Policy,
+}
+
+impl IterationLimit {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            on_exceed: Policy::Error,
+        }
+    }
+
+    pub fn return_best(max: usize) -> Self {
+        Self {
+            max,
+            on_exceed: Policy::ReturnBest,
+        }
+    }
+
+    pub fn exceeded<T, E>(&self, best: T, err: E) -> Result<T, E> {
+        match self.on_exceed {
+            Policy::Error => Err(err),
+            Policy::ReturnBest => Ok(best),
+        }
+    }
+}
+
+impl From<usize> for IterationLimit {
+    fn from(max: usize) -> Self {
+        Self::new(max)
+    }
+}
@@ -2,6 +2,8 @@ pub mod app;
 pub mod area_calc;
 pub mod functions;
 pub mod integral_eq;
+pub mod iteration_limit;
+pub mod linalg;
 pub mod mathparse;
 pub mod min_find;
 pub mod problems;
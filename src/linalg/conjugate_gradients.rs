@@ -1,3 +1,7 @@
+use crate::iteration_limit::IterationLimit;
+
+use super::Error;
+
 /*
 void discrepency(const double* mat, const double* x, const double* f, double* r,
                  uint32_t n) {
@@ -73,12 +77,12 @@ double dot(const double* a, const double* b, uint32_t n) {
 }
 */
 
-fn dot(a: &[f64], b: &[f64], _: usize) -> f64 {
+pub fn dot(a: &[f64], b: &[f64], _: usize) -> f64 {
     a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
 }
 
 /*
-MethodReturnType conjugate_gradient_method(const double* a, const double* b,
+MethodReturnType conjugate_gradient(const double* a, const double* b,
                                            const double* inv_b, double* x,
                                            const double* f, uint32_t n,
                                            double eps,
@@ -141,15 +145,23 @@ MethodReturnType conjugate_gradient_method(const double* a, const double* b,
 }
 */
 
-pub fn conjugate_gradient_method(
+/// How a `conjugate_gradient` run ended, `residual` is `|Ax - f|` at the returned `x`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CgResult {
+    pub iterations: usize,
+    pub residual: f64,
+    pub converged: bool,
+}
+
+pub fn conjugate_gradient(
     a: &[f64],
     inv_b: &[f64],
     x: &mut [f64],
     f: &[f64],
     n: usize,
     eps: f64,
-    max_iter_count: usize,
-) {
+    limit: IterationLimit,
+) -> Result<CgResult, Error> {
     let mut rk = (0..n).map(|_| 0.0).collect::<Vec<_>>();
     let mut wk = (0..n).map(|_| 0.0).collect::<Vec<_>>();
     let mut awk = (0..n).map(|_| 0.0).collect::<Vec<_>>();
@@ -158,7 +170,11 @@ pub fn conjugate_gradient_method(
     discrepency(a, &prev_x, f, &mut rk, n);
     let e = dot(&rk, &rk, n);
     if e < eps * eps {
-        return;
+        return Ok(CgResult {
+            iterations: 0,
+            residual: e.sqrt(),
+            converged: true,
+        });
     }
 
     apply(inv_b, &rk, &mut wk, n);
@@ -174,11 +190,15 @@ pub fn conjugate_gradient_method(
     let mut prev_alpha = 1.0;
     let mut prev_wkrk = wkrk;
 
-    for _ in 0..max_iter_count {
+    for iterations in 1..=limit.max {
         discrepency(a, x, f, &mut rk, n);
         let e = dot(&rk, &rk, n);
         if e < eps * eps {
-            return;
+            return Ok(CgResult {
+                iterations,
+                residual: e.sqrt(),
+                converged: true,
+            });
         }
 
         apply(inv_b, &rk, &mut wk, n);
@@ -197,4 +217,86 @@ pub fn conjugate_gradient_method(
         prev_tau = tau;
         prev_wkrk = wkrk;
     }
+
+    discrepency(a, x, f, &mut rk, n);
+    limit.exceeded(
+        CgResult {
+            iterations: limit.max,
+            residual: dot(&rk, &rk, n).sqrt(),
+            converged: false,
+        },
+        Error::ItersEnded,
+    )
+}
+
+#[test]
+fn conjugate_gradient_limit() {
+    let a = [4.0, 1.0, 0.0, 1.0, 3.0, 1.0, 0.0, 1.0, 2.0];
+    let identity = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+    let f = [1.0, 2.0, 3.0];
+    let residual = |x: &[f64]| {
+        let mut r = [0.0; 3];
+        discrepency(&a, x, &f, &mut r, 3);
+        dot(&r, &r, 3).sqrt()
+    };
+
+    let mut x = [0.0; 3];
+    assert_eq!(
+        conjugate_gradient(&a, &identity, &mut x, &f, 3, 1e-12, IterationLimit::new(1)),
+        Err(Error::ItersEnded)
+    );
+
+    let mut x = [0.0; 3];
+    let res = conjugate_gradient(
+        &a,
+        &identity,
+        &mut x,
+        &f,
+        3,
+        1e-12,
+        IterationLimit::return_best(1),
+    )
+    .unwrap();
+    assert!(!res.converged);
+    assert_eq!(res.iterations, 1);
+    assert_eq!(res.residual, residual(&x));
+    assert!(residual(&x) < residual(&[0.0; 3]) * 0.5);
+
+    let mut x = [0.0; 3];
+    let res =
+        conjugate_gradient(&a, &identity, &mut x, &f, 3, 1e-12, IterationLimit::new(10)).unwrap();
+    assert!(residual(&x) < 1e-10);
+    assert!(res.converged && res.residual < 1e-12);
+}
+
+#[test]
+fn conjugate_gradient_converges_early() {
+    let n: usize = 50;
+    // diagonally dominant, so well conditioned
+    let a = (0..n * n)
+        .map(|k| match (k / n, k % n) {
+            (i, j) if i == j => 4.0,
+            (i, j) if i.abs_diff(j) == 1 => 1.0,
+            _ => 0.0,
+        })
+        .collect::<Vec<_>>();
+    let identity = (0..n * n)
+        .map(|k| if k / n == k % n { 1.0 } else { 0.0 })
+        .collect::<Vec<_>>();
+    let f = (0..n).map(|i| i as f64).collect::<Vec<_>>();
+
+    let mut x = vec![0.0; n];
+    let res = conjugate_gradient(
+        &a,
+        &identity,
+        &mut x,
+        &f,
+        n,
+        1e-10,
+        IterationLimit::new(1000),
+    )
+    .unwrap();
+    assert!(res.converged);
+    assert!(res.iterations < 40, "{res:?}");
+    assert!(res.residual < 1e-10);
 }
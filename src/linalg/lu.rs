@@ -0,0 +1,70 @@
+use super::Error;
+
+/// Solves `a x = f` for a row-major `n` by `n` matrix, LU decomposition with partial pivoting.
+pub fn lu_solve(a: &[f64], f: &[f64], n: usize) -> Result<Vec<f64>, Error> {
+    let mut lu = a.to_vec();
+    let mut x = f.to_vec();
+
+    for k in 0..n {
+        let pivot = (k..n)
+            .max_by(|i, j| {
+                lu[i * n + k]
+                    .abs()
+                    .partial_cmp(&lu[j * n + k].abs())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(k);
+        if lu[pivot * n + k] == 0.0 || !lu[pivot * n + k].is_finite() {
+            return Err(Error::Singular);
+        }
+
+        if pivot != k {
+            for j in 0..n {
+                lu.swap(k * n + j, pivot * n + j);
+            }
+            x.swap(k, pivot);
+        }
+
+        for i in k + 1..n {
+            let m = lu[i * n + k] / lu[k * n + k];
+            lu[i * n + k] = m;
+            for j in k + 1..n {
+                lu[i * n + j] -= m * lu[k * n + j];
+            }
+            x[i] -= m * x[k];
+        }
+    }
+
+    for i in (0..n).rev() {
+        for j in i + 1..n {
+            x[i] -= lu[i * n + j] * x[j];
+        }
+        x[i] /= lu[i * n + i];
+    }
+
+    Ok(x)
+}
+
+#[test]
+fn lu() -> Result<(), Error> {
+    let a = [0.0, 2.0, 1.0, 1.0, 1.0, 1.0, 2.0, 1.0, 3.0];
+    let actual = [1.0, -2.0, 3.0];
+    let f = [
+        2.0 * -2.0 + 3.0,
+        1.0 - 2.0 + 3.0,
+        2.0 * 1.0 - 2.0 + 3.0 * 3.0,
+    ];
+
+    let x = lu_solve(&a, &f, 3)?;
+    assert!(x
+        .iter()
+        .zip(actual.iter())
+        .all(|(a, b)| (a - b).abs() < 1e-12));
+
+    assert_eq!(
+        lu_solve(&[1.0, 2.0, 2.0, 4.0], &[1.0, 2.0], 2),
+        Err(Error::Singular)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,13 @@
+mod conjugate_gradients;
+mod lu;
+mod thomas;
+
+pub use conjugate_gradients::{apply, conjugate_gradient, dot, mult_mat, CgResult};
+pub use lu::lu_solve;
+pub use thomas::thomas_solve;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    ItersEnded,
+    Singular,
+}
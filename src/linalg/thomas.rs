@@ -0,0 +1,48 @@
+use super::Error;
+
+/// Solves a tridiagonal system, row `i` is `sub[i - 1] x[i - 1] + diag[i] x[i] + sup[i] x[i + 1] = f[i]`.
+/// `sub` and `sup` are one shorter than `diag`.
+pub fn thomas_solve(sub: &[f64], diag: &[f64], sup: &[f64], f: &[f64]) -> Result<Vec<f64>, Error> {
+    let n = diag.len();
+    let mut alpha = (0..n).map(|_| 0.0).collect::<Vec<_>>();
+    let mut beta = (0..n).map(|_| 0.0).collect::<Vec<_>>();
+
+    for i in 0..n {
+        let (a, prev_alpha, prev_beta) = if i == 0 {
+            (0.0, 0.0, 0.0)
+        } else {
+            (sub[i - 1], alpha[i - 1], beta[i - 1])
+        };
+        let y = diag[i] + a * prev_alpha;
+        if y == 0.0 {
+            return Err(Error::Singular);
+        }
+
+        alpha[i] = if i + 1 < n { -sup[i] / y } else { 0.0 };
+        beta[i] = (f[i] - a * prev_beta) / y;
+    }
+
+    let mut x = beta;
+    for i in (0..n.saturating_sub(1)).rev() {
+        x[i] += alpha[i] * x[i + 1];
+    }
+
+    Ok(x)
+}
+
+#[test]
+fn thomas() -> Result<(), Error> {
+    let sub = [1.0, 1.0, 2.0];
+    let diag = [4.0, 4.0, 5.0, 3.0];
+    let sup = [1.0, -1.0, 1.0];
+    let actual = [1.0, 2.0, -1.0, 0.5];
+    let f = [6.0, 10.0, -2.5, -0.5];
+
+    let x = thomas_solve(&sub, &diag, &sup, &f)?;
+    assert!(x
+        .iter()
+        .zip(actual.iter())
+        .all(|(a, b)| (a - b).abs() < 1e-12));
+
+    Ok(())
+}
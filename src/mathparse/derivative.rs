@@ -0,0 +1,169 @@
+//! Differentiation rules, the constructors fold constants so the results stay readable.
+
+use super::expr::*;
+
+/// Value of `e` if it does not depend on any variable.
+fn number(e: &dyn Expression) -> Option<f64> {
+    if e.query_vars().is_empty() {
+        e.eval(&DefaultRuntime::default()).ok()
+    } else {
+        None
+    }
+}
+
+pub fn add(l: Box<dyn Expression>, r: Box<dyn Expression>) -> Box<dyn Expression> {
+    match (number(l.as_ref()), number(r.as_ref())) {
+        (Some(l), Some(r)) => Box::new(l + r),
+        (Some(0.0), _) => r,
+        (_, Some(0.0)) => l,
+        _ => Box::new(BasicOp::Plus(l, r)),
+    }
+}
+
+pub fn sub(l: Box<dyn Expression>, r: Box<dyn Expression>) -> Box<dyn Expression> {
+    match (number(l.as_ref()), number(r.as_ref())) {
+        (Some(l), Some(r)) => Box::new(l - r),
+        (Some(0.0), _) => neg(r),
+        (_, Some(0.0)) => l,
+        _ => Box::new(BasicOp::Minus(l, r)),
+    }
+}
+
+pub fn mul(l: Box<dyn Expression>, r: Box<dyn Expression>) -> Box<dyn Expression> {
+    match (number(l.as_ref()), number(r.as_ref())) {
+        (Some(l), Some(r)) => Box::new(l * r),
+        (Some(0.0), _) | (_, Some(0.0)) => Box::new(0.0),
+        (Some(1.0), _) => r,
+        (_, Some(1.0)) => l,
+        _ => Box::new(BasicOp::Multiply(l, r)),
+    }
+}
+
+pub fn div(l: Box<dyn Expression>, r: Box<dyn Expression>) -> Box<dyn Expression> {
+    match (number(l.as_ref()), number(r.as_ref())) {
+        (Some(l), Some(r)) if r != 0.0 => Box::new(l / r),
+        (Some(0.0), _) => Box::new(0.0),
+        (_, Some(1.0)) => l,
+        _ => Box::new(BasicOp::Divide(l, r)),
+    }
+}
+
+pub fn neg(e: Box<dyn Expression>) -> Box<dyn Expression> {
+    match number(e.as_ref()) {
+        Some(v) => Box::new(-v),
+        None => Box::new(BasicOp::Negate(e)),
+    }
+}
+
+pub fn pow(base: Box<dyn Expression>, exp: Box<dyn Expression>) -> Box<dyn Expression> {
+    match (number(base.as_ref()), number(exp.as_ref())) {
+        (Some(b), Some(e)) => Box::new(b.powf(e)),
+        (_, Some(0.0)) => Box::new(1.0),
+        (_, Some(1.0)) => base,
+        _ => Box::new(BasicOp::Power(base, exp)),
+    }
+}
+
+fn call(name: &str, args: Vec<Box<dyn Expression>>) -> Box<dyn Expression> {
+    FunctionExpression::new_expression(args, name.to_string())
+}
+
+/// `d(u^v) = v u^(v-1) du` for a constant `v`, otherwise `u^v (dv ln(u) + v du / u)`.
+pub fn power_derivative(
+    u: &dyn Expression,
+    v: &dyn Expression,
+    var: &str,
+) -> Result<Box<dyn Expression>, Error> {
+    let du = u.derivative(var)?;
+    let dv = v.derivative(var)?;
+
+    if number(dv.as_ref()) == Some(0.0) {
+        return Ok(mul(
+            mul(
+                v.boxed_clone(),
+                pow(u.boxed_clone(), sub(v.boxed_clone(), Box::new(1.0))),
+            ),
+            du,
+        ));
+    }
+
+    Ok(mul(
+        pow(u.boxed_clone(), v.boxed_clone()),
+        add(
+            mul(dv, call("ln", vec![u.boxed_clone()])),
+            div(mul(v.boxed_clone(), du), u.boxed_clone()),
+        ),
+    ))
+}
+
+/// Chain rule for the functions of `DefaultRuntime`.
+pub fn function_derivative(
+    name: &str,
+    args: &[Box<dyn Expression>],
+    var: &str,
+) -> Result<Box<dyn Expression>, Error> {
+    let arg = |i: usize| args[i].boxed_clone();
+    let square = |e: Box<dyn Expression>| pow(e, Box::new(2.0));
+
+    let res = match (name, args.len()) {
+        ("sin", 1) => mul(call("cos", vec![arg(0)]), args[0].derivative(var)?),
+        ("cos", 1) => neg(mul(call("sin", vec![arg(0)]), args[0].derivative(var)?)),
+        ("tan", 1) => div(args[0].derivative(var)?, square(call("cos", vec![arg(0)]))),
+        ("asin", 1) | ("acos", 1) => {
+            let d = div(
+                args[0].derivative(var)?,
+                call("sqrt", vec![sub(Box::new(1.0), square(arg(0)))]),
+            );
+            if name == "acos" {
+                neg(d)
+            } else {
+                d
+            }
+        }
+        ("atan", 1) => div(args[0].derivative(var)?, add(Box::new(1.0), square(arg(0)))),
+        ("atan2", 2) => div(
+            sub(
+                mul(arg(1), args[0].derivative(var)?),
+                mul(arg(0), args[1].derivative(var)?),
+            ),
+            add(square(arg(0)), square(arg(1))),
+        ),
+        ("sinh", 1) => mul(call("cosh", vec![arg(0)]), args[0].derivative(var)?),
+        ("cosh", 1) => mul(call("sinh", vec![arg(0)]), args[0].derivative(var)?),
+        ("tanh", 1) => div(args[0].derivative(var)?, square(call("cosh", vec![arg(0)]))),
+        ("exp", 1) => mul(call("exp", vec![arg(0)]), args[0].derivative(var)?),
+        ("ln", 1) => div(args[0].derivative(var)?, arg(0)),
+        ("log", 2) => {
+            BasicOp::Divide(call("ln", vec![arg(1)]), call("ln", vec![arg(0)])).derivative(var)?
+        }
+        ("sqrt", 1) => div(
+            args[0].derivative(var)?,
+            mul(Box::new(2.0), call("sqrt", vec![arg(0)])),
+        ),
+        ("pow", 2) => power_derivative(args[0].as_ref(), args[1].as_ref(), var)?,
+        ("abs", 1) => mul(
+            div(arg(0), call("abs", vec![arg(0)])),
+            args[0].derivative(var)?,
+        ),
+        ("hypot", 2) => div(
+            add(
+                mul(arg(0), args[0].derivative(var)?),
+                mul(arg(1), args[1].derivative(var)?),
+            ),
+            call("hypot", vec![arg(0), arg(1)]),
+        ),
+        ("lt", 2) | ("gt", 2) | ("le", 2) | ("ge", 2) | ("eq", 2) => Box::new(0.0),
+        ("if", 3) => call(
+            "if",
+            vec![arg(0), args[1].derivative(var)?, args[2].derivative(var)?],
+        ),
+        ("sum", _) => args
+            .iter()
+            .map(|a| a.derivative(var))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .fold(Box::new(0.0) as Box<dyn Expression>, add),
+        _ => return Err(Error::NotDifferentiable(name.to_string())),
+    };
+    Ok(res)
+}
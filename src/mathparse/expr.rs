@@ -1,4 +1,5 @@
 use std::{
+    cell::Cell,
     collections::{HashMap, HashSet},
     fmt::Debug,
 };
@@ -14,6 +15,15 @@ pub enum Error {
     },
 
     Math(String),
+    Parse(super::ParseError),
+    NotDifferentiable(String),
+}
+
+/// Names that `DefaultRuntime` knows without them being bound.
+pub const CONSTANTS: [(&str, f64); 2] = [("pi", std::f64::consts::PI), ("e", std::f64::consts::E)];
+
+fn constant(name: &str) -> Option<f64> {
+    CONSTANTS.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)
 }
 
 pub trait Runtime {
@@ -26,7 +36,37 @@ pub trait Runtime {
 pub trait Expression: Debug {
     fn eval(&self, runtime: &dyn Runtime) -> Result<f64, Error>;
     fn query_vars(&self) -> HashSet<&str>;
+    /// Names of the functions called anywhere in the expression.
+    fn query_funcs(&self) -> HashSet<&str>;
     fn to_latex(&self, runtime: &dyn Runtime) -> Result<String, Error>;
+    fn boxed_clone(&self) -> Box<dyn Expression>;
+    /// Symbolic derivative with respect to `var`.
+    fn derivative(&self, var: &str) -> Result<Box<dyn Expression>, Error>;
+    /// Substitutes the variables `runtime` knows and folds the constant subtrees.
+    fn compile(&self, runtime: &dyn Runtime) -> Result<Box<dyn Expression>, Error>;
+    fn to_number(&self) -> Option<f64> {
+        None
+    }
+    /// Infix form that `parse` reads back into the same tree.
+    fn to_infix(&self) -> String;
+    /// How tightly the infix form binds, see `to_infix`.
+    fn precedence(&self) -> u8 {
+        ATOM
+    }
+}
+
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const NEGATION: u8 = 3;
+const POWER: u8 = 4;
+const ATOM: u8 = 5;
+
+fn infix_operand(e: &dyn Expression, min_precedence: u8) -> String {
+    if e.precedence() < min_precedence {
+        format!("({})", e.to_infix())
+    } else {
+        e.to_infix()
+    }
 }
 
 impl Expression for f64 {
@@ -38,9 +78,46 @@ impl Expression for f64 {
         HashSet::new()
     }
 
+    fn query_funcs(&self) -> HashSet<&str> {
+        HashSet::new()
+    }
+
     fn to_latex(&self, _: &dyn Runtime) -> Result<String, Error> {
         Ok(self.to_string())
     }
+
+    fn boxed_clone(&self) -> Box<dyn Expression> {
+        Box::new(*self)
+    }
+
+    fn derivative(&self, _: &str) -> Result<Box<dyn Expression>, Error> {
+        Ok(Box::new(0.0))
+    }
+
+    fn compile(&self, _: &dyn Runtime) -> Result<Box<dyn Expression>, Error> {
+        Ok(Box::new(*self))
+    }
+
+    fn to_number(&self) -> Option<f64> {
+        Some(*self)
+    }
+
+    fn to_infix(&self) -> String {
+        let s = self.to_string();
+        if s.len() > 12 {
+            format!("{:e}", self)
+        } else {
+            s
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        if self.is_sign_negative() {
+            NEGATION
+        } else {
+            ATOM
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -62,11 +139,42 @@ impl Expression for Variable {
     }
 
     fn query_vars(&self) -> HashSet<&str> {
-        HashSet::from([self.name.as_str()])
+        if constant(&self.name).is_some() {
+            HashSet::new()
+        } else {
+            HashSet::from([self.name.as_str()])
+        }
+    }
+
+    fn query_funcs(&self) -> HashSet<&str> {
+        HashSet::new()
     }
 
     fn to_latex(&self, _: &dyn Runtime) -> Result<String, Error> {
-        Ok(self.name.clone())
+        if self.name == "pi" {
+            Ok("\\pi".to_string())
+        } else {
+            Ok(self.name.clone())
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn Expression>, Error> {
+        Ok(Box::new(if self.name == var { 1.0 } else { 0.0 }))
+    }
+
+    fn compile(&self, runtime: &dyn Runtime) -> Result<Box<dyn Expression>, Error> {
+        match runtime.get_var(&self.name) {
+            Some(v) => Ok(Box::new(v)),
+            None => Ok(Box::new(self.clone())),
+        }
+    }
+
+    fn to_infix(&self) -> String {
+        self.name.clone()
     }
 }
 
@@ -76,7 +184,10 @@ pub enum BasicOp {
     Minus(Box<dyn Expression>, Box<dyn Expression>),
     Multiply(Box<dyn Expression>, Box<dyn Expression>),
     Divide(Box<dyn Expression>, Box<dyn Expression>),
+    /// `a % b`, always in `[0, |b|)`, same as the `mod` function.
+    Mod(Box<dyn Expression>, Box<dyn Expression>),
     Negate(Box<dyn Expression>),
+    Power(Box<dyn Expression>, Box<dyn Expression>),
 }
 
 impl Expression for BasicOp {
@@ -101,7 +212,20 @@ impl Expression for BasicOp {
                         Ok(l / r)
                     }
                 }),
+            BasicOp::Mod(left, right) => left
+                .eval(runtime)
+                .and_then(|l| right.eval(runtime).map(|r| (l, r)))
+                .and_then(|(l, r)| {
+                    if r == 0.0 {
+                        Err(Error::Math("Mod by zero".to_owned()))
+                    } else {
+                        Ok(l.rem_euclid(r))
+                    }
+                }),
             BasicOp::Negate(r) => r.eval(runtime).map(|res| -res),
+            BasicOp::Power(left, right) => left
+                .eval(runtime)
+                .and_then(|l| right.eval(runtime).map(|r| l.powf(r))),
         }
     }
 
@@ -111,7 +235,21 @@ impl Expression for BasicOp {
             BasicOp::Minus(l, r) => l.query_vars().union(&r.query_vars()).copied().collect(),
             BasicOp::Multiply(l, r) => l.query_vars().union(&r.query_vars()).copied().collect(),
             BasicOp::Divide(l, r) => l.query_vars().union(&r.query_vars()).copied().collect(),
+            BasicOp::Mod(l, r) => l.query_vars().union(&r.query_vars()).copied().collect(),
             BasicOp::Negate(l) => l.query_vars(),
+            BasicOp::Power(l, r) => l.query_vars().union(&r.query_vars()).copied().collect(),
+        }
+    }
+
+    fn query_funcs(&self) -> HashSet<&str> {
+        match self {
+            BasicOp::Plus(l, r)
+            | BasicOp::Minus(l, r)
+            | BasicOp::Multiply(l, r)
+            | BasicOp::Divide(l, r)
+            | BasicOp::Mod(l, r)
+            | BasicOp::Power(l, r) => l.query_funcs().union(&r.query_funcs()).copied().collect(),
+            BasicOp::Negate(l) => l.query_funcs(),
         }
     }
 
@@ -137,10 +275,133 @@ impl Expression for BasicOp {
                 let r = r.to_latex(runtime)?;
                 Ok(format!("{{{}}}\\over{{{}}}", l, r))
             }
+            BasicOp::Mod(l, r) => {
+                let l = l.to_latex(runtime)?;
+                let r = r.to_latex(runtime)?;
+                Ok(format!("{{{}}} \\bmod {{{}}}", l, r))
+            }
             BasicOp::Negate(r) => {
                 let r = r.to_latex(runtime)?;
                 Ok(format!("-{{{}}}", r))
             }
+            BasicOp::Power(l, r) => {
+                let l = l.to_latex(runtime)?;
+                let r = r.to_latex(runtime)?;
+                Ok(format!("{{{}}}^{{{}}}", l, r))
+            }
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Expression> {
+        Box::new(match self {
+            BasicOp::Plus(l, r) => BasicOp::Plus(l.boxed_clone(), r.boxed_clone()),
+            BasicOp::Minus(l, r) => BasicOp::Minus(l.boxed_clone(), r.boxed_clone()),
+            BasicOp::Multiply(l, r) => BasicOp::Multiply(l.boxed_clone(), r.boxed_clone()),
+            BasicOp::Divide(l, r) => BasicOp::Divide(l.boxed_clone(), r.boxed_clone()),
+            BasicOp::Mod(l, r) => BasicOp::Mod(l.boxed_clone(), r.boxed_clone()),
+            BasicOp::Negate(r) => BasicOp::Negate(r.boxed_clone()),
+            BasicOp::Power(l, r) => BasicOp::Power(l.boxed_clone(), r.boxed_clone()),
+        })
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn Expression>, Error> {
+        use super::derivative::*;
+
+        Ok(match self {
+            BasicOp::Plus(l, r) => add(l.derivative(var)?, r.derivative(var)?),
+            BasicOp::Minus(l, r) => sub(l.derivative(var)?, r.derivative(var)?),
+            BasicOp::Multiply(l, r) => add(
+                mul(l.derivative(var)?, r.boxed_clone()),
+                mul(l.boxed_clone(), r.derivative(var)?),
+            ),
+            BasicOp::Divide(l, r) => div(
+                sub(
+                    mul(l.derivative(var)?, r.boxed_clone()),
+                    mul(l.boxed_clone(), r.derivative(var)?),
+                ),
+                pow(r.boxed_clone(), Box::new(2.0)),
+            ),
+            BasicOp::Mod(..) => return Err(Error::NotDifferentiable("%".to_string())),
+            BasicOp::Negate(r) => neg(r.derivative(var)?),
+            BasicOp::Power(l, r) => power_derivative(l.as_ref(), r.as_ref(), var)?,
+        })
+    }
+
+    fn compile(&self, runtime: &dyn Runtime) -> Result<Box<dyn Expression>, Error> {
+        let compiled = match self {
+            BasicOp::Plus(l, r) => BasicOp::Plus(l.compile(runtime)?, r.compile(runtime)?),
+            BasicOp::Minus(l, r) => BasicOp::Minus(l.compile(runtime)?, r.compile(runtime)?),
+            BasicOp::Multiply(l, r) => BasicOp::Multiply(l.compile(runtime)?, r.compile(runtime)?),
+            BasicOp::Divide(l, r) => BasicOp::Divide(l.compile(runtime)?, r.compile(runtime)?),
+            BasicOp::Mod(l, r) => BasicOp::Mod(l.compile(runtime)?, r.compile(runtime)?),
+            BasicOp::Negate(r) => BasicOp::Negate(r.compile(runtime)?),
+            BasicOp::Power(l, r) => BasicOp::Power(l.compile(runtime)?, r.compile(runtime)?),
+        };
+
+        let constant = match &compiled {
+            BasicOp::Plus(l, r)
+            | BasicOp::Minus(l, r)
+            | BasicOp::Multiply(l, r)
+            | BasicOp::Divide(l, r)
+            | BasicOp::Mod(l, r)
+            | BasicOp::Power(l, r) => l.to_number().is_some() && r.to_number().is_some(),
+            BasicOp::Negate(r) => r.to_number().is_some(),
+        };
+
+        if constant {
+            Ok(Box::new(compiled.eval(runtime)?))
+        } else {
+            Ok(Box::new(compiled))
+        }
+    }
+
+    fn to_infix(&self) -> String {
+        // the right operand of `*` and `/` is a power in the grammar, so `a*-b` is `a*(-b)`
+        match self {
+            BasicOp::Plus(l, r) => {
+                format!(
+                    "{}+{}",
+                    infix_operand(l.as_ref(), SUM),
+                    infix_operand(r.as_ref(), PRODUCT)
+                )
+            }
+            BasicOp::Minus(l, r) => {
+                format!(
+                    "{}-{}",
+                    infix_operand(l.as_ref(), SUM),
+                    infix_operand(r.as_ref(), PRODUCT)
+                )
+            }
+            BasicOp::Multiply(l, r) => format!(
+                "{}*{}",
+                infix_operand(l.as_ref(), PRODUCT),
+                infix_operand(r.as_ref(), POWER)
+            ),
+            BasicOp::Divide(l, r) => format!(
+                "{}/{}",
+                infix_operand(l.as_ref(), PRODUCT),
+                infix_operand(r.as_ref(), POWER)
+            ),
+            BasicOp::Mod(l, r) => format!(
+                "{}%{}",
+                infix_operand(l.as_ref(), PRODUCT),
+                infix_operand(r.as_ref(), POWER)
+            ),
+            BasicOp::Negate(r) => format!("-{}", infix_operand(r.as_ref(), NEGATION)),
+            BasicOp::Power(l, r) => format!(
+                "{}^{}",
+                infix_operand(l.as_ref(), ATOM),
+                infix_operand(r.as_ref(), POWER)
+            ),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            BasicOp::Plus(..) | BasicOp::Minus(..) => SUM,
+            BasicOp::Multiply(..) | BasicOp::Divide(..) | BasicOp::Mod(..) => PRODUCT,
+            BasicOp::Negate(..) => NEGATION,
+            BasicOp::Power(..) => POWER,
         }
     }
 }
@@ -157,8 +418,30 @@ impl FunctionExpression {
     }
 }
 
+impl FunctionExpression {
+    /// `if(c, a, b)` with the right number of arguments. Only the branch it picks is
+    /// evaluated, so `if(gt(x,0), ln(x), 0)` is fine for `x <= 0`. Because of that
+    /// `if` can not be shadowed by `DefaultRuntime::with_function`.
+    fn as_if(&self) -> Option<(&dyn Expression, &dyn Expression, &dyn Expression)> {
+        match self.args.as_slice() {
+            [cond, then, otherwise] if self.name == "if" => {
+                Some((cond.as_ref(), then.as_ref(), otherwise.as_ref()))
+            }
+            _ => None,
+        }
+    }
+}
+
 impl Expression for FunctionExpression {
     fn eval(&self, runtime: &dyn Runtime) -> Result<f64, Error> {
+        if let Some((cond, then, otherwise)) = self.as_if() {
+            return if cond.eval(runtime)? != 0.0 {
+                then.eval(runtime)
+            } else {
+                otherwise.eval(runtime)
+            };
+        }
+
         let calculated_args = self
             .args
             .iter()
@@ -177,6 +460,15 @@ impl Expression for FunctionExpression {
             })
     }
 
+    fn query_funcs(&self) -> HashSet<&str> {
+        self.args
+            .iter()
+            .map(|a| a.query_funcs())
+            .fold(HashSet::from([self.name.as_str()]), |acc, funcs| {
+                acc.union(&funcs).copied().collect()
+            })
+    }
+
     fn to_latex(&self, runtime: &dyn Runtime) -> Result<String, Error> {
         let args = self
             .args
@@ -185,33 +477,328 @@ impl Expression for FunctionExpression {
             .collect::<Result<Vec<_>, _>>()?;
         runtime.to_latex(&self.name, &args)
     }
+
+    fn boxed_clone(&self) -> Box<dyn Expression> {
+        FunctionExpression::new_expression(
+            self.args.iter().map(|a| a.boxed_clone()).collect(),
+            self.name.clone(),
+        )
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn Expression>, Error> {
+        super::derivative::function_derivative(&self.name, &self.args, var)
+    }
+
+    fn to_infix(&self) -> String {
+        let args = self
+            .args
+            .iter()
+            .map(|a| a.to_infix())
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{}({})", self.name, args)
+    }
+
+    fn compile(&self, runtime: &dyn Runtime) -> Result<Box<dyn Expression>, Error> {
+        if let Some((cond, then, otherwise)) = self.as_if() {
+            let cond = cond.compile(runtime)?;
+            return match cond.to_number() {
+                Some(c) if c != 0.0 => then.compile(runtime),
+                Some(_) => otherwise.compile(runtime),
+                // a branch that fails to fold may never be taken, it is kept as it is
+                None => Ok(FunctionExpression::new_expression(
+                    vec![
+                        cond,
+                        then.compile(runtime).unwrap_or_else(|_| then.boxed_clone()),
+                        otherwise
+                            .compile(runtime)
+                            .unwrap_or_else(|_| otherwise.boxed_clone()),
+                    ],
+                    self.name.clone(),
+                )),
+            };
+        }
+
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.compile(runtime))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match args
+            .iter()
+            .map(|a| a.to_number())
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(values) => Ok(Box::new(runtime.eval_func(&self.name, &values)?)),
+            None => Ok(FunctionExpression::new_expression(args, self.name.clone())),
+        }
+    }
+}
+
+/// Wraps an expression, variables in `bound` are constants unless the runtime defines them.
+#[derive(Debug)]
+pub struct BoundExpression {
+    expr: Box<dyn Expression>,
+    bound: HashMap<String, f64>,
+}
+
+impl BoundExpression {
+    pub fn new_expression(
+        expr: Box<dyn Expression>,
+        bound: HashMap<String, f64>,
+    ) -> Box<dyn Expression> {
+        Box::new(Self { expr, bound })
+    }
+}
+
+struct BoundRuntime<'a> {
+    inner: &'a dyn Runtime,
+    bound: &'a HashMap<String, f64>,
+}
+
+impl<'a> Runtime for BoundRuntime<'a> {
+    fn get_var(&self, name: &str) -> Option<f64> {
+        self.inner
+            .get_var(name)
+            .or_else(|| self.bound.get(name).copied())
+    }
+
+    fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, Error> {
+        self.inner.eval_func(name, args)
+    }
+
+    fn has_func(&self, name: &str) -> bool {
+        self.inner.has_func(name)
+    }
+
+    fn to_latex(&self, name: &str, args: &[String]) -> Result<String, Error> {
+        self.inner.to_latex(name, args)
+    }
+}
+
+impl Expression for BoundExpression {
+    fn eval(&self, runtime: &dyn Runtime) -> Result<f64, Error> {
+        self.expr.eval(&BoundRuntime {
+            inner: runtime,
+            bound: &self.bound,
+        })
+    }
+
+    fn query_vars(&self) -> HashSet<&str> {
+        self.expr
+            .query_vars()
+            .into_iter()
+            .filter(|v| !self.bound.contains_key(*v))
+            .collect()
+    }
+
+    fn query_funcs(&self) -> HashSet<&str> {
+        self.expr.query_funcs()
+    }
+
+    fn to_latex(&self, runtime: &dyn Runtime) -> Result<String, Error> {
+        self.expr.to_latex(runtime)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Expression> {
+        BoundExpression::new_expression(self.expr.boxed_clone(), self.bound.clone())
+    }
+
+    fn derivative(&self, var: &str) -> Result<Box<dyn Expression>, Error> {
+        Ok(BoundExpression::new_expression(
+            self.expr.derivative(var)?,
+            self.bound.clone(),
+        ))
+    }
+
+    fn compile(&self, runtime: &dyn Runtime) -> Result<Box<dyn Expression>, Error> {
+        self.expr.compile(&BoundRuntime {
+            inner: runtime,
+            bound: &self.bound,
+        })
+    }
+
+    fn to_infix(&self) -> String {
+        self.expr.to_infix()
+    }
+
+    fn precedence(&self) -> u8 {
+        self.expr.precedence()
+    }
+}
+
+/// Variables are looked up by their position in `names`, so a single runtime can be
+/// reused for many evaluations instead of building a `DefaultRuntime` every time.
+#[derive(Debug)]
+pub struct PositionalRuntime<'a> {
+    names: &'a [&'a str],
+    values: Vec<Cell<f64>>,
+    functions: DefaultRuntime,
+}
+
+impl<'a> PositionalRuntime<'a> {
+    pub fn new(names: &'a [&'a str]) -> Self {
+        Self {
+            names,
+            values: names.iter().map(|_| Cell::new(0.0)).collect(),
+            functions: DefaultRuntime::default(),
+        }
+    }
+
+    /// `values[i]` becomes the value of `names[i]`.
+    pub fn set(&self, values: &[f64]) {
+        for (cell, val) in self.values.iter().zip(values) {
+            cell.set(*val);
+        }
+    }
+}
+
+impl<'a> Runtime for PositionalRuntime<'a> {
+    fn get_var(&self, name: &str) -> Option<f64> {
+        self.names
+            .iter()
+            .position(|n| *n == name)
+            .map(|i| self.values[i].get())
+            .or_else(|| self.functions.get_var(name))
+    }
+
+    fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, Error> {
+        self.functions.eval_func(name, args)
+    }
+
+    fn has_func(&self, name: &str) -> bool {
+        self.functions.has_func(name)
+    }
+
+    fn to_latex(&self, name: &str, args: &[String]) -> Result<String, Error> {
+        self.functions.to_latex(name, args)
+    }
+}
+
+/// The unit of the arguments of `sin`, `cos` and `tan` and of the results of their inverses.
+/// `Expression::derivative` always assumes radians.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AngleMode {
+    #[default]
+    Radians,
+    Degrees,
 }
 
-#[derive(Default, Debug)]
+/// Arity and body of a function added with `DefaultRuntime::with_function`.
+type UserFunction = (usize, Box<dyn Fn(&[f64]) -> Result<f64, Error>>);
+
+#[derive(Default)]
 pub struct DefaultRuntime {
     vars: HashMap<String, f64>,
+    functions: HashMap<String, UserFunction>,
+    angle_mode: AngleMode,
+}
+
+impl Debug for DefaultRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DefaultRuntime")
+            .field("vars", &self.vars)
+            .field(
+                "functions",
+                &self
+                    .functions
+                    .iter()
+                    .map(|(name, (arity, _))| (name, arity))
+                    .collect::<HashMap<_, _>>(),
+            )
+            .field("angle_mode", &self.angle_mode)
+            .finish()
+    }
 }
 
 impl DefaultRuntime {
     pub fn new(vars: &[(&str, f64)]) -> Self {
         Self {
             vars: HashMap::from_iter(vars.iter().map(|(n, v)| (n.to_string(), *v))),
+            functions: HashMap::new(),
+            angle_mode: AngleMode::Radians,
+        }
+    }
+
+    pub fn set_var(&mut self, name: &str, val: f64) {
+        self.vars.insert(name.to_string(), val);
+    }
+
+    /// Adds a function of `arity` arguments, it shadows a built-in one of the same name
+    /// (except `if`, which the expression tree evaluates itself).
+    pub fn with_function<F>(mut self, name: &str, arity: usize, f: F) -> Self
+    where
+        F: Fn(&[f64]) -> Result<f64, Error> + 'static,
+    {
+        self.functions
+            .insert(name.to_string(), (arity, Box::new(f)));
+        self
+    }
+
+    pub fn with_angle_mode(mut self, angle_mode: AngleMode) -> Self {
+        self.angle_mode = angle_mode;
+        self
+    }
+
+    fn angle_to_radians(&self, angle: f64) -> f64 {
+        match self.angle_mode {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_radians(),
+        }
+    }
+
+    fn angle_from_radians(&self, angle: f64) -> f64 {
+        match self.angle_mode {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_degrees(),
         }
     }
+
+    /// `{arg}`, with a degree sign in degrees mode.
+    fn angle_to_latex(&self, arg: &str) -> String {
+        match self.angle_mode {
+            AngleMode::Radians => format!("{{{}}}", arg),
+            AngleMode::Degrees => format!("{{{}}}^\\circ", arg),
+        }
+    }
+
+    fn user_function(&self, name: &str, args: usize) -> Option<Result<&UserFunction, Error>> {
+        self.functions.get(name).map(|func| {
+            if func.0 == args {
+                Ok(func)
+            } else {
+                Err(Error::InvalidArgCount {
+                    op_name: name.to_string(),
+                    got_args: args,
+                    expected_args: func.0,
+                })
+            }
+        })
+    }
 }
 
 impl Runtime for DefaultRuntime {
     fn get_var(&self, name: &str) -> Option<f64> {
-        self.vars.get(name).copied()
+        self.vars.get(name).copied().or_else(|| constant(name))
     }
 
     fn has_func(&self, name: &str) -> bool {
-        ["sin", "cos", "pow", "exp", "sqrt", "ln", "abs"]
+        self.functions.contains_key(name)
+            || [
+                "sin", "cos", "tan", "asin", "acos", "atan", "atan2", "sinh", "cosh", "tanh",
+                "pow", "exp", "sqrt", "ln", "log", "abs", "hypot", "mod", "min", "max", "sum",
+                "floor", "ceil", "round", "sign", "clamp", "lt", "gt", "le", "ge", "eq", "if",
+            ]
             .into_iter()
             .any(|v| v.eq(name))
     }
 
     fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, Error> {
+        if let Some(func) = self.user_function(name, args.len()) {
+            return func.and_then(|(_, f)| f(args));
+        }
+
         match name {
             "sin" => {
                 if args.len() != 1 {
@@ -221,7 +808,7 @@ impl Runtime for DefaultRuntime {
                         expected_args: 1,
                     })
                 } else {
-                    Ok(args[0].sin())
+                    Ok(self.angle_to_radians(args[0]).sin())
                 }
             }
             "cos" => {
@@ -232,7 +819,99 @@ impl Runtime for DefaultRuntime {
                         expected_args: 1,
                     })
                 } else {
-                    Ok(args[0].cos())
+                    Ok(self.angle_to_radians(args[0]).cos())
+                }
+            }
+            "tan" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "tan".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(self.angle_to_radians(args[0]).tan())
+                }
+            }
+            "asin" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "asin".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else if !(-1.0..=1.0).contains(&args[0]) {
+                    Err(Error::Math("Asin of a value outside [-1, 1]".to_owned()))
+                } else {
+                    Ok(self.angle_from_radians(args[0].asin()))
+                }
+            }
+            "acos" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "acos".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else if !(-1.0..=1.0).contains(&args[0]) {
+                    Err(Error::Math("Acos of a value outside [-1, 1]".to_owned()))
+                } else {
+                    Ok(self.angle_from_radians(args[0].acos()))
+                }
+            }
+            "atan" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "atan".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(self.angle_from_radians(args[0].atan()))
+                }
+            }
+            "atan2" => {
+                if args.len() != 2 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "atan2".to_string(),
+                        got_args: args.len(),
+                        expected_args: 2,
+                    })
+                } else {
+                    Ok(self.angle_from_radians(args[0].atan2(args[1])))
+                }
+            }
+            "sinh" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "sinh".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(args[0].sinh())
+                }
+            }
+            "cosh" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "cosh".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(args[0].cosh())
+                }
+            }
+            "tanh" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "tanh".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(args[0].tanh())
                 }
             }
             "pow" => {
@@ -283,6 +962,23 @@ impl Runtime for DefaultRuntime {
                     Ok(args[0].ln())
                 }
             }
+            "log" => {
+                if args.len() != 2 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "log".to_string(),
+                        got_args: args.len(),
+                        expected_args: 2,
+                    })
+                } else if args[0] <= 0.0 || args[0] == 1.0 {
+                    Err(Error::Math(
+                        "Log base must be positive and not 1".to_owned(),
+                    ))
+                } else if args[1] <= 0.0 {
+                    Err(Error::Math("Log of non-positive".to_owned()))
+                } else {
+                    Ok(args[1].ln() / args[0].ln())
+                }
+            }
             "abs" => {
                 if args.len() != 1 {
                     Err(Error::InvalidArgCount {
@@ -294,11 +990,141 @@ impl Runtime for DefaultRuntime {
                     Ok(args[0].abs())
                 }
             }
+            "hypot" => {
+                if args.len() != 2 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "hypot".to_string(),
+                        got_args: args.len(),
+                        expected_args: 2,
+                    })
+                } else {
+                    Ok(args[0].hypot(args[1]))
+                }
+            }
+            "mod" => {
+                if args.len() != 2 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "mod".to_string(),
+                        got_args: args.len(),
+                        expected_args: 2,
+                    })
+                } else if args[1] == 0.0 {
+                    Err(Error::Math("Mod by zero".to_owned()))
+                } else {
+                    Ok(args[0].rem_euclid(args[1]))
+                }
+            }
+            "min" => {
+                if args.is_empty() {
+                    Err(Error::InvalidArgCount {
+                        op_name: "min".to_string(),
+                        got_args: 0,
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(args.iter().copied().fold(f64::INFINITY, f64::min))
+                }
+            }
+            "max" => {
+                if args.is_empty() {
+                    Err(Error::InvalidArgCount {
+                        op_name: "max".to_string(),
+                        got_args: 0,
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(args.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+                }
+            }
+            "sum" => {
+                if args.is_empty() {
+                    Err(Error::InvalidArgCount {
+                        op_name: "sum".to_string(),
+                        got_args: 0,
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(args.iter().sum())
+                }
+            }
+            "floor" | "ceil" | "round" | "sign" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: name.to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(match name {
+                        "floor" => args[0].floor(),
+                        "ceil" => args[0].ceil(),
+                        "round" => args[0].round(),
+                        _ if args[0] == 0.0 => 0.0,
+                        _ => args[0].signum(),
+                    })
+                }
+            }
+            "clamp" => {
+                if args.len() != 3 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "clamp".to_string(),
+                        got_args: args.len(),
+                        expected_args: 3,
+                    })
+                } else if args[1].is_nan() || args[2].is_nan() || args[1] > args[2] {
+                    Err(Error::Math("Clamp bounds must satisfy lo <= hi".to_owned()))
+                } else {
+                    Ok(args[0].clamp(args[1], args[2]))
+                }
+            }
+            "lt" | "gt" | "le" | "ge" | "eq" => {
+                if args.len() != 2 {
+                    Err(Error::InvalidArgCount {
+                        op_name: name.to_string(),
+                        got_args: args.len(),
+                        expected_args: 2,
+                    })
+                } else {
+                    let holds = match name {
+                        "lt" => args[0] < args[1],
+                        "gt" => args[0] > args[1],
+                        "le" => args[0] <= args[1],
+                        "ge" => args[0] >= args[1],
+                        _ => args[0] == args[1],
+                    };
+                    Ok(if holds { 1.0 } else { 0.0 })
+                }
+            }
+            "if" => {
+                if args.len() != 3 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "if".to_string(),
+                        got_args: args.len(),
+                        expected_args: 3,
+                    })
+                } else if args[0] != 0.0 {
+                    Ok(args[1])
+                } else {
+                    Ok(args[2])
+                }
+            }
             _ => Err(Error::UndefinedFunction(name.to_string())),
         }
     }
 
     fn to_latex(&self, name: &str, args: &[String]) -> Result<String, Error> {
+        if let Some(func) = self.user_function(name, args.len()) {
+            return func.map(|_| {
+                format!(
+                    "\\operatorname{{{name}}}({})",
+                    args.iter()
+                        .map(|a| format!("{{{}}}", a))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+            });
+        }
+
         match name {
             "sin" => {
                 if args.len() != 1 {
@@ -308,7 +1134,7 @@ impl Runtime for DefaultRuntime {
                         expected_args: 1,
                     })
                 } else {
-                    Ok(format!("sin({{{}}})", args[0]))
+                    Ok(format!("sin({})", self.angle_to_latex(&args[0])))
                 }
             }
             "cos" => {
@@ -319,7 +1145,98 @@ impl Runtime for DefaultRuntime {
                         expected_args: 1,
                     })
                 } else {
-                    Ok(format!("cos({{{}}})", args[0]))
+                    Ok(format!("cos({})", self.angle_to_latex(&args[0])))
+                }
+            }
+            "tan" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "tan".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(format!("\\tan({})", self.angle_to_latex(&args[0])))
+                }
+            }
+            "asin" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "asin".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(format!("\\arcsin({{{}}})", args[0]))
+                }
+            }
+            "acos" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "acos".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(format!("\\arccos({{{}}})", args[0]))
+                }
+            }
+            "atan" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "atan".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(format!("\\arctan({{{}}})", args[0]))
+                }
+            }
+            "atan2" => {
+                if args.len() != 2 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "atan2".to_string(),
+                        got_args: args.len(),
+                        expected_args: 2,
+                    })
+                } else {
+                    Ok(format!(
+                        "\\operatorname{{atan2}}({{{}}},{{{}}})",
+                        args[0], args[1]
+                    ))
+                }
+            }
+            "sinh" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "sinh".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(format!("\\sinh({{{}}})", args[0]))
+                }
+            }
+            "cosh" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "cosh".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(format!("\\cosh({{{}}})", args[0]))
+                }
+            }
+            "tanh" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "tanh".to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(format!("\\tanh({{{}}})", args[0]))
                 }
             }
             "pow" => {
@@ -366,6 +1283,17 @@ impl Runtime for DefaultRuntime {
                     Ok(format!("ln({{{}}})", args[0]))
                 }
             }
+            "log" => {
+                if args.len() != 2 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "log".to_string(),
+                        got_args: args.len(),
+                        expected_args: 2,
+                    })
+                } else {
+                    Ok(format!("\\log_{{{}}}({{{}}})", args[0], args[1]))
+                }
+            }
             "abs" => {
                 if args.len() != 1 {
                     Err(Error::InvalidArgCount {
@@ -377,6 +1305,141 @@ impl Runtime for DefaultRuntime {
                     Ok(format!("|{{{}}}|", args[0]))
                 }
             }
+            "hypot" => {
+                if args.len() != 2 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "hypot".to_string(),
+                        got_args: args.len(),
+                        expected_args: 2,
+                    })
+                } else {
+                    Ok(format!("\\sqrt{{({{{}}})^2+({{{}}})^2}}", args[0], args[1]))
+                }
+            }
+            "mod" => {
+                if args.len() != 2 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "mod".to_string(),
+                        got_args: args.len(),
+                        expected_args: 2,
+                    })
+                } else {
+                    Ok(format!("{{{}}} \\bmod {{{}}}", args[0], args[1]))
+                }
+            }
+            "min" => {
+                if args.is_empty() {
+                    Err(Error::InvalidArgCount {
+                        op_name: "min".to_string(),
+                        got_args: 0,
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(format!(
+                        "\\min({})",
+                        args.iter()
+                            .map(|a| format!("{{{}}}", a))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ))
+                }
+            }
+            "max" => {
+                if args.is_empty() {
+                    Err(Error::InvalidArgCount {
+                        op_name: "max".to_string(),
+                        got_args: 0,
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(format!(
+                        "\\max({})",
+                        args.iter()
+                            .map(|a| format!("{{{}}}", a))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ))
+                }
+            }
+            "sum" => {
+                if args.is_empty() {
+                    Err(Error::InvalidArgCount {
+                        op_name: "sum".to_string(),
+                        got_args: 0,
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(format!(
+                        "\\operatorname{{sum}}({})",
+                        args.iter()
+                            .map(|a| format!("{{{}}}", a))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ))
+                }
+            }
+            "floor" | "ceil" | "round" | "sign" => {
+                if args.len() != 1 {
+                    Err(Error::InvalidArgCount {
+                        op_name: name.to_string(),
+                        got_args: args.len(),
+                        expected_args: 1,
+                    })
+                } else {
+                    Ok(match name {
+                        "floor" => format!("\\lfloor{{{}}}\\rfloor", args[0]),
+                        "ceil" => format!("\\lceil{{{}}}\\rceil", args[0]),
+                        "round" => format!("\\operatorname{{round}}({{{}}})", args[0]),
+                        _ => format!("\\operatorname{{sgn}}({{{}}})", args[0]),
+                    })
+                }
+            }
+            "clamp" => {
+                if args.len() != 3 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "clamp".to_string(),
+                        got_args: args.len(),
+                        expected_args: 3,
+                    })
+                } else {
+                    Ok(format!(
+                        "\\operatorname{{clamp}}({{{}}},{{{}}},{{{}}})",
+                        args[0], args[1], args[2]
+                    ))
+                }
+            }
+            "lt" | "gt" | "le" | "ge" | "eq" => {
+                if args.len() != 2 {
+                    Err(Error::InvalidArgCount {
+                        op_name: name.to_string(),
+                        got_args: args.len(),
+                        expected_args: 2,
+                    })
+                } else {
+                    let op = match name {
+                        "lt" => "<",
+                        "gt" => ">",
+                        "le" => "\\le",
+                        "ge" => "\\ge",
+                        _ => "=",
+                    };
+                    Ok(format!("[{{{}}}{}{{{}}}]", args[0], op, args[1]))
+                }
+            }
+            "if" => {
+                if args.len() != 3 {
+                    Err(Error::InvalidArgCount {
+                        op_name: "if".to_string(),
+                        got_args: args.len(),
+                        expected_args: 3,
+                    })
+                } else {
+                    Ok(format!(
+                        "\\begin{{cases}}{{{}}} & {{{}}} \\\\ {{{}}} & \\text{{otherwise}}\\end{{cases}}",
+                        args[1], args[0], args[2]
+                    ))
+                }
+            }
             _ => Err(Error::UndefinedFunction(name.to_string())),
         }
     }
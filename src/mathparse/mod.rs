@@ -1,33 +1,159 @@
+mod derivative;
 mod expr;
 mod parse;
 
 pub use expr::*;
 use parse::*;
 
-pub fn parse(expr: &str, language: &dyn Runtime) -> Option<Box<dyn Expression>> {
-    tokenize(expr).and_then(|tokens| parse_expr(&tokens, language))
+/// `position` is a byte offset into the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+/// Inputs past these are rejected before parsing, the parser slows down badly on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    pub max_tokens: usize,
+    pub max_depth: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_tokens: 500,
+            max_depth: 32,
+        }
+    }
+}
+
+pub fn parse(expr: &str, language: &dyn Runtime) -> Result<Box<dyn Expression>, ParseError> {
+    parse_with_limits(expr, language, ParseLimits::default())
+}
+
+pub fn parse_opt(expr: &str, language: &dyn Runtime) -> Option<Box<dyn Expression>> {
+    parse(expr, language).ok()
+}
+
+pub fn parse_with_limits(
+    expr: &str,
+    language: &dyn Runtime,
+    limits: ParseLimits,
+) -> Result<Box<dyn Expression>, ParseError> {
+    let (tokens, positions) = tokenize_with_positions(expr)?;
+
+    // the first token past a limit
+    let mut over_limit = (tokens.len() > limits.max_tokens).then_some(limits.max_tokens);
+    let mut level = 0i64;
+    let mut depth = 0;
+    for (i, t) in tokens.iter().enumerate() {
+        match t {
+            Token::OpenBracket => level += 1,
+            Token::CloseBracket => level -= 1,
+            _ => {}
+        }
+        depth = depth.max(level.max(0) as usize);
+        if depth > limits.max_depth && i < over_limit.unwrap_or(usize::MAX) {
+            over_limit = Some(i);
+        }
+    }
+
+    if let Some(i) = over_limit {
+        return Err(ParseError {
+            message: format!(
+                "too complex ({} tokens, nesting depth {depth})",
+                tokens.len()
+            ),
+            position: positions[i],
+        });
+    }
+
+    parse_expr(&tokens, &positions, language)
+}
+
+/// Parses `expr` with `DefaultRuntime` and evaluates it with `vars` bound.
+pub fn eval_str(expr: &str, vars: &[(&str, f64)]) -> Result<f64, Error> {
+    let runtime = DefaultRuntime::new(vars);
+    parse_with_limits(expr, &runtime, ParseLimits::default())
+        .map_err(Error::Parse)?
+        .eval(&runtime)
+}
+
+/// Function names that appear in `expr` without a call, i.e. used as variables.
+pub fn reserved_names_used(expr: &str, language: &dyn Runtime) -> Vec<String> {
+    let tokens = match tokenize(expr) {
+        Ok(tokens) => tokens,
+        Err(_) => return vec![],
+    };
+
+    let mut res: Vec<String> = vec![];
+    for (i, t) in tokens.iter().enumerate() {
+        if let Token::Identifier(id) = t {
+            if language.has_func(id)
+                && tokens.get(i + 1) != Some(&Token::OpenBracket)
+                && !res.contains(id)
+            {
+                res.push(id.to_owned());
+            }
+        }
+    }
+    res
+}
+
+/// Names followed by `(` that `language` has no function for, e.g. a misspelled function.
+/// A name that is also used without a bracket is taken for a variable, `x(x+1)` is a
+/// multiplication. Constants such as `pi` are never reported.
+pub fn unknown_calls(expr: &str, language: &dyn Runtime) -> Vec<String> {
+    let tokens = match tokenize(expr) {
+        Ok(tokens) => tokens,
+        Err(_) => return vec![],
+    };
+    let is_call = |i: usize| tokens.get(i + 1) == Some(&Token::OpenBracket);
+    let used_as_var = |id: &str| {
+        tokens
+            .iter()
+            .enumerate()
+            .any(|(i, t)| matches!(t, Token::Identifier(other) if other == id) && !is_call(i))
+    };
+
+    let mut res: Vec<String> = vec![];
+    for (i, t) in tokens.iter().enumerate() {
+        if let Token::Identifier(id) = t {
+            if !language.has_func(id)
+                && !CONSTANTS.iter().any(|(name, _)| name == id)
+                && is_call(i)
+                && !res.contains(id)
+                && !used_as_var(id)
+            {
+                res.push(id.to_owned());
+            }
+        }
+    }
+    res
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn parse_no_func() {
         let expr = "1+2";
         let lang = DefaultRuntime::default();
 
-        assert_eq!(parse(expr, &lang).map(|e| e.eval(&lang)), Some(Ok(3.0)));
+        assert_eq!(parse_opt(expr, &lang).map(|e| e.eval(&lang)), Some(Ok(3.0)));
 
         let expr = "x-10";
         assert_eq!(
-            parse(expr, &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", 10.0)]))),
+            parse_opt(expr, &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", 10.0)]))),
             Some(Ok(0.0))
         );
 
         let expr = "122+904-23.1*(72-x/4)";
         assert_eq!(
-            parse(expr, &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", 8.0)]))),
+            parse_opt(expr, &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", 8.0)]))),
             Some(Ok(122.0 + 904.0 - 23.1 * (72.0 - 8.0 / 4.0)))
         );
     }
@@ -35,12 +161,14 @@ mod tests {
     #[test]
     fn order_of_ops() {
         assert_eq!(
-            parse("1/2/3", &DefaultRuntime::default()).map(|e| e.eval(&DefaultRuntime::default())),
+            parse_opt("1/2/3", &DefaultRuntime::default())
+                .map(|e| e.eval(&DefaultRuntime::default())),
             Some(Ok(1.0 / 2.0 / 3.0))
         );
 
         assert_eq!(
-            parse("1-2-3", &DefaultRuntime::default()).map(|e| e.eval(&DefaultRuntime::default())),
+            parse_opt("1-2-3", &DefaultRuntime::default())
+                .map(|e| e.eval(&DefaultRuntime::default())),
             Some(Ok(1.0 - 2.0 - 3.0))
         );
     }
@@ -51,17 +179,17 @@ mod tests {
         let y = -1.2;
         let lang = DefaultRuntime::default();
         assert_eq!(
-            parse("2x", &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", x)]))),
+            parse_opt("2x", &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", x)]))),
             Some(Ok(4.0))
         );
 
         assert_eq!(
-            parse("2sin(x)-3cos(4x)", &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", x)]))),
+            parse_opt("2sin(x)-3cos(4x)", &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", x)]))),
             Some(Ok(2.0 * f64::sin(2.0) - 3.0 * f64::cos(4.0 * 2.0)))
         );
 
         assert_eq!(
-            parse(
+            parse_opt(
                 "-sin((5-3)cos(2.1x-sqrt(3+2-0.2x))+3pow(6,2y))-pow(1.1,-(10-y)x+y)",
                 &lang
             )
@@ -73,13 +201,543 @@ mod tests {
         );
     }
 
+    #[test]
+    fn bracket_groups() {
+        let lang = DefaultRuntime::default();
+        let eval = |expr: &str, x: f64| {
+            parse_opt(expr, &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", x)])))
+        };
+
+        assert_eq!(eval("(-x)(x+1)", 2.0), Some(Ok(-6.0)));
+        assert_eq!(eval("(x+1)(x-1)(x)", 2.0), Some(Ok(3.0 * 1.0 * 2.0)));
+        assert_eq!(eval("(x+1)((x-1)(x))", 3.0), Some(Ok(4.0 * 2.0 * 3.0)));
+        assert_eq!(
+            eval("2(x)(x+1)-(x)(x)", 3.0),
+            Some(Ok(2.0 * 3.0 * 4.0 - 9.0))
+        );
+        assert_eq!(eval("(x)sin(x)", 1.0), Some(Ok(f64::sin(1.0))));
+    }
+
+    #[test]
+    fn power() {
+        let lang = DefaultRuntime::default();
+        let eval = |expr: &str, x: f64| {
+            parse_opt(expr, &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", x)])))
+        };
+
+        assert_eq!(eval("2^3^2", 0.0), Some(Ok(512.0)));
+        assert_eq!(eval("2x^2", 3.0), Some(Ok(18.0)));
+        assert_eq!(eval("x^2+3x^3", 2.0), Some(Ok(4.0 + 24.0)));
+        assert_eq!(eval("-x^2", 3.0), Some(Ok(-9.0)));
+        assert_eq!(eval("2^-x", 1.0), Some(Ok(0.5)));
+        assert_eq!(eval("(x+1)^2/2", 1.0), Some(Ok(2.0)));
+        assert_eq!(
+            eval("2sin(x)^2x", 1.0),
+            Some(Ok(2.0 * f64::sin(1.0).powf(2.0)))
+        );
+        assert_eq!(eval("x^", 1.0), None);
+        assert_eq!(
+            parse_opt("x^2", &lang).map(|e| e.to_latex(&lang)),
+            Some(Ok("{x}^{2}".to_string()))
+        );
+    }
+
+    #[test]
+    fn inverse_trig() {
+        use std::f64::consts::PI;
+
+        let lang = DefaultRuntime::default();
+        let eval = |expr: &str| parse_opt(expr, &lang).map(|e| e.eval(&lang));
+
+        assert_eq!(eval("atan(1)"), Some(Ok(PI / 4.0)));
+        assert_eq!(eval("atan2(1,-1)"), Some(Ok(3.0 * PI / 4.0)));
+        assert_eq!(eval("asin(1)"), Some(Ok(PI / 2.0)));
+        assert_eq!(eval("acos(-1)"), Some(Ok(PI)));
+        assert!(matches!(eval("tan(0.5)"), Some(Ok(v)) if (v - 0.5f64.tan()).abs() < 1e-15));
+        assert!(matches!(eval("asin(1.5)"), Some(Err(Error::Math(_)))));
+        assert!(matches!(eval("acos(-2)"), Some(Err(Error::Math(_)))));
+        assert_eq!(
+            parse_opt("atan2(y,x)", &lang).map(|e| e.to_latex(&lang)),
+            Some(Ok("\\operatorname{atan2}({y},{x})".to_string()))
+        );
+    }
+
+    #[test]
+    fn hyperbolic() {
+        let lang = DefaultRuntime::default();
+        let eval = |expr: &str| parse_opt(expr, &lang).map(|e| e.eval(&lang));
+
+        assert_eq!(eval("tanh(0)"), Some(Ok(0.0)));
+        assert_eq!(eval("cosh(0)"), Some(Ok(1.0)));
+        assert_eq!(eval("sinh(1)"), Some(Ok(1f64.sinh())));
+        assert_eq!(
+            parse_opt("cosh(x)", &lang).map(|e| e.to_latex(&lang)),
+            Some(Ok("\\cosh({x})".to_string()))
+        );
+    }
+
+    #[test]
+    fn log_base() {
+        let lang = DefaultRuntime::default();
+        let eval = |expr: &str| parse_opt(expr, &lang).map(|e| e.eval(&lang));
+
+        assert!(matches!(eval("log(10,1000)"), Some(Ok(v)) if (v - 3.0).abs() < 1e-12));
+        assert_eq!(eval("log(2,8)"), Some(Ok(3.0)));
+        assert!(matches!(eval("log(1,5)"), Some(Err(Error::Math(_)))));
+        assert!(matches!(eval("log(0,5)"), Some(Err(Error::Math(_)))));
+        assert!(matches!(eval("log(-2,5)"), Some(Err(Error::Math(_)))));
+        assert!(matches!(eval("log(10,0)"), Some(Err(Error::Math(_)))));
+        assert_eq!(
+            parse_opt("log(2,x)", &lang).map(|e| e.to_latex(&lang)),
+            Some(Ok("\\log_{2}({x})".to_string()))
+        );
+    }
+
+    #[test]
+    fn constants() {
+        let lang = DefaultRuntime::default();
+
+        let sin_pi = parse_opt("sin(pi)", &lang).unwrap();
+        assert!(sin_pi.query_vars().is_empty());
+        assert!(matches!(sin_pi.eval(&lang), Ok(v) if v.abs() < 1e-15));
+
+        let circumference = parse_opt("2*pi*r", &lang).unwrap();
+        assert_eq!(circumference.query_vars(), HashSet::from(["r"]));
+        assert_eq!(
+            circumference.eval(&DefaultRuntime::new(&[("r", 0.5)])),
+            Ok(std::f64::consts::PI)
+        );
+
+        let e = parse_opt("ln(e)", &lang).unwrap();
+        assert_eq!(e.eval(&lang), Ok(1.0));
+        assert_eq!(
+            e.eval(&DefaultRuntime::new(&[("e", 1.0)])),
+            Ok(0.0),
+            "explicit bindings shadow the constants"
+        );
+
+        let e = std::f64::consts::E;
+        let eval = |src: &str| parse_opt(src, &lang).unwrap().eval(&lang).unwrap();
+        assert_eq!(eval("(1+2e)"), 1.0 + 2.0 * e);
+        assert_eq!(eval("3e*2"), 3.0 * e * 2.0);
+        assert_eq!(eval("2e3"), 2000.0);
+    }
+
+    #[test]
+    fn variadic() {
+        let lang = DefaultRuntime::default();
+        let eval = |expr: &str| parse_opt(expr, &lang).map(|e| e.eval(&lang));
+
+        assert_eq!(eval("min(3,-1,2)"), Some(Ok(-1.0)));
+        assert_eq!(eval("max(3,-1,2)"), Some(Ok(3.0)));
+        assert_eq!(eval("sum(1,2,3,4)"), Some(Ok(10.0)));
+        assert_eq!(eval("min(5)"), Some(Ok(5.0)));
+        assert_eq!(
+            lang.eval_func("sum", &[]),
+            Err(Error::InvalidArgCount {
+                op_name: "sum".to_string(),
+                got_args: 0,
+                expected_args: 1
+            })
+        );
+        assert_eq!(
+            parse_opt("max(x,1,y)", &lang).map(|e| e.to_latex(&lang)),
+            Some(Ok("\\max({x},{1},{y})".to_string()))
+        );
+    }
+
+    #[test]
+    fn hypot_mod() {
+        let lang = DefaultRuntime::default();
+        let eval = |expr: &str| parse_opt(expr, &lang).map(|e| e.eval(&lang));
+
+        assert_eq!(eval("hypot(3,4)"), Some(Ok(5.0)));
+        assert_eq!(eval("mod(7,3)"), Some(Ok(1.0)));
+        assert_eq!(eval("mod(-1,3)"), Some(Ok(2.0)));
+        assert!(matches!(eval("mod(1,0)"), Some(Err(Error::Math(_)))));
+        assert_eq!(
+            parse_opt("mod(x,2)", &lang).map(|e| e.to_latex(&lang)),
+            Some(Ok("{x} \\bmod {2}".to_string()))
+        );
+
+        assert_eq!(eval("7%3"), Some(Ok(1.0)));
+        assert_eq!(eval("-7 % 3"), Some(Ok(2.0)));
+        // same precedence as `*`, left to right
+        assert_eq!(eval("2*7%4"), Some(Ok(2.0)));
+        assert_eq!(eval("1+7%3*2"), Some(Ok(3.0)));
+        assert_eq!(
+            eval("5%0"),
+            Some(Err(Error::Math("Mod by zero".to_string())))
+        );
+        assert_eq!(
+            parse_opt("x%2", &lang).map(|e| e.to_latex(&lang)),
+            Some(Ok("{x} \\bmod {2}".to_string()))
+        );
+        assert!(matches!(
+            parse_opt("x%2", &lang).unwrap().derivative("x"),
+            Err(Error::NotDifferentiable(_))
+        ));
+    }
+
+    #[test]
+    fn too_complex() {
+        let lang = DefaultRuntime::default();
+
+        let nested = format!("{}x{}", "(".repeat(40), ")".repeat(40));
+        assert_eq!(
+            parse_with_limits(&nested, &lang, ParseLimits::default()).err(),
+            Some(ParseError {
+                message: "too complex (81 tokens, nesting depth 40)".to_string(),
+                position: 32
+            })
+        );
+
+        let long = vec!["x"; 1000].join("+");
+        let start = std::time::Instant::now();
+        assert!(matches!(
+            parse_with_limits(&long, &lang, ParseLimits::default()),
+            Err(e) if e.message.starts_with("too complex")
+        ));
+        assert!(parse_opt(&long, &lang).is_none());
+
+        let allowed = vec!["x"; 200].join("+");
+        assert_eq!(
+            parse_opt(&allowed, &lang).map(|e| e.eval(&DefaultRuntime::new(&[("x", 1.0)]))),
+            Some(Ok(200.0))
+        );
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+
+        let limits = ParseLimits {
+            max_tokens: 5,
+            max_depth: 1,
+        };
+        assert!(parse_with_limits("(x+1)", &lang, limits).is_ok());
+        assert!(parse_with_limits("((x))+1", &lang, limits).is_err());
+    }
+
     #[test]
     fn vars() {
         let expr = "x+4(x-2y)sin(z*x)";
         let lang = DefaultRuntime::default();
-        let expr = parse(expr, &lang).unwrap();
+        let expr = parse_opt(expr, &lang).unwrap();
         let vars = expr.query_vars();
         dbg!(&vars);
         assert!(vars.len() == 3 && vars.contains("x") && vars.contains("y") && vars.contains("z"));
     }
+
+    #[test]
+    fn eval_string() {
+        assert_eq!(eval_str("2x+1", &[("x", 3.0)]), Ok(7.0));
+        assert_eq!(
+            eval_str("2x+", &[("x", 3.0)]),
+            Err(Error::Parse(ParseError {
+                message: "expected a value after '+'".to_string(),
+                position: 3
+            }))
+        );
+        assert_eq!(
+            eval_str("2x+y", &[("x", 3.0)]),
+            Err(Error::UndefinedVariable("y".to_string()))
+        );
+    }
+
+    #[test]
+    fn derivative() {
+        let lang = DefaultRuntime::default();
+        let h = 1e-5;
+
+        for (expr, var, at) in [
+            ("x^2*sin(x)", "x", [1.3, 0.0]),
+            ("ln(x)/x", "x", [2.0, 0.0]),
+            ("sqrt(x)-3cos(x)", "x", [0.7, 0.0]),
+            ("exp(2x)", "x", [0.4, 0.0]),
+            ("pow(x,y)", "y", [1.5, 2.0]),
+            ("x^y", "x", [1.5, 2.0]),
+            ("abs(x)*atan(x*y)", "x", [-0.5, 2.0]),
+            ("hypot(x,y)+atan2(y,x)", "y", [1.0, 2.0]),
+            ("sum(x,x*x,tan(x))", "x", [0.3, 0.0]),
+            ("sinh(x)*cosh(y)+tanh(x*y)", "x", [0.6, -0.4]),
+            ("log(y,x*x)", "y", [1.5, 3.0]),
+        ] {
+            let e = parse_opt(expr, &lang).unwrap();
+            let d = e.derivative(var).unwrap();
+            let at = |dx: f64| {
+                let mut vals = [("x", at[0]), ("y", at[1])];
+                vals.iter_mut().find(|(n, _)| *n == var).unwrap().1 += dx;
+                DefaultRuntime::new(&vals)
+            };
+
+            let numeric = (e.eval(&at(h)).unwrap() - e.eval(&at(-h)).unwrap()) / (2.0 * h);
+            let symbolic = d.eval(&at(0.0)).unwrap();
+            assert!(
+                (numeric - symbolic).abs() < 1e-4,
+                "{expr}: {numeric} {symbolic}"
+            );
+        }
+
+        let d = parse_opt("3x+y", &lang).unwrap().derivative("x").unwrap();
+        assert_eq!(d.to_latex(&lang), Ok("3".to_string()));
+        assert!(matches!(
+            parse_opt("mod(x,2)", &lang).unwrap().derivative("x"),
+            Err(Error::NotDifferentiable(_))
+        ));
+    }
+
+    #[test]
+    fn error_position() {
+        let lang = DefaultRuntime::default();
+        let error = |expr: &str| parse(expr, &lang).err().map(|e| (e.message, e.position));
+
+        assert_eq!(
+            error("2x+)"),
+            Some(("expected a value before ')'".to_string(), 3))
+        );
+        assert_eq!(error("(x+1))"), Some(("unmatched ')'".to_string(), 5)));
+        assert_eq!(error("1 + (x*2"), Some(("unclosed '('".to_string(), 4)));
+        assert_eq!(error("x * * 2"), Some(("unexpected '*'".to_string(), 4)));
+        assert_eq!(
+            error("x, y"),
+            Some(("',' outside of a function call".to_string(), 1))
+        );
+        assert_eq!(
+            error("2sin+1"),
+            Some(("sin is a function and needs arguments".to_string(), 1))
+        );
+        assert_eq!(
+            error("x^"),
+            Some(("expected a value after '^'".to_string(), 2))
+        );
+        assert_eq!(error("  "), Some(("empty expression".to_string(), 2)));
+        assert_eq!(error("3.x"), Some(("invalid number".to_string(), 0)));
+        assert!(parse("2x+1", &lang).is_ok());
+    }
+
+    #[test]
+    fn infix() {
+        let lang = DefaultRuntime::default();
+        let infix = |expr: &str| parse_opt(expr, &lang).map(|e| e.to_infix());
+
+        assert_eq!(infix("2x+sin(y)"), Some("2*x+sin(y)".to_string()));
+        assert_eq!(infix("(a+b)(c-d)"), Some("(a+b)*(c-d)".to_string()));
+        assert_eq!(infix("a-(b-c)"), Some("a-(b-c)".to_string()));
+        assert_eq!(infix("(a-b)-c"), Some("a-b-c".to_string()));
+        assert_eq!(infix("2^3^2"), Some("2^3^2".to_string()));
+        assert_eq!(infix("(2^3)^2"), Some("(2^3)^2".to_string()));
+        assert_eq!(infix("-x^2"), Some("-x^2".to_string()));
+        assert_eq!(infix("x/(y*z)"), Some("x/(y*z)".to_string()));
+
+        let points = [(0.3, 1.7), (-1.2, 0.4), (2.5, -0.9)];
+        for expr in [
+            "2x^2-3x+1",
+            "-(x+y)*(-y)",
+            "x^-y/(1-x)",
+            "x - -y - (x - y)",
+            "atan2(y,x)*pow(x*x,0.5)+max(x,y,-1)",
+            "(x-y)^2/(-(x*y))",
+            "exp(-x^2/2)/sqrt(2pi)",
+            "0.1+0.2-x*1e-8",
+            "x%(y+3)*2-(x*y)%1.5",
+        ] {
+            let e = parse_opt(expr, &lang).unwrap();
+            let reparsed = parse(&e.to_infix(), &lang)
+                .unwrap_or_else(|err| panic!("{expr} -> {}: {:?}", e.to_infix(), err));
+            assert_eq!(reparsed.to_infix(), e.to_infix());
+            for (x, y) in points {
+                let runtime = DefaultRuntime::new(&[("x", x), ("y", y)]);
+                assert_eq!(
+                    e.eval(&runtime).map(f64::to_bits),
+                    reparsed.eval(&runtime).map(f64::to_bits),
+                    "{}",
+                    e.to_infix()
+                );
+            }
+        }
+
+        let d = parse_opt("x^3", &lang).unwrap().derivative("x").unwrap();
+        assert_eq!(d.to_infix(), "3*x^2");
+    }
+
+    #[test]
+    fn compile() {
+        let lang = DefaultRuntime::default();
+        let compiled = parse_opt("(2+3)*x", &lang).unwrap().compile(&lang).unwrap();
+        assert_eq!(
+            format!("{:?}", compiled),
+            format!(
+                "{:?}",
+                BasicOp::Multiply(Box::new(5.0), Variable::new_expression("x".to_string()))
+            )
+        );
+
+        let compiled = parse_opt("sin(pi/2)+y*exp(x-1)", &lang)
+            .unwrap()
+            .compile(&DefaultRuntime::new(&[("x", 1.0)]))
+            .unwrap();
+        assert_eq!(compiled.to_number(), None);
+        assert_eq!(compiled.eval(&DefaultRuntime::new(&[("y", 2.0)])), Ok(3.0));
+        assert_eq!(
+            parse_opt("max(1,sqrt(4))", &lang)
+                .unwrap()
+                .compile(&lang)
+                .unwrap()
+                .to_number(),
+            Some(2.0)
+        );
+    }
+
+    #[test]
+    fn rounding_functions() {
+        let lang = DefaultRuntime::default();
+        let eval = |src: &str| parse_opt(src, &lang).unwrap().eval(&lang);
+
+        assert_eq!(eval("floor(2.9)"), Ok(2.0));
+        assert_eq!(eval("ceil(2.1)"), Ok(3.0));
+        assert_eq!(eval("round(-2.5)"), Ok(-3.0));
+        assert_eq!(eval("sign(-3)"), Ok(-1.0));
+        assert_eq!(eval("sign(0)"), Ok(0.0));
+        assert_eq!(eval("sign(0.2)"), Ok(1.0));
+        assert_eq!(eval("clamp(5,0,3)"), Ok(3.0));
+        assert_eq!(eval("clamp(-1,0,3)"), Ok(0.0));
+        assert!(matches!(eval("clamp(1,3,0)"), Err(Error::Math(_))));
+        assert_eq!(
+            eval("clamp(1,2)"),
+            Err(Error::InvalidArgCount {
+                op_name: "clamp".to_string(),
+                got_args: 2,
+                expected_args: 3
+            })
+        );
+
+        assert_eq!(
+            parse_opt("clamp(x,0,1)", &lang).unwrap().to_latex(&lang),
+            Ok("\\operatorname{clamp}({x},{0},{1})".to_string())
+        );
+    }
+
+    #[test]
+    fn piecewise() {
+        use crate::integral_eq::fredholm_second_kind::{
+            FredholmSecondKind, FredholmSecondKindSystemOfEquations, Quadrature,
+        };
+        use std::f64::consts::PI;
+
+        let lang = DefaultRuntime::default();
+        let eval = |src: &str| parse_opt(src, &lang).unwrap().eval(&lang);
+        assert_eq!(eval("lt(1,2)+gt(1,2)+le(2,2)+ge(1,2)+eq(3,3)"), Ok(3.0));
+        assert_eq!(eval("if(0,1,2)"), Ok(2.0));
+        assert_eq!(eval("if(-0.5,1,2)"), Ok(1.0));
+
+        // only the chosen branch is evaluated
+        let guarded = parse_opt("if(gt(x,0),ln(x),0)", &lang).unwrap();
+        assert_eq!(guarded.eval(&DefaultRuntime::new(&[("x", -1.0)])), Ok(0.0));
+        assert_eq!(guarded.eval(&DefaultRuntime::new(&[("x", 1.0)])), Ok(0.0));
+        assert!(eval("if(1,ln(-1),0)").is_err());
+        let compiled = parse_opt("if(ge(x,0),sqrt(x),sqrt(-1))", &lang)
+            .unwrap()
+            .compile(&lang)
+            .unwrap();
+        assert_eq!(compiled.eval(&DefaultRuntime::new(&[("x", 4.0)])), Ok(2.0));
+        assert_eq!(
+            parse_opt("if(0,ln(-1),3)", &lang)
+                .unwrap()
+                .compile(&lang)
+                .unwrap()
+                .to_number(),
+            Some(3.0)
+        );
+
+        // Green's function of -y'' on [0, 1] with zero ends
+        let green = parse_opt("if(lt(x,s),x*(1-s),s*(1-x))", &lang).unwrap();
+        let kernel = |x: f64, s: f64| green.eval(&DefaultRuntime::new(&[("x", x), ("s", s)]));
+        for (x, s) in [(0.25, 0.5), (0.5, 0.25)] {
+            assert!((kernel(x, s).unwrap() - 0.125).abs() < 1e-12);
+        }
+
+        // int G(x, s) sin(pi s) ds = sin(pi x) / pi^2, so y = sin(pi x) solves
+        // y(x) - int G(x, s) y(s) ds = (1 - 1 / pi^2) sin(pi x)
+        let right_side =
+            |x: f64| -> Result<f64, Error> { Ok((1.0 - 1.0 / (PI * PI)) * (PI * x).sin()) };
+        let solution = FredholmSecondKindSystemOfEquations {
+            n: 101,
+            eps: 1e-12,
+            max_iter_count: 10000,
+            quadrature: Quadrature::Trapezoid,
+        }
+        .solve(&kernel, &right_side, 0.0, 1.0, 1.0)
+        .unwrap();
+        for (x, y) in solution.to_table() {
+            assert!((y - (PI * x).sin()).abs() < 1e-4, "{y} at {x}");
+        }
+
+        let d = parse_opt("if(lt(x,1),x^2,2x)", &lang)
+            .unwrap()
+            .derivative("x")
+            .unwrap();
+        assert_eq!(d.eval(&DefaultRuntime::new(&[("x", 0.5)])), Ok(1.0));
+        assert_eq!(d.eval(&DefaultRuntime::new(&[("x", 3.0)])), Ok(2.0));
+    }
+
+    #[test]
+    fn angle_mode() {
+        let degrees = DefaultRuntime::default().with_angle_mode(AngleMode::Degrees);
+        let eval = |src: &str, lang: &DefaultRuntime| parse_opt(src, lang).unwrap().eval(lang);
+
+        assert_eq!(eval("sin(90)", &degrees), Ok(1.0));
+        assert!((eval("cos(60)", &degrees).unwrap() - 0.5).abs() < 1e-12);
+        assert!((eval("atan(1)", &degrees).unwrap() - 45.0).abs() < 1e-12);
+        assert!((eval("sin(90)", &DefaultRuntime::default()).unwrap() - 90f64.sin()).abs() < 1e-12);
+
+        let sin = parse_opt("sin(x)", &degrees).unwrap();
+        assert_eq!(sin.to_latex(&degrees), Ok("sin({x}^\\circ)".to_string()));
+        assert_eq!(
+            sin.to_latex(&DefaultRuntime::default()),
+            Ok("sin({x})".to_string())
+        );
+    }
+
+    #[test]
+    fn user_functions() {
+        let lang = DefaultRuntime::new(&[("z", 12.0)])
+            .with_function("hypot", 3, |args| {
+                Ok(args.iter().map(|a| a * a).sum::<f64>().sqrt())
+            })
+            .with_function("kernel", 2, |args| {
+                if args[0] < args[1] {
+                    Ok(args[0])
+                } else {
+                    Err(Error::Math("kernel is zero below the diagonal".to_string()))
+                }
+            });
+
+        let expr = parse_opt("hypot(3,4,z)+1", &lang).unwrap();
+        assert_eq!(expr.eval(&lang), Ok(14.0));
+        assert_eq!(
+            expr.to_latex(&lang)
+                .map(|s| s.contains("\\operatorname{hypot}({3},{4},{z})")),
+            Ok(true)
+        );
+        // the built-in hypot takes two arguments
+        assert_eq!(
+            parse_opt("hypot(3,4)", &lang).unwrap().eval(&lang),
+            Err(Error::InvalidArgCount {
+                op_name: "hypot".to_string(),
+                got_args: 2,
+                expected_args: 3
+            })
+        );
+
+        let kernel = parse_opt("kernel(x,s)", &lang).unwrap();
+        assert_eq!(
+            kernel.eval(&DefaultRuntime::new(&[("x", 1.0), ("s", 2.0)])),
+            Err(Error::UndefinedFunction("kernel".to_string()))
+        );
+        assert_eq!(
+            parse_opt("kernel(1,2)*2", &lang).unwrap().eval(&lang),
+            Ok(2.0)
+        );
+        assert!(matches!(
+            parse_opt("kernel(2,1)", &lang).unwrap().eval(&lang),
+            Err(Error::Math(_))
+        ));
+    }
 }
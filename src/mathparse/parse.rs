@@ -1,4 +1,4 @@
-use super::expr::*;
+use super::{expr::*, ParseError};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
@@ -7,16 +7,27 @@ pub enum Token {
     Minus,
     Multiply,
     Divide,
+    Percent,
+    Caret,
     Identifier(String),
     OpenBracket,
     CloseBracket,
     Coma,
 }
 
-pub fn tokenize(mut src: &str) -> Option<Vec<Token>> {
+pub fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+    tokenize_with_positions(src).map(|(tokens, _)| tokens)
+}
+
+/// Also returns the byte offset of every token, followed by the length of `full_src`.
+pub fn tokenize_with_positions(full_src: &str) -> Result<(Vec<Token>, Vec<usize>), ParseError> {
+    let mut src = full_src;
     let mut res = vec![];
+    let mut positions = vec![];
     loop {
         src = src.trim_start();
+        let position = full_src.len() - src.len();
+        positions.push(position);
 
         if let Some(next) = src.strip_prefix('(') {
             src = next;
@@ -30,60 +41,94 @@ pub fn tokenize(mut src: &str) -> Option<Vec<Token>> {
         } else if let Some(next) = src.strip_prefix('+') {
             src = next;
             res.push(Token::Plus);
-        } else if let Some(next) = src.strip_prefix('-') {
+        } else if let Some(next) = src.strip_prefix(['-', '\u{2212}']) {
             src = next;
             res.push(Token::Minus);
-        } else if let Some(next) = src.strip_prefix('*') {
+        } else if let Some(next) = src.strip_prefix(['*', '\u{d7}', '\u{b7}']) {
             src = next;
             res.push(Token::Multiply);
-        } else if let Some(next) = src.strip_prefix('/') {
+        } else if let Some(next) = src.strip_prefix(['/', '\u{f7}']) {
             src = next;
             res.push(Token::Divide);
-        } else if let Some((num, next)) = read_number(src) {
+        } else if let Some(next) = src.strip_prefix('%') {
+            src = next;
+            res.push(Token::Percent);
+        } else if let Some(next) = src.strip_prefix('^') {
+            src = next;
+            res.push(Token::Caret);
+        } else if let Some((num, next)) = read_number(src, position)? {
             src = next;
             res.push(Token::Num(num));
         } else if let Some((identifier, next)) = read_identifier(src) {
             src = next;
             res.push(Token::Identifier(identifier));
-        } else if src.is_empty() {
-            return Some(res);
+        } else if let Some(c) = src.chars().next() {
+            return Err(ParseError {
+                message: format!("unexpected character '{c}'"),
+                position,
+            });
         } else {
-            return None;
+            return Ok((res, positions));
         }
     }
 }
 
-fn read_number(src: &str) -> Option<(f64, &str)> {
-    let src = src.trim_start();
-    let (before_dot, before_dot_str_size) = src
-        .char_indices()
-        .map_while(|(i, c)| c.to_digit(10).map(|d| (d, i)))
-        .fold((0.0, 0), |(acc, _), (d, i)| (acc * 10.0 + d as f64, i + 1));
-    if before_dot_str_size == 0 {
-        return None;
+/// `Ok(None)` if `src` does not start with a digit.
+fn read_number(src: &str, position: usize) -> Result<Option<(f64, &str)>, ParseError> {
+    let invalid = || ParseError {
+        message: "invalid number".to_string(),
+        position,
+    };
+    if !src.starts_with(|c: char| c.is_ascii_digit()) {
+        return Ok(None);
     }
+    let (mantissa, next) = read_mantissa(src).ok_or_else(invalid)?;
 
-    if let Some(next) = src[before_dot_str_size..].strip_prefix('.') {
-        let (after_dot, after_dot_divisor, after_dot_str_size) = next
-            .char_indices()
-            .map_while(|(i, c)| c.to_digit(10).map(|d| (d, i)))
-            .fold((0.0, 1, 0), |(acc, divisor, _), (d, i)| {
-                (acc * 10.0 + d as f64, divisor * 10, i + 1)
-            });
-        if after_dot_str_size == 0 {
+    let exp = match next.strip_prefix(['e', 'E']) {
+        Some(exp) => exp,
+        None => return Ok(Some((mantissa, next))),
+    };
+    let digits = exp.strip_prefix(['+', '-']).unwrap_or(exp);
+    let digit_count = digits.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digit_count == 0 {
+        // `2exp(x)` and `3e*x` multiply by what follows, but `1e` and `5e+` are broken numbers
+        return if digits.len() == exp.len() && !exp.is_empty() {
+            Ok(Some((mantissa, next)))
+        } else {
+            Err(invalid())
+        };
+    }
+
+    let rest = &digits[digit_count..];
+    src[..src.len() - rest.len()]
+        .parse()
+        .map(|num| Some((num, rest)))
+        .map_err(|_| invalid())
+}
+
+fn read_mantissa(src: &str) -> Option<(f64, &str)> {
+    let digit_count = |s: &str| s.chars().take_while(|c| c.is_ascii_digit()).count();
+
+    let mut len = digit_count(src);
+    if len == 0 {
+        return None;
+    }
+    if let Some(after_dot) = src[len..].strip_prefix('.') {
+        let after_dot_len = digit_count(after_dot);
+        if after_dot_len == 0 {
             return None;
         }
-
-        Some((
-            before_dot + after_dot / (after_dot_divisor as f64),
-            &next[after_dot_str_size..],
-        ))
-    } else {
-        Some((before_dot as f64, &src[before_dot_str_size..]))
+        len += 1 + after_dot_len;
     }
+
+    // parsing the whole literal at once keeps long fractions exact
+    src[..len].parse().ok().map(|num| (num, &src[len..]))
 }
 
-const RESERVED_SYMBOLS: [char; 7] = ['+', '-', '*', '/', ',', '(', ')'];
+/// Also the unicode minus, times, middle dot and division signs pasted from documents.
+const RESERVED_SYMBOLS: [char; 13] = [
+    '+', '-', '*', '/', '%', '^', ',', '(', ')', '\u{2212}', '\u{d7}', '\u{b7}', '\u{f7}',
+];
 
 fn read_identifier(src: &str) -> Option<(String, &str)> {
     let src = src.trim_start();
@@ -93,7 +138,7 @@ fn read_identifier(src: &str) -> Option<(String, &str)> {
         .take_while(|(_, c)| !c.is_whitespace() && RESERVED_SYMBOLS.iter().all(|sym| c != sym))
         .fold(("".to_string(), 0), |(mut acc, _), (i, c)| {
             acc.push(c);
-            (acc, i + 1)
+            (acc, i + c.len_utf8())
         });
 
     if len == 0 || identifier.starts_with(|c: char| c.is_ascii_digit()) {
@@ -130,68 +175,251 @@ fn tokenizer() {
         Token::CloseBracket,
     ];
 
-    assert_eq!(tokenize(expr), Some(expr_tokenized));
+    assert_eq!(tokenize(expr), Ok(expr_tokenized));
+}
+
+#[test]
+fn unicode_operators() {
+    assert_eq!(tokenize("2×x−3"), tokenize("2*x-3"));
+    assert_eq!(tokenize("a·b÷c"), tokenize("a*b/c"));
+    // the identifier ends at the sign instead of swallowing it
+    assert_eq!(
+        tokenize("α−1"),
+        Ok(vec![
+            Token::Identifier("α".to_string()),
+            Token::Minus,
+            Token::Num(1.0)
+        ])
+    );
+
+    let runtime = DefaultRuntime::new(&[("x", 4.0)]);
+    let (tokens, positions) = tokenize_with_positions("2×x−3").unwrap();
+    let expr = parse_expr(&tokens, &positions, &runtime).unwrap();
+    assert_eq!(expr.eval(&runtime), Ok(5.0));
+}
+
+#[test]
+fn exponent_numbers() {
+    assert_eq!(tokenize("1e-8"), Ok(vec![Token::Num(1e-8)]));
+    assert_eq!(tokenize("6.022e23"), Ok(vec![Token::Num(6.022e23)]));
+    assert_eq!(tokenize("2.5E3"), Ok(vec![Token::Num(2500.0)]));
+    assert_eq!(
+        tokenize("3e+8*x"),
+        Ok(vec![
+            Token::Num(3e8),
+            Token::Multiply,
+            Token::Identifier("x".to_string())
+        ])
+    );
+    assert_eq!(
+        tokenize("2exp"),
+        Ok(vec![Token::Num(2.0), Token::Identifier("exp".to_string())])
+    );
+    assert_eq!(
+        tokenize("3e*x"),
+        Ok(vec![
+            Token::Num(3.0),
+            Token::Identifier("e".to_string()),
+            Token::Multiply,
+            Token::Identifier("x".to_string())
+        ])
+    );
+    assert_eq!(
+        tokenize("2e)"),
+        Ok(vec![
+            Token::Num(2.0),
+            Token::Identifier("e".to_string()),
+            Token::CloseBracket
+        ])
+    );
+    assert!(tokenize("5e+").is_err());
+    assert!(tokenize("1e").is_err());
+    assert_eq!(
+        tokenize("x+1e-x"),
+        Err(ParseError {
+            message: "invalid number".to_string(),
+            position: 2
+        })
+    );
 }
 
 /*
     expr = expr ('+' | '-') term | term
-    term = term ('*' | '/' ) factor | -term | term factor | factor
+    term = term ('*' | '/' | '%') power | -term | term power | power
+    power = factor '^' power | factor '^' '-' power | factor
     factor = number | variable | func '(' arglist ')' | '(' expr ')'
     arglist = expr (',' expr)*
 */
 
-pub fn parse_expr(tokens: &[Token], runtime: &dyn Runtime) -> Option<Box<dyn Expression>> {
-    // println!("parse_expr: {:?}", &tokens);
+/// `positions` are the ones from `tokenize_with_positions`.
+pub fn parse_expr(
+    tokens: &[Token],
+    positions: &[usize],
+    runtime: &dyn Runtime,
+) -> Result<Box<dyn Expression>, ParseError> {
+    parse_sum(tokens, runtime).ok_or_else(|| locate_error(tokens, positions, runtime))
+}
 
-    [Token::Plus, Token::Minus]
-        .iter()
-        .find_map(|op| {
-            tokens.iter().enumerate().find_map(|(i, t)| {
-                if t.eq(op) {
-                    let expr: Box<dyn Expression> = match op {
-                        Token::Plus => Box::new(BasicOp::Plus(
-                            parse_expr(&tokens[..i], runtime)?,
-                            parse_term(&tokens[i + 1..], runtime)?,
-                        )),
-                        Token::Minus => Box::new(BasicOp::Minus(
-                            parse_expr(&tokens[..i], runtime)?,
-                            parse_term(&tokens[i + 1..], runtime)?,
-                        )),
-                        _ => unreachable!(),
-                    };
-                    Some(expr)
-                } else {
-                    None
-                }
-            })
+/// The parser backtracks, so it can not tell where it failed.
+/// Instead look for the first token that can not be where it is.
+fn locate_error(tokens: &[Token], positions: &[usize], runtime: &dyn Runtime) -> ParseError {
+    let error = |i: usize, message: String| ParseError {
+        message,
+        position: positions.get(i).or(positions.last()).copied().unwrap_or(0),
+    };
+
+    if tokens.is_empty() {
+        return error(0, "empty expression".to_string());
+    }
+
+    // for every open bracket, whether it belongs to a function call
+    let mut brackets = vec![];
+    for (i, t) in tokens.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|i| &tokens[i]);
+        let expects_operand = matches!(
+            prev,
+            None | Some(
+                Token::Plus
+                    | Token::Minus
+                    | Token::Multiply
+                    | Token::Divide
+                    | Token::Percent
+                    | Token::Caret
+                    | Token::OpenBracket
+                    | Token::Coma
+            )
+        );
+
+        match t {
+            Token::Plus
+            | Token::Multiply
+            | Token::Divide
+            | Token::Percent
+            | Token::Caret
+            | Token::Coma
+                if expects_operand =>
+            {
+                return error(i, format!("unexpected '{}'", symbol(t)));
+            }
+            Token::CloseBracket if expects_operand => {
+                return error(i, "expected a value before ')'".to_string());
+            }
+            Token::OpenBracket => brackets.push((
+                i,
+                matches!(prev, Some(Token::Identifier(id)) if runtime.has_func(id)),
+            )),
+            Token::CloseBracket if brackets.pop().is_none() => {
+                return error(i, "unmatched ')'".to_string());
+            }
+            Token::Coma if !matches!(brackets.last(), Some((_, true))) => {
+                return error(i, "',' outside of a function call".to_string());
+            }
+            Token::Identifier(id)
+                if runtime.has_func(id) && tokens.get(i + 1) != Some(&Token::OpenBracket) =>
+            {
+                return error(i, format!("{id} is a function and needs arguments"));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((i, _)) = brackets.last() {
+        return error(*i, "unclosed '('".to_string());
+    }
+    match tokens.last() {
+        Some(
+            t @ (Token::Plus
+            | Token::Minus
+            | Token::Multiply
+            | Token::Divide
+            | Token::Percent
+            | Token::Caret),
+        ) => error(
+            tokens.len(),
+            format!("expected a value after '{}'", symbol(t)),
+        ),
+        _ => error(0, "invalid expression".to_string()),
+    }
+}
+
+fn symbol(t: &Token) -> String {
+    match t {
+        Token::Num(n) => n.to_string(),
+        Token::Plus => "+".to_string(),
+        Token::Minus => "-".to_string(),
+        Token::Multiply => "*".to_string(),
+        Token::Divide => "/".to_string(),
+        Token::Percent => "%".to_string(),
+        Token::Caret => "^".to_string(),
+        Token::Identifier(id) => id.clone(),
+        Token::OpenBracket => "(".to_string(),
+        Token::CloseBracket => ")".to_string(),
+        Token::Coma => ",".to_string(),
+    }
+}
+
+fn parse_sum(tokens: &[Token], runtime: &dyn Runtime) -> Option<Box<dyn Expression>> {
+    // println!("parse_sum: {:?}", &tokens);
+
+    top_level_ops(tokens, &[Token::Plus, Token::Minus])
+        .into_iter()
+        .find_map(|i| {
+            let expr: Box<dyn Expression> = match tokens[i] {
+                Token::Plus => Box::new(BasicOp::Plus(
+                    parse_sum(&tokens[..i], runtime)?,
+                    parse_term(&tokens[i + 1..], runtime)?,
+                )),
+                Token::Minus => Box::new(BasicOp::Minus(
+                    parse_sum(&tokens[..i], runtime)?,
+                    parse_term(&tokens[i + 1..], runtime)?,
+                )),
+                _ => unreachable!(),
+            };
+            Some(expr)
         })
         .or_else(|| parse_term(tokens, runtime))
 }
 
+/// Positions of `ops` outside of any brackets, last one first.
+/// An operator inside brackets can never split the tokens into two valid halves,
+/// and trying the last one first keeps the left-associative split from backtracking.
+fn top_level_ops(tokens: &[Token], ops: &[Token]) -> Vec<usize> {
+    let mut level = 0;
+    let mut res = vec![];
+    for (i, t) in tokens.iter().enumerate() {
+        match t {
+            Token::OpenBracket => level += 1,
+            Token::CloseBracket => level -= 1,
+            t if level == 0 && ops.contains(t) => res.push(i),
+            _ => {}
+        }
+    }
+    res.reverse();
+    res
+}
+
 fn parse_term(tokens: &[Token], runtime: &dyn Runtime) -> Option<Box<dyn Expression>> {
     // println!("parse_term: {:?}", &tokens);
 
-    [Token::Multiply, Token::Divide]
-        .iter()
-        .find_map(|op| {
-            tokens.iter().enumerate().find_map(|(i, t)| {
-                if t.eq(op) {
-                    let expr: Box<dyn Expression> = match op {
-                        Token::Multiply => Box::new(BasicOp::Multiply(
-                            parse_term(&tokens[..i], runtime)?,
-                            parse_factor(&tokens[i + 1..], runtime)?,
-                        )),
-                        Token::Divide => Box::new(BasicOp::Divide(
-                            parse_term(&tokens[..i], runtime)?,
-                            parse_factor(&tokens[i + 1..], runtime)?,
-                        )),
-                        _ => unreachable!(),
-                    };
-                    Some(expr)
-                } else {
-                    None
-                }
-            })
+    top_level_ops(tokens, &[Token::Multiply, Token::Divide, Token::Percent])
+        .into_iter()
+        .find_map(|i| {
+            let expr: Box<dyn Expression> = match tokens[i] {
+                Token::Multiply => Box::new(BasicOp::Multiply(
+                    parse_term(&tokens[..i], runtime)?,
+                    parse_power(&tokens[i + 1..], runtime)?,
+                )),
+                Token::Divide => Box::new(BasicOp::Divide(
+                    parse_term(&tokens[..i], runtime)?,
+                    parse_power(&tokens[i + 1..], runtime)?,
+                )),
+                Token::Percent => Box::new(BasicOp::Mod(
+                    parse_term(&tokens[..i], runtime)?,
+                    parse_power(&tokens[i + 1..], runtime)?,
+                )),
+                _ => unreachable!(),
+            };
+            Some(expr)
         })
         .or_else(|| {
             tokens.first().and_then(|t| match t {
@@ -204,7 +432,7 @@ fn parse_term(tokens: &[Token], runtime: &dyn Runtime) -> Option<Box<dyn Express
             })
         })
         .or_else(|| parse_implicit_multiplication(tokens, runtime))
-        .or_else(|| parse_factor(tokens, runtime))
+        .or_else(|| parse_power(tokens, runtime))
 }
 
 fn parse_implicit_multiplication(
@@ -213,15 +441,26 @@ fn parse_implicit_multiplication(
 ) -> Option<Box<dyn Expression>> {
     // println!("parse_implicit_multiplication: {:?}", &tokens);
 
+    // the last power chain, so that `2x^2` is `2(x^2)`
+    let mut start = last_operand_start(tokens, runtime)?;
+    while start >= 2 && tokens[start - 1] == Token::Caret {
+        start = last_operand_start(&tokens[..start - 1], runtime)?;
+    }
+    if start == 0 {
+        return None;
+    }
+
+    Some(Box::new(BasicOp::Multiply(
+        parse_term(&tokens[..start], runtime)?,
+        parse_power(&tokens[start..], runtime)?,
+    )))
+}
+
+/// Where the number, variable, function call or bracket group at the end of `tokens` starts.
+fn last_operand_start(tokens: &[Token], runtime: &dyn Runtime) -> Option<usize> {
     match tokens.iter().last()? {
-        Token::Num(n) => Some(Box::new(BasicOp::Multiply(
-            parse_term(&tokens[..tokens.len() - 1], runtime)?,
-            Box::new(*n),
-        ))),
-        Token::Identifier(var) if !runtime.has_func(var) => Some(Box::new(BasicOp::Multiply(
-            parse_term(&tokens[..tokens.len() - 1], runtime)?,
-            Variable::new_expression(var.to_string()),
-        ))),
+        Token::Num(_) => Some(tokens.len() - 1),
+        Token::Identifier(var) if !runtime.has_func(var) => Some(tokens.len() - 1),
         Token::CloseBracket => {
             let (corresponding_open_bracket, _, _) = tokens
                 .iter()
@@ -241,25 +480,38 @@ fn parse_implicit_multiplication(
                 .skip(1)
                 .find(|(_, bracket_level, t)| *bracket_level == 0 && *t == &Token::OpenBracket)?;
 
-            if corresponding_open_bracket >= 2 {
+            if corresponding_open_bracket >= 1 {
                 if let Token::Identifier(id) = &tokens[corresponding_open_bracket - 1] {
                     if runtime.has_func(id) {
-                        return Some(Box::new(BasicOp::Multiply(
-                            parse_term(&tokens[..corresponding_open_bracket - 1], runtime)?,
-                            parse_factor(&tokens[corresponding_open_bracket - 1..], runtime)?,
-                        )));
+                        return Some(corresponding_open_bracket - 1);
                     }
                 }
             }
-            Some(Box::new(BasicOp::Multiply(
-                parse_term(&tokens[..corresponding_open_bracket], runtime)?,
-                parse_factor(&tokens[corresponding_open_bracket..], runtime)?,
-            )))
+            Some(corresponding_open_bracket)
         }
         _ => None,
     }
 }
 
+/// `^` is right-associative, so the first one splits.
+fn parse_power(tokens: &[Token], runtime: &dyn Runtime) -> Option<Box<dyn Expression>> {
+    // println!("parse_power: {:?}", &tokens);
+
+    match top_level_ops(tokens, &[Token::Caret]).last() {
+        Some(&i) => {
+            let exponent = match tokens.get(i + 1)? {
+                Token::Minus => Box::new(BasicOp::Negate(parse_power(&tokens[i + 2..], runtime)?)),
+                _ => parse_power(&tokens[i + 1..], runtime)?,
+            };
+            Some(Box::new(BasicOp::Power(
+                parse_factor(&tokens[..i], runtime)?,
+                exponent,
+            )))
+        }
+        None => parse_factor(tokens, runtime),
+    }
+}
+
 fn parse_factor(tokens: &[Token], runtime: &dyn Runtime) -> Option<Box<dyn Expression>> {
     // println!("parse_factor: {:?}", &tokens);
 
@@ -280,7 +532,7 @@ fn parse_factor(tokens: &[Token], runtime: &dyn Runtime) -> Option<Box<dyn Expre
             Some(Variable::new_expression(id.to_owned()))
         }
         Token::OpenBracket if Some(&Token::CloseBracket) == tokens.last() => {
-            parse_expr(&tokens[1..tokens.len() - 1], runtime)
+            parse_sum(&tokens[1..tokens.len() - 1], runtime)
         }
         _ => None,
     }
@@ -314,10 +566,10 @@ fn parse_arglist(tokens: &[Token], runtime: &dyn Runtime) -> Option<Vec<Box<dyn
     loop {
         let next_coma = coma_iterator.next();
         if let Some(i) = next_coma {
-            args.push(parse_expr(&tokens[arg_start..i], runtime)?);
+            args.push(parse_sum(&tokens[arg_start..i], runtime)?);
             arg_start = i + 1;
         } else {
-            args.push(parse_expr(&tokens[arg_start..], runtime)?);
+            args.push(parse_sum(&tokens[arg_start..], runtime)?);
             return Some(args);
         }
     }
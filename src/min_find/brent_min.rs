@@ -0,0 +1,147 @@
+use std::fmt::Debug;
+
+use crate::functions::function::Function;
+
+use super::Minimum1d;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    FunctionError(String),
+    ItersEnded(Minimum1d, f64),
+}
+
+/// Golden section search that takes a parabolic step through the three best points
+/// whenever that step stays inside the interval and keeps shrinking.
+/// Stops once the minimum is located within about `eps`.
+pub fn brent_min<E>(
+    from: f64,
+    to: f64,
+    f: &dyn Function<Error = E>,
+    eps: f64,
+    max_iter_count: usize,
+) -> Result<Minimum1d, Error>
+where
+    E: Debug,
+{
+    let golden = (3.0 - 5.0f64.sqrt()) * 0.5;
+    let eval = |x| {
+        f.apply(x)
+            .map_err(|e| Error::FunctionError(format!("{:?}", e)))
+    };
+
+    let mut a = f64::min(from, to);
+    let mut b = f64::max(from, to);
+    // x is the best point so far, w the second best and v the previous w
+    let mut x = a + golden * (b - a);
+    let mut fx = eval(x)?;
+    let (mut w, mut fw, mut v, mut fv) = (x, fx, x, fx);
+    // d is the last step, e the one before it
+    let mut d: f64 = 0.0;
+    let mut e: f64 = 0.0;
+
+    for _ in 0..max_iter_count {
+        let m = (a + b) * 0.5;
+        let tol = f64::EPSILON.sqrt() * x.abs() + eps / 3.0;
+        if (x - m).abs() <= 2.0 * tol - (b - a) * 0.5 {
+            return Ok(Minimum1d { x, y: fx });
+        }
+
+        let mut parabolic = false;
+        if e.abs() > tol {
+            let r = (x - w) * (fx - fv);
+            let q = (x - v) * (fx - fw);
+            let mut p = (x - v) * q - (x - w) * r;
+            let mut q = 2.0 * (q - r);
+            if q > 0.0 {
+                p = -p;
+            } else {
+                q = -q;
+            }
+
+            if p.abs() < (0.5 * q * e).abs() && p > q * (a - x) && p < q * (b - x) {
+                e = d;
+                d = p / q;
+                let u = x + d;
+                if u - a < 2.0 * tol || b - u < 2.0 * tol {
+                    d = if x < m { tol } else { -tol };
+                }
+                parabolic = true;
+            }
+        }
+        if !parabolic {
+            e = if x < m { b - x } else { a - x };
+            d = golden * e;
+        }
+
+        let u = if d.abs() >= tol {
+            x + d
+        } else if d > 0.0 {
+            x + tol
+        } else {
+            x - tol
+        };
+        let fu = eval(u)?;
+
+        if fu <= fx {
+            if u < x {
+                b = x;
+            } else {
+                a = x;
+            }
+            (v, fv) = (w, fw);
+            (w, fw) = (x, fx);
+            (x, fx) = (u, fu);
+        } else {
+            if u < x {
+                a = u;
+            } else {
+                b = u;
+            }
+            if fu <= fw || w == x {
+                (v, fv) = (w, fw);
+                (w, fw) = (u, fu);
+            } else if fu <= fv || v == x || v == w {
+                (v, fv) = (u, fu);
+            }
+        }
+    }
+
+    Err(Error::ItersEnded(Minimum1d { x, y: fx }, b - a))
+}
+
+#[test]
+fn brent_cos() -> Result<(), Error> {
+    use super::golden_ratio_min::golden_ratio_min;
+    use std::cell::Cell;
+
+    let evals = Cell::new(0);
+    let f = |x: f64| -> Result<f64, Error> {
+        evals.set(evals.get() + 1);
+        Ok(x.cos())
+    };
+    let to = 2.0 * std::f64::consts::PI;
+
+    let min = brent_min(0.0, to, &f, 1e-8, 100)?;
+    assert!((min.x - std::f64::consts::PI).abs() < 1e-6, "{min:?}");
+    assert!((min.y + 1.0).abs() < 1e-12);
+
+    evals.set(0);
+
+    // golden_ratio_min can not shrink [0, 2pi] since the ends have equal values
+    let brent = brent_min(1.0, to, &f, 1e-8, 100)?;
+    let brent_evals = evals.replace(0);
+    let golden = golden_ratio_min(1.0, to, &f, 1e-8, 1000).unwrap();
+    let golden_evals = evals.get();
+    assert!((brent.x - golden.min.x).abs() < 1e-3);
+    assert!(
+        brent_evals * 2 < golden_evals,
+        "brent: {brent_evals}, golden: {golden_evals}"
+    );
+
+    assert!(matches!(
+        brent_min(0.0, to, &f, 1e-8, 2),
+        Err(Error::ItersEnded(_, _))
+    ));
+
+    Ok(())
+}
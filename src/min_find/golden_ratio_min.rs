@@ -7,16 +7,33 @@ use super::Minimum1d;
 #[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     FunctionError(String),
-    ItersEnded(Minimum1d, f64),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Converged,
+    /// `max_iter_count` ran out before the interval got narrower than `min_width`.
+    ItersEnded,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenRatioResult {
+    pub min: Minimum1d,
+    pub iterations: usize,
+    pub reason: StopReason,
+    /// Width of the last interval.
+    pub width: f64,
+}
+
+/// Running out of iterations is not an error, the best point so far is returned
+/// with `StopReason::ItersEnded`.
 pub fn golden_ratio_min<E>(
     from: f64,
     to: f64,
     func: &dyn Function<Error = E>,
     min_width: f64,
     max_iter_count: usize,
-) -> Result<Minimum1d, Error>
+) -> Result<GoldenRatioResult, Error>
 where
     E: Debug,
 {
@@ -32,9 +49,14 @@ where
         .apply(b)
         .map_err(|e| Error::FunctionError(format!("{:?}", e)))?;
 
-    for _ in 0..max_iter_count {
+    for iterations in 0..max_iter_count {
         if (a - b).abs() < min_width {
-            return Ok(Minimum1d { x: a, y: f_a });
+            return Ok(GoldenRatioResult {
+                min: Minimum1d { x: a, y: f_a },
+                iterations,
+                reason: StopReason::Converged,
+                width: (b - a).abs(),
+            });
         }
 
         let x1 = a * a_coef + b * b_coef;
@@ -66,7 +88,33 @@ where
         }
     }
 
-    Err(Error::ItersEnded(Minimum1d { x: a, y: f_a }, (b - a).abs()))
+    Ok(GoldenRatioResult {
+        min: Minimum1d { x: a, y: f_a },
+        iterations: max_iter_count,
+        reason: StopReason::ItersEnded,
+        width: (b - a).abs(),
+    })
+}
+
+/// `golden_ratio_min` of `-func`, the returned `y` is a value of `func`.
+pub fn golden_ratio_max<E>(
+    from: f64,
+    to: f64,
+    func: &dyn Function<Error = E>,
+    min_width: f64,
+    max_iter_count: usize,
+) -> Result<GoldenRatioResult, Error>
+where
+    E: Debug,
+{
+    let neg = |x: f64| func.apply(x).map(|y| -y);
+    golden_ratio_min(from, to, &neg, min_width, max_iter_count).map(|res| GoldenRatioResult {
+        min: Minimum1d {
+            x: res.min.x,
+            y: -res.min.y,
+        },
+        ..res
+    })
 }
 
 #[test]
@@ -82,10 +130,34 @@ fn find_min() -> Result<(), Error> {
     let eps = 0.001;
     let max_iter = 10000;
 
-    let min = golden_ratio_min(a, b, &f, eps, max_iter)?;
+    let res = golden_ratio_min(a, b, &f, eps, max_iter)?;
 
     let actual_min_x = 3.389;
-    assert!((min.x - actual_min_x).abs() < 0.01);
+    assert!((res.min.x - actual_min_x).abs() < 0.01);
+    assert_eq!(res.reason, StopReason::Converged);
+    assert!(res.iterations > 0 && res.iterations < max_iter);
+
+    Ok(())
+}
+
+#[test]
+fn find_max() -> Result<(), Error> {
+    let f = |x: f64| -> Result<f64, Error> { Ok(-(x - 2.0) * (x - 2.0)) };
+
+    let max = golden_ratio_max(0.0, 5.0, &f, 1e-6, 10000)?.min;
+    assert!((max.x - 2.0).abs() < 1e-3 && max.y.abs() < 1e-6, "{max:?}");
+
+    Ok(())
+}
+
+#[test]
+fn flat_interval() -> Result<(), Error> {
+    let f = |_: f64| -> Result<f64, Error> { Ok(1.0) };
+
+    let res = golden_ratio_min(0.0, 1.0, &f, 1e-6, 100)?;
+    assert_eq!(res.reason, StopReason::ItersEnded);
+    assert_eq!(res.iterations, 100);
+    assert!(res.width > 1e-6);
 
     Ok(())
 }
@@ -1,14 +1,31 @@
 use std::{cell::RefCell, fmt::Debug};
 
-use crate::functions::function::{Function, FunctionNd};
+use crate::{
+    functions::function::{Function, FunctionNd},
+    iteration_limit::IterationLimit,
+};
 
-use super::{golden_ratio_min::golden_ratio_min, MinimumNd};
+use super::{
+    brent_min::{self, brent_min},
+    golden_ratio_min::{golden_ratio_min, StopReason},
+    MinimumNd,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     FunctionError(String),
     SizeMismatch,
     ItersEnded(MinimumNd, f64),
+    /// The line search along the antigradient ran out of iterations,
+    /// holds the point it started from and how many iterations it did.
+    LineSearchStalled(MinimumNd, usize),
+}
+
+/// 1-D search used to pick the step along the antigradient.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineSearch {
+    GoldenRatio,
+    Brent,
 }
 
 pub fn gradients_min<E1, E2>(
@@ -16,7 +33,22 @@ pub fn gradients_min<E1, E2>(
     grad: &[&dyn FunctionNd<Error = E2>],
     x0: &[f64],
     eps: f64,
-    max_iter_count: usize,
+    limit: IterationLimit,
+) -> Result<MinimumNd, Error>
+where
+    E1: Debug,
+    E2: Debug,
+{
+    gradients_min_with(f, grad, x0, eps, limit, LineSearch::GoldenRatio)
+}
+
+pub fn gradients_min_with<E1, E2>(
+    f: &dyn FunctionNd<Error = E1>,
+    grad: &[&dyn FunctionNd<Error = E2>],
+    x0: &[f64],
+    eps: f64,
+    limit: IterationLimit,
+    line_search: LineSearch,
 ) -> Result<MinimumNd, Error>
 where
     E1: Debug,
@@ -53,23 +85,46 @@ where
     }
 
     let mut step = 0.0;
-    for _ in 0..max_iter_count {
+    for _ in 0..limit.max {
         let norm_h: f64 = h.iter().map(|x| x * x).sum();
-        let alpha_res = golden_ratio_min(
-            0.0,
-            1.0,
-            &AlphaFunc {
-                x_plus_alpha_h: RefCell::new(&mut x_plus_alpha_h),
-                x: &x,
-                h: &h,
-                f,
+        let alpha_func = AlphaFunc {
+            x_plus_alpha_h: RefCell::new(&mut x_plus_alpha_h),
+            x: &x,
+            h: &h,
+            f,
+        };
+        // Either the step or how many iterations the stalled search did
+        let alpha = match line_search {
+            LineSearch::GoldenRatio => golden_ratio_min(0.0, 1.0, &alpha_func, eps, limit.max)
+                .map_err(|e| Error::FunctionError(format!("{:?}", e)))
+                .map(|res| match res.reason {
+                    StopReason::Converged => Ok(res.min.x),
+                    StopReason::ItersEnded => Err(res.iterations),
+                })?,
+            LineSearch::Brent => match brent_min(0.0, 1.0, &alpha_func, eps, limit.max) {
+                Ok(min) => Ok(min.x),
+                Err(brent_min::Error::ItersEnded(_, _)) => Err(limit.max),
+                Err(e) => return Err(Error::FunctionError(format!("{:?}", e))),
             },
-            eps,
-            max_iter_count,
-        )
-        .map_err(|e| Error::FunctionError(format!("{:?}", e)))?;
+        };
+        let alpha = match alpha {
+            Ok(alpha) => alpha,
+            Err(iterations) => {
+                return Err(Error::LineSearchStalled(
+                    MinimumNd {
+                        y: f.apply(&x)
+                            .map_err(|e| Error::FunctionError(format!("{:?}", e)))?,
+                        x,
+                    },
+                    iterations,
+                ))
+            }
+        };
+        // the search leaves the last point it tried in x_plus_alpha_h
+        for i in 0..n {
+            x_plus_alpha_h[i] = x[i] + alpha * h[i];
+        }
 
-        let alpha = alpha_res.x;
         step = alpha * alpha * norm_h;
         if step < eps * eps {
             return Ok(MinimumNd {
@@ -85,14 +140,95 @@ where
             .map_err(|e| Error::FunctionError(format!("{:?}", e)))?;
     }
 
-    Err(Error::ItersEnded(
-        MinimumNd {
-            y: f.apply(&x_plus_alpha_h)
-                .map_err(|e| Error::FunctionError(format!("{:?}", e)))?,
-            x: x_plus_alpha_h,
-        },
-        step.sqrt(),
-    ))
+    let best = MinimumNd {
+        y: f.apply(&x_plus_alpha_h)
+            .map_err(|e| Error::FunctionError(format!("{:?}", e)))?,
+        x: x_plus_alpha_h,
+    };
+    limit.exceeded(best.clone(), Error::ItersEnded(best, step.sqrt()))
+}
+
+/// `gradients_min` of `-f`, the returned `y` is a value of `f`.
+pub fn gradients_max<E1, E2>(
+    f: &dyn FunctionNd<Error = E1>,
+    grad: &[&dyn FunctionNd<Error = E2>],
+    x0: &[f64],
+    eps: f64,
+    limit: IterationLimit,
+) -> Result<MinimumNd, Error>
+where
+    E1: Debug,
+    E2: Debug,
+{
+    let neg = |x: &[f64]| f.apply(x).map(|y| -y);
+    let neg_grad = grad
+        .iter()
+        .map(|g| move |x: &[f64]| g.apply(x).map(|y| -y))
+        .collect::<Vec<_>>();
+
+    gradients_min(
+        &neg,
+        &neg_grad
+            .iter()
+            .map(|g| g as &dyn FunctionNd<Error = E2>)
+            .collect::<Vec<_>>(),
+        x0,
+        eps,
+        limit,
+    )
+    .map(|m| MinimumNd { x: m.x, y: -m.y })
+    .map_err(|e| match e {
+        Error::ItersEnded(m, step) => Error::ItersEnded(MinimumNd { x: m.x, y: -m.y }, step),
+        Error::LineSearchStalled(m, iterations) => {
+            Error::LineSearchStalled(MinimumNd { x: m.x, y: -m.y }, iterations)
+        }
+        e => e,
+    })
+}
+
+/// Central difference estimate of `df/dx_i`.
+struct CentralDifference<'a, E> {
+    f: &'a dyn FunctionNd<Error = E>,
+    i: usize,
+}
+
+impl<'a, E> FunctionNd for CentralDifference<'a, E> {
+    type Error = E;
+
+    fn apply(&self, x: &[f64]) -> Result<f64, Self::Error> {
+        let h = f64::EPSILON.cbrt() * x[self.i].abs().max(1.0);
+        let mut shifted = x.to_owned();
+        shifted[self.i] = x[self.i] + h;
+        let right = self.f.apply(&shifted)?;
+        shifted[self.i] = x[self.i] - h;
+        let left = self.f.apply(&shifted)?;
+        Ok((right - left) / (2.0 * h))
+    }
+}
+
+/// Same as `gradients_min`, but the gradient is estimated with central differences.
+pub fn gradients_min_numeric<E>(
+    f: &dyn FunctionNd<Error = E>,
+    x0: &[f64],
+    eps: f64,
+    limit: IterationLimit,
+) -> Result<MinimumNd, Error>
+where
+    E: Debug,
+{
+    let partials = (0..x0.len())
+        .map(|i| CentralDifference { f, i })
+        .collect::<Vec<_>>();
+    gradients_min(
+        f,
+        &partials
+            .iter()
+            .map(|df| df as &dyn FunctionNd<Error = E>)
+            .collect::<Vec<_>>(),
+        x0,
+        eps,
+        limit,
+    )
 }
 
 #[test]
@@ -121,7 +257,7 @@ fn gradients() -> Result<(), Error> {
 
     let x0 = [3.0, 3.0];
     let actual = [1.0, 1.0];
-    let res = gradients_min(&f, &[&grad1, &grad2], &x0, 0.00001, 10000)?;
+    let res = gradients_min(&f, &[&grad1, &grad2], &x0, 0.00001, 10000.into())?;
 
     assert!(
         res.x
@@ -135,3 +271,92 @@ fn gradients() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn gradients_limit() -> Result<(), Error> {
+    let f = |x: &[f64]| -> Result<f64, Error> {
+        Ok(10.0 * (x[1] - x[0] * x[0]) * (x[1] - x[0] * x[0]) + (1.0 - x[0]) * (1.0 - x[0]))
+    };
+    let grad1 = |x: &[f64]| -> Result<f64, Error> {
+        Ok(-40.0 * x[0] * x[1] + 40.0 * x[0] * x[0] * x[0] - 2.0 + 2.0 * x[0])
+    };
+    let grad2 = |x: &[f64]| -> Result<f64, Error> { Ok(20.0 * x[1] - 20.0 * x[0] * x[0]) };
+
+    let x0 = [3.0, 3.0];
+    assert!(matches!(
+        gradients_min(&f, &[&grad1, &grad2], &x0, 1e-4, IterationLimit::new(30)),
+        Err(Error::ItersEnded(_, _))
+    ));
+
+    let res = gradients_min(
+        &f,
+        &[&grad1, &grad2],
+        &x0,
+        1e-4,
+        IterationLimit::return_best(30),
+    )?;
+    assert!(res.y < f(&x0)?);
+
+    Ok(())
+}
+
+#[test]
+fn gradients_numeric() -> Result<(), Error> {
+    let rosenbrock = |x: &[f64]| -> Result<f64, Error> {
+        Ok(10.0 * (x[1] - x[0] * x[0]) * (x[1] - x[0] * x[0]) + (1.0 - x[0]) * (1.0 - x[0]))
+    };
+
+    let res = gradients_min_numeric(&rosenbrock, &[3.0, 3.0], 0.00001, 10000.into())?;
+    assert!(
+        (res.x[0] - 1.0).abs() < 0.01 && (res.x[1] - 1.0).abs() < 0.01,
+        "{res:?}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn gradients_maximum() -> Result<(), Error> {
+    let f = |x: &[f64]| -> Result<f64, Error> {
+        Ok(-10.0 * (x[1] - x[0] * x[0]) * (x[1] - x[0] * x[0]) - (1.0 - x[0]) * (1.0 - x[0]))
+    };
+    let df_dx = |x: &[f64]| -> Result<f64, Error> {
+        Ok(40.0 * x[0] * x[1] - 40.0 * x[0] * x[0] * x[0] + 2.0 - 2.0 * x[0])
+    };
+    let df_dy = |x: &[f64]| -> Result<f64, Error> { Ok(-20.0 * x[1] + 20.0 * x[0] * x[0]) };
+
+    let res = gradients_max(&f, &[&df_dx, &df_dy], &[3.0, 3.0], 0.00001, 10000.into())?;
+    assert!(
+        (res.x[0] - 1.0).abs() < 0.01 && (res.x[1] - 1.0).abs() < 0.01,
+        "{res:?}"
+    );
+    assert!(res.y.abs() < 1e-3 && res.y <= 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn gradients_brent() -> Result<(), Error> {
+    let f = |x: &[f64]| -> Result<f64, Error> {
+        Ok(10.0 * (x[1] - x[0] * x[0]) * (x[1] - x[0] * x[0]) + (1.0 - x[0]) * (1.0 - x[0]))
+    };
+    let grad1 = |x: &[f64]| -> Result<f64, Error> {
+        Ok(-40.0 * x[0] * x[1] + 40.0 * x[0] * x[0] * x[0] - 2.0 + 2.0 * x[0])
+    };
+    let grad2 = |x: &[f64]| -> Result<f64, Error> { Ok(20.0 * x[1] - 20.0 * x[0] * x[0]) };
+
+    let res = gradients_min_with(
+        &f,
+        &[&grad1, &grad2],
+        &[3.0, 3.0],
+        0.00001,
+        10000.into(),
+        LineSearch::Brent,
+    )?;
+    assert!(
+        (res.x[0] - 1.0).abs() < 0.01 && (res.x[1] - 1.0).abs() < 0.01,
+        "{res:?}"
+    );
+
+    Ok(())
+}
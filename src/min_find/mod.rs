@@ -1,5 +1,7 @@
+pub mod brent_min;
 pub mod golden_ratio_min;
 pub mod gradients_min;
+pub mod nelder_mead;
 pub mod penalty_min;
 
 #[derive(Debug, Clone, PartialEq)]
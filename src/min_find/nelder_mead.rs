@@ -0,0 +1,154 @@
+use std::fmt::Debug;
+
+use crate::functions::function::FunctionNd;
+
+use super::MinimumNd;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    FunctionError(String),
+    ItersEnded(MinimumNd, f64),
+}
+
+const REFLECTION: f64 = 1.0;
+const EXPANSION: f64 = 2.0;
+const CONTRACTION: f64 = 0.5;
+const SHRINK: f64 = 0.5;
+
+/// Derivative free simplex search. The starting simplex is `x0` and `x0 + step * e_i`.
+/// Stops once either the simplex size or the spread of `f` over its vertices is below `eps`.
+pub fn nelder_mead<E>(
+    f: &dyn FunctionNd<Error = E>,
+    x0: &[f64],
+    step: f64,
+    eps: f64,
+    max_iter_count: usize,
+) -> Result<MinimumNd, Error>
+where
+    E: Debug,
+{
+    let n = x0.len();
+    let eval = |x: &[f64]| {
+        f.apply(x)
+            .map_err(|e| Error::FunctionError(format!("{:?}", e)))
+    };
+    // x + coef * (y - x)
+    let towards = |x: &[f64], y: &[f64], coef: f64| {
+        x.iter()
+            .zip(y.iter())
+            .map(|(x, y)| x + coef * (y - x))
+            .collect::<Vec<_>>()
+    };
+
+    let mut simplex = vec![x0.to_owned()];
+    for i in 0..n {
+        let mut x = x0.to_owned();
+        x[i] += step;
+        simplex.push(x);
+    }
+    let mut values = simplex
+        .iter()
+        .map(|x| eval(x))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut size = f64::INFINITY;
+    for _ in 0..max_iter_count {
+        let mut order = (0..=n).collect::<Vec<_>>();
+        order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+        simplex = order.iter().map(|i| simplex[*i].clone()).collect();
+        values = order.iter().map(|i| values[*i]).collect();
+
+        size = simplex[1..]
+            .iter()
+            .flat_map(|x| x.iter().zip(simplex[0].iter()).map(|(a, b)| (a - b).abs()))
+            .fold(0.0, f64::max);
+        if size < eps || values[n] - values[0] < eps {
+            return Ok(MinimumNd {
+                x: simplex.swap_remove(0),
+                y: values[0],
+            });
+        }
+
+        let centroid = (0..n)
+            .map(|j| simplex[..n].iter().map(|x| x[j]).sum::<f64>() / (n as f64))
+            .collect::<Vec<_>>();
+
+        let reflected = towards(&centroid, &simplex[n], -REFLECTION);
+        let f_reflected = eval(&reflected)?;
+
+        if f_reflected < values[0] {
+            let expanded = towards(&centroid, &simplex[n], -EXPANSION);
+            let f_expanded = eval(&expanded)?;
+            if f_expanded < f_reflected {
+                simplex[n] = expanded;
+                values[n] = f_expanded;
+            } else {
+                simplex[n] = reflected;
+                values[n] = f_reflected;
+            }
+            continue;
+        }
+
+        if f_reflected < values[n - 1] {
+            simplex[n] = reflected;
+            values[n] = f_reflected;
+            continue;
+        }
+
+        // Outside contraction when the reflected point beats the worst one, inside otherwise
+        let contracted = if f_reflected < values[n] {
+            towards(&centroid, &reflected, CONTRACTION)
+        } else {
+            towards(&centroid, &simplex[n], CONTRACTION)
+        };
+        let f_contracted = eval(&contracted)?;
+        if f_contracted < values[n].min(f_reflected) {
+            simplex[n] = contracted;
+            values[n] = f_contracted;
+            continue;
+        }
+
+        for i in 1..=n {
+            simplex[i] = towards(&simplex[0], &simplex[i], SHRINK);
+            values[i] = eval(&simplex[i])?;
+        }
+    }
+
+    let best = (0..=n)
+        .min_by(|a, b| values[*a].total_cmp(&values[*b]))
+        .unwrap_or(0);
+    Err(Error::ItersEnded(
+        MinimumNd {
+            x: simplex[best].clone(),
+            y: values[best],
+        },
+        size,
+    ))
+}
+
+#[test]
+fn nelder_mead_quadratic() -> Result<(), Error> {
+    let f = |x: &[f64]| -> Result<f64, Error> {
+        Ok((x[0] - 3.0) * (x[0] - 3.0) + (x[1] + 1.0) * (x[1] + 1.0))
+    };
+
+    let res = nelder_mead(&f, &[0.0, 0.0], 1.0, 1e-10, 1000)?;
+    assert!(
+        (res.x[0] - 3.0).abs() < 1e-4 && (res.x[1] + 1.0).abs() < 1e-4,
+        "{res:?}"
+    );
+
+    let abs = |x: &[f64]| -> Result<f64, Error> { Ok((x[0] - 1.0).abs() + (x[1] - 2.0).abs()) };
+    let res = nelder_mead(&abs, &[0.0, 0.0], 1.0, 1e-10, 1000)?;
+    assert!(
+        (res.x[0] - 1.0).abs() < 1e-4 && (res.x[1] - 2.0).abs() < 1e-4,
+        "{res:?}"
+    );
+
+    assert!(matches!(
+        nelder_mead(&f, &[0.0, 0.0], 1.0, 1e-10, 3),
+        Err(Error::ItersEnded(_, _))
+    ));
+
+    Ok(())
+}
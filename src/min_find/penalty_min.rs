@@ -1,15 +1,40 @@
 use std::fmt::Debug;
 
-use crate::functions::function::Function;
+use crate::functions::function::{Function, FunctionNd};
 
-use super::{golden_ratio_min::golden_ratio_min, Minimum1d};
+use super::{
+    golden_ratio_min::{golden_ratio_min, StopReason},
+    nelder_mead::nelder_mead,
+    Minimum1d, MinimumNd,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     FunctionError(String),
     ItersEnded(Minimum1d, f64),
+    ItersEndedNd(MinimumNd, f64),
+    SizeMismatch,
+    /// The line search for one of the penalized functions ran out of iterations,
+    /// holds its best point and how many iterations it did.
+    LineSearchStalled(Minimum1d, usize),
 }
 
+/// `f(x) + sum(max(0, c(x))^2) / eps`, the function that is minimized for a given `eps`.
+pub fn penalized<E>(
+    f: &dyn Function<Error = E>,
+    constraints: &[&dyn Function<Error = E>],
+    eps: f64,
+    x: f64,
+) -> Result<f64, E> {
+    constraints
+        .iter()
+        .map(|c| c.apply(x).map(|cx| f64::max(0.0, cx)))
+        .map(|m| m.map(|m| m * m))
+        .try_fold(0.0, |acc, m| m.map(|m| m + acc))
+        .and_then(|sum| f.apply(x).map(|y| y + sum / eps))
+}
+
+/// Returns the minimum and the `eps` of the last penalized function.
 pub fn penalty_min<E>(
     f: &dyn Function<Error = E>,
     constraints: &[&dyn Function<Error = E>],
@@ -18,7 +43,7 @@ pub fn penalty_min<E>(
     start_eps: f64,
     min_step: f64,
     max_iter_count: usize,
-) -> Result<Minimum1d, Error>
+) -> Result<(Minimum1d, f64), Error>
 where
     E: Debug,
 {
@@ -27,22 +52,29 @@ where
     let mut prev_min = from;
     let mut prev_prev_min = 0.0;
     for _ in 0..max_iter_count {
-        let penalty_func = |x| {
-            constraints
-                .iter()
-                .map(|c| c.apply(x).map(|cx| f64::max(0.0, cx)))
-                .map(|m| m.map(|m| m * m))
-                .try_fold(0.0, |acc, m| m.map(|m| m + acc))
-                .and_then(|sum| f.apply(x).map(|y| y + sum / eps))
-        };
-        let min = golden_ratio_min(from, to, &penalty_func, min_step, max_iter_count)
+        let penalty_func = |x| penalized(f, constraints, eps, x);
+        let res = golden_ratio_min(from, to, &penalty_func, min_step, max_iter_count)
             .map_err(|e| Error::FunctionError(format!("{:?}", e)))?;
+        if res.reason == StopReason::ItersEnded {
+            return Err(Error::LineSearchStalled(
+                Minimum1d {
+                    x: res.min.x,
+                    y: f.apply(res.min.x)
+                        .map_err(|e| Error::FunctionError(format!("{:?}", e)))?,
+                },
+                res.iterations,
+            ));
+        }
+        let min = res.min;
         if (prev_min - min.x).abs() < min_step {
-            return Ok(Minimum1d {
-                x: min.x,
-                y: f.apply(min.x)
-                    .map_err(|e| Error::FunctionError(format!("{:?}", e)))?,
-            });
+            return Ok((
+                Minimum1d {
+                    x: min.x,
+                    y: f.apply(min.x)
+                        .map_err(|e| Error::FunctionError(format!("{:?}", e)))?,
+                },
+                eps,
+            ));
         }
         eps *= 0.5;
         prev_prev_min = prev_min;
@@ -59,6 +91,133 @@ where
     ))
 }
 
+/// `penalty_min` of `-f`, the returned `y` is a value of `f`.
+pub fn penalty_max<E>(
+    f: &dyn Function<Error = E>,
+    constraints: &[&dyn Function<Error = E>],
+    from: f64,
+    to: f64,
+    start_eps: f64,
+    min_step: f64,
+    max_iter_count: usize,
+) -> Result<(Minimum1d, f64), Error>
+where
+    E: Debug,
+{
+    let neg = |x: f64| f.apply(x).map(|y| -y);
+    penalty_min(
+        &neg,
+        constraints,
+        from,
+        to,
+        start_eps,
+        min_step,
+        max_iter_count,
+    )
+    .map(|(m, eps)| (Minimum1d { x: m.x, y: -m.y }, eps))
+    .map_err(|e| match e {
+        Error::ItersEnded(m, step) => Error::ItersEnded(Minimum1d { x: m.x, y: -m.y }, step),
+        Error::LineSearchStalled(m, iterations) => {
+            Error::LineSearchStalled(Minimum1d { x: m.x, y: -m.y }, iterations)
+        }
+        e => e,
+    })
+}
+
+/// `penalized` for `N` dimensions, leaving the `[from, to]` box is penalized the same way.
+pub fn penalized_nd<E>(
+    f: &dyn FunctionNd<Error = E>,
+    constraints: &[&dyn FunctionNd<Error = E>],
+    from: &[f64],
+    to: &[f64],
+    eps: f64,
+    x: &[f64],
+) -> Result<f64, E> {
+    let outside: f64 = x
+        .iter()
+        .zip(from.iter().zip(to.iter()))
+        .map(|(x, (from, to))| f64::max(0.0, from - x) + f64::max(0.0, x - to))
+        .map(|m| m * m)
+        .sum();
+    constraints
+        .iter()
+        .map(|c| c.apply(x).map(|cx| f64::max(0.0, cx)))
+        .map(|m| m.map(|m| m * m))
+        .try_fold(outside, |acc, m| m.map(|m| m + acc))
+        .and_then(|sum| f.apply(x).map(|y| y + sum / eps))
+}
+
+/// Same as `penalty_min`, but each penalized function is minimized with `nelder_mead`,
+/// starting from the previous minimum (the center of the box at first).
+pub fn penalty_min_nd<E>(
+    f: &dyn FunctionNd<Error = E>,
+    constraints: &[&dyn FunctionNd<Error = E>],
+    from: &[f64],
+    to: &[f64],
+    start_eps: f64,
+    min_step: f64,
+    max_iter_count: usize,
+) -> Result<(MinimumNd, f64), Error>
+where
+    E: Debug,
+{
+    if from.len() != to.len() {
+        return Err(Error::SizeMismatch);
+    }
+
+    let simplex_step = from
+        .iter()
+        .zip(to.iter())
+        .map(|(from, to)| (to - from).abs() / 4.0)
+        .fold(min_step, f64::max);
+    let mut eps = start_eps;
+
+    let mut prev_min = from
+        .iter()
+        .zip(to.iter())
+        .map(|(from, to)| (from + to) / 2.0)
+        .collect::<Vec<_>>();
+    let mut last_step = f64::INFINITY;
+    for _ in 0..max_iter_count {
+        let penalty_func = |x: &[f64]| penalized_nd(f, constraints, from, to, eps, x);
+        let min = nelder_mead(
+            &penalty_func,
+            &prev_min,
+            simplex_step,
+            min_step * min_step,
+            max_iter_count,
+        )
+        .map_err(|e| Error::FunctionError(format!("{:?}", e)))?;
+
+        last_step = prev_min
+            .iter()
+            .zip(min.x.iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        prev_min = min.x;
+        if last_step < min_step {
+            return Ok((
+                MinimumNd {
+                    y: f.apply(&prev_min)
+                        .map_err(|e| Error::FunctionError(format!("{:?}", e)))?,
+                    x: prev_min,
+                },
+                eps,
+            ));
+        }
+        eps *= 0.5;
+    }
+
+    Err(Error::ItersEndedNd(
+        MinimumNd {
+            y: f.apply(&prev_min)
+                .map_err(|e| Error::FunctionError(format!("{:?}", e)))?,
+            x: prev_min,
+        },
+        last_step,
+    ))
+}
+
 #[test]
 fn penaty() -> Result<(), Error> {
     let f = |x: f64| -> Result<f64, Error> {
@@ -70,10 +229,47 @@ fn penaty() -> Result<(), Error> {
     let from = -10.0;
     let to = 10.0;
 
-    let res = penalty_min(&f, &[&c1, &c2], from, to, 0.001, 0.001, 1001)?;
+    let (res, _) = penalty_min(&f, &[&c1, &c2], from, to, 0.001, 0.001, 1001)?;
     let actual = -0.262;
     dbg!(&res);
     assert!((res.x - actual).abs() < 0.01);
 
     Ok(())
 }
+
+#[test]
+fn penalty_nd() -> Result<(), Error> {
+    let f = |x: &[f64]| -> Result<f64, Error> {
+        Ok((x[0] - 2.0) * (x[0] - 2.0) + (x[1] - 2.0) * (x[1] - 2.0))
+    };
+    // x + y <= 2
+    let c = |x: &[f64]| -> Result<f64, Error> { Ok(x[0] + x[1] - 2.0) };
+
+    let (res, _) = penalty_min_nd(&f, &[&c], &[-5.0, -5.0], &[5.0, 5.0], 1.0, 1e-4, 1000)?;
+    assert!(
+        (res.x[0] - 1.0).abs() < 0.01 && (res.x[1] - 1.0).abs() < 0.01,
+        "{res:?}"
+    );
+
+    assert!(matches!(
+        penalty_min_nd(&f, &[&c], &[0.0], &[1.0, 1.0], 1.0, 1e-4, 1000),
+        Err(Error::SizeMismatch)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn penalty_maximum() -> Result<(), Error> {
+    let f = |x: f64| -> Result<f64, Error> { Ok(-(x - 2.0) * (x - 2.0)) };
+    // x <= 1
+    let c = |x: f64| -> Result<f64, Error> { Ok(x - 1.0) };
+
+    let (res, _) = penalty_max(&f, &[&c], 0.0, 5.0, 0.001, 0.0001, 1001)?;
+    assert!(
+        (res.x - 1.0).abs() < 0.01 && (res.y + 1.0).abs() < 0.02,
+        "{res:?}"
+    );
+
+    Ok(())
+}
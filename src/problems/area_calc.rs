@@ -1,14 +1,15 @@
 use crate::{
-    area_calc::calc_area,
-    functions::function::Function,
-    mathparse::{DefaultRuntime, Expression},
+    area_calc::{calc_area, Error, NewtonCotes},
+    iteration_limit::IterationLimit,
+    mathparse::{DefaultRuntime, Expression, Runtime},
 };
 
 use super::{
+    csv_download,
     form::Form,
-    graph::{Graph, Path},
-    validate_expr, validate_from_str, Problem, ProblemCreator, Solution, SolutionParagraph,
-    ValidationError,
+    graph::{plot_points, Graph, Path},
+    validate_expr, validate_from_str, validate_positive, Problem, ProblemCreator, Solution,
+    SolutionParagraph, ValidationError,
 };
 
 struct AreaCalcProblem {
@@ -37,7 +38,8 @@ impl Problem for AreaCalcProblem {
             self.x23,
             0.001,
             self.eps,
-            self.max_iter_count,
+            NewtonCotes::Closed,
+            IterationLimit::new(self.max_iter_count),
         );
 
         match res {
@@ -66,21 +68,24 @@ impl Problem for AreaCalcProblem {
                             .unwrap_or_else(|_| String::new())
                     )),
                 ];
+                if let Some(warning) = &area.warning {
+                    expl.push(SolutionParagraph::Text(format!("Warning: {warning}")));
+                }
 
-                let p1 = f1.sample(
+                let p1 = plot_points(
+                    &f1,
                     f64::min(self.x12[0], self.x13[0]),
                     f64::max(self.x12[1], self.x13[1]),
-                    50,
                 );
-                let p3 = f3.sample(
+                let p3 = plot_points(
+                    &f3,
                     f64::min(self.x23[0], self.x13[0]),
                     f64::max(self.x23[1], self.x13[1]),
-                    50,
                 );
-                let p2 = f2.sample(
+                let p2 = plot_points(
+                    &f2,
                     f64::min(self.x23[0], self.x12[0]),
                     f64::max(self.x23[1], self.x12[1]),
-                    50,
                 );
                 if let Err(e) = &p1 {
                     expl.push(SolutionParagraph::RuntimeError(format!("{:?}", e)));
@@ -107,6 +112,10 @@ impl Problem for AreaCalcProblem {
                 if let (Ok(p1), Ok(p2), Ok(p3), Ok(mut seg_1), Ok(mut seg_2), Ok(mut seg_3)) =
                     (p1, p2, p3, seg_1, seg_2, seg_3)
                 {
+                    expl.push(csv_download("f1.csv", &p1));
+                    expl.push(csv_download("f2.csv", &p2));
+                    expl.push(csv_download("f3.csv", &p3));
+
                     let mut a = vec![];
                     a.append(&mut seg_1);
                     a.append(&mut seg_3);
@@ -117,21 +126,31 @@ impl Problem for AreaCalcProblem {
                             pts: a,
                             kind: super::graph::PathKind::Filled,
                             color: (0.5, 0.5, 0.5),
+                            label: Some("area".to_string()),
                         },
                         Path {
                             pts: p1,
                             kind: super::graph::PathKind::Line,
                             color: (1.0, 0.0, 0.0),
+                            label: Some("f1".to_string()),
                         },
                         Path {
                             pts: p2,
                             kind: super::graph::PathKind::Line,
                             color: (0.0, 1.0, 0.0),
+                            label: Some("f2".to_string()),
                         },
                         Path {
                             pts: p3,
                             kind: super::graph::PathKind::Line,
                             color: (0.0, 0.0, 1.0),
+                            label: Some("f3".to_string()),
+                        },
+                        Path {
+                            pts: area.vertices.to_vec(),
+                            kind: super::graph::PathKind::Dot,
+                            color: (0.0, 0.0, 0.0),
+                            label: Some("vertices".to_string()),
                         },
                     ]);
 
@@ -143,10 +162,25 @@ impl Problem for AreaCalcProblem {
                     }
                 }
 
-                Solution { explanation: expl }
+                Solution {
+                    explanation: expl,
+                    values: vec![
+                        ("area".to_string(), area.area),
+                        ("x12".to_string(), area.x12),
+                        ("x13".to_string(), area.x13),
+                        ("x23".to_string(), area.x23),
+                    ],
+                }
             }
+            Err(Error::NoIntersection { which: (a, b) }) => Solution {
+                explanation: vec![SolutionParagraph::RuntimeError(format!(
+                    "curves f{a} and f{b} do not intersect in the given range"
+                ))],
+                values: vec![],
+            },
             Err(e) => Solution {
                 explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                values: vec![],
             },
         }
     }
@@ -189,7 +223,7 @@ impl Default for AreaCalcProblemCreator {
 }
 
 impl ProblemCreator for AreaCalcProblemCreator {
-    fn try_create(&self) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
+    fn try_create(&self, runtime: &dyn Runtime) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
         let mut f1 = None;
         let mut f2 = None;
         let mut f3 = None;
@@ -206,18 +240,18 @@ impl ProblemCreator for AreaCalcProblemCreator {
 
         for (name, val) in self.form.get_fields() {
             let res = match name {
-                "f1" => validate_expr("f1", val, Some(&["x"]), &DefaultRuntime::default(), &mut f1),
-                "f2" => validate_expr("f2", val, Some(&["x"]), &DefaultRuntime::default(), &mut f2),
-                "f3" => validate_expr("f3", val, Some(&["x"]), &DefaultRuntime::default(), &mut f3),
+                "f1" => validate_expr("f1", val, Some(&["x"]), runtime, &mut f1),
+                "f2" => validate_expr("f2", val, Some(&["x"]), runtime, &mut f2),
+                "f3" => validate_expr("f3", val, Some(&["x"]), runtime, &mut f3),
                 "x12_from" => validate_from_str::<f64>("x12_from", val, &mut x12_from),
                 "x12_to" => validate_from_str::<f64>("x12_to", val, &mut x12_to),
                 "x13_from" => validate_from_str::<f64>("x13_from", val, &mut x13_from),
                 "x13_to" => validate_from_str::<f64>("x13_to", val, &mut x13_to),
                 "x23_from" => validate_from_str::<f64>("x23_from", val, &mut x23_from),
                 "x23_to" => validate_from_str::<f64>("x23_to", val, &mut x23_to),
-                "eps" => validate_from_str::<f64>("eps", val, &mut eps),
+                "eps" => validate_positive::<f64>("eps", val, &mut eps),
                 "max_iter_count" => {
-                    validate_from_str::<usize>("max_iter_count", val, &mut max_iter_count)
+                    validate_positive::<usize>("max_iter_count", val, &mut max_iter_count)
                 }
                 _ => Err(ValidationError(format!(
                     "{name} - no such field (probably a devs error)"
@@ -297,4 +331,29 @@ impl ProblemCreator for AreaCalcProblemCreator {
     fn set_field(&mut self, name: &str, val: String) {
         self.form.set(name, val)
     }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[test]
+fn area_csv_downloads() {
+    let solution = AreaCalcProblemCreator::default()
+        .try_create(&DefaultRuntime::default())
+        .ok()
+        .unwrap()
+        .solve();
+
+    let downloads = solution.downloads().collect::<Vec<_>>();
+    assert_eq!(
+        downloads.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+        vec!["f1.csv", "f2.csv", "f3.csv"]
+    );
+    for (_, contents) in downloads {
+        assert!(contents.lines().count() > 2);
+        assert!(contents
+            .lines()
+            .all(|l| l.split(',').all(|v| v.parse::<f64>().is_ok())));
+    }
 }
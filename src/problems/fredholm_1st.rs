@@ -1,15 +1,16 @@
-use std::{fs::File, io::Write};
+use std::path::{Path as FilePath, PathBuf};
 
 use crate::{
-    integral_eq::fredholm_first_kind::fredholm_1st_system,
-    mathparse::{DefaultRuntime, Expression},
+    integral_eq::fredholm_first_kind::{fredholm_1st_system, fredholm_1st_system_adaptive},
+    mathparse::{DefaultRuntime, Expression, PositionalRuntime, Runtime},
 };
 
 use super::{
+    default_output_dir,
     form::Form,
     graph::{Graph, Path, PathKind},
-    validate_expr, validate_from_str, Problem, ProblemCreator, Solution, SolutionParagraph,
-    ValidationError,
+    kernel_heatmap, resolve_output_path, shape_summary, validate_expr, validate_from_str,
+    validate_positive, Problem, ProblemCreator, Solution, SolutionParagraph, ValidationError,
 };
 
 struct Fredholm1stProblem {
@@ -20,27 +21,75 @@ struct Fredholm1stProblem {
     eps: f64,
     n: usize,
     max_iter_count: usize,
-    dest_file: String,
+    dest_file: PathBuf,
+    adaptive: bool,
+    max_n: usize,
+    tol: f64,
+    symmetrize: bool,
 }
 
 impl Problem for Fredholm1stProblem {
     fn solve(&self) -> Solution {
-        let res = fredholm_1st_system(
-            &|x, s| {
-                self.kernel
-                    .eval(&DefaultRuntime::new(&[("x", x), ("s", s)]))
-            },
-            &|x| self.right_side.eval(&DefaultRuntime::new(&[("x", x)])),
-            self.from,
-            self.to,
-            self.n,
-            self.eps,
-            self.max_iter_count,
-        );
+        let compiled_kernel = match self.kernel.compile(&DefaultRuntime::default()) {
+            Ok(kernel) => kernel,
+            Err(e) => {
+                return Solution {
+                    explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                    values: vec![],
+                }
+            }
+        };
+        let kernel_runtime = PositionalRuntime::new(&["x", "s"]);
+        let kernel = |x, s| {
+            kernel_runtime.set(&[x, s]);
+            compiled_kernel.eval(&kernel_runtime)
+        };
+        let right_side = |x| self.right_side.eval(&DefaultRuntime::new(&[("x", x)]));
+
+        let res = if self.adaptive {
+            fredholm_1st_system_adaptive(
+                &kernel,
+                &right_side,
+                self.from,
+                self.to,
+                self.n,
+                self.max_n,
+                self.tol,
+                self.eps,
+                self.max_iter_count,
+                self.symmetrize,
+            )
+            .map(|(res, n, cg)| (res, Some(n), cg))
+        } else {
+            fredholm_1st_system(
+                &kernel,
+                &right_side,
+                self.from,
+                self.to,
+                self.n,
+                self.eps,
+                self.max_iter_count,
+                self.symmetrize,
+            )
+            .map(|(res, cg)| (res, None, cg))
+        };
 
         match res {
-            Ok(res) => {
+            Ok((res, final_n, cg)) => {
                 let mut solution = vec![];
+                if let Some(n) = final_n {
+                    solution.push(SolutionParagraph::Text(format!("Solved with n = {n}")));
+                }
+                solution.push(SolutionParagraph::Text(format!(
+                    "Conjugate gradients: {} iterations, residual {:.2e}{}",
+                    cg.iterations,
+                    cg.residual,
+                    if cg.converged {
+                        ""
+                    } else {
+                        " (did not converge)"
+                    }
+                )));
                 let kernel_latex = self.kernel.to_latex(&DefaultRuntime::default());
                 let right_side_latex = self.right_side.to_latex(&DefaultRuntime::default());
 
@@ -52,22 +101,20 @@ impl Problem for Fredholm1stProblem {
                     solution.push(latex);
                 }
 
-                let pts = res.to_table();
-                let write_res = match File::create(&self.dest_file) {
-                    Ok(mut file) => pts
-                        .iter()
-                        .try_for_each(|(x, y)| writeln!(file, "{},{}", x, y)),
-                    Err(e) => Err(e),
-                };
+                let write_res = res.to_file(&self.dest_file);
+                let pts = res.into_table();
 
                 let _ = write_res.map_err(|e| {
                     solution.push(SolutionParagraph::RuntimeError(format!("{:?}", e)))
                 });
 
+                solution.push(shape_summary(&pts));
+                solution.push(kernel_heatmap(&kernel, self.from, self.to));
                 match Graph::new(vec![Path {
                     pts,
                     kind: PathKind::Line,
                     color: (1.0, 0.0, 0.0),
+                    label: None,
                 }]) {
                     Some(g) => solution.push(SolutionParagraph::Graph(g)),
                     None => solution.push(SolutionParagraph::RuntimeError(
@@ -77,10 +124,12 @@ impl Problem for Fredholm1stProblem {
 
                 Solution {
                     explanation: solution,
+                    values: vec![],
                 }
             }
             Err(e) => Solution {
                 explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                values: vec![],
             },
         }
     }
@@ -88,6 +137,7 @@ impl Problem for Fredholm1stProblem {
 
 pub struct Fredholm1stProblemCreator {
     form: Form,
+    output_dir: PathBuf,
 }
 
 impl Default for Fredholm1stProblemCreator {
@@ -101,6 +151,10 @@ impl Default for Fredholm1stProblemCreator {
             "n".to_string(),
             "max_iter_count".to_string(),
             "dest_file".to_string(),
+            "adaptive".to_string(),
+            "max_n".to_string(),
+            "tol".to_string(),
+            "symmetrize".to_string(),
         ]);
 
         form.set("kernel", "abs(x-s)".to_string());
@@ -111,13 +165,20 @@ impl Default for Fredholm1stProblemCreator {
         form.set("n", "50".to_string());
         form.set("max_iter_count", "10000".to_string());
         form.set("dest_file", "y.csv".to_string());
+        form.set("adaptive", "false".to_string());
+        form.set("max_n", "200".to_string());
+        form.set("tol", "1e-4".to_string());
+        form.set("symmetrize", "false".to_string());
 
-        Self { form }
+        Self {
+            form,
+            output_dir: default_output_dir(),
+        }
     }
 }
 
 impl ProblemCreator for Fredholm1stProblemCreator {
-    fn try_create(&self) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
+    fn try_create(&self, runtime: &dyn Runtime) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
         let mut kernel: Option<Box<dyn Expression>> = None;
         let mut right_side: Option<Box<dyn Expression>> = None;
         let mut from: Option<f64> = None;
@@ -125,30 +186,26 @@ impl ProblemCreator for Fredholm1stProblemCreator {
         let mut eps: Option<f64> = None;
         let mut n: Option<usize> = None;
         let mut max_iter_count: Option<usize> = None;
+        let mut adaptive: Option<bool> = None;
+        let mut max_n: Option<usize> = None;
+        let mut tol: Option<f64> = None;
+        let mut symmetrize: Option<bool> = None;
 
         let mut errors = vec![];
         for (name, val) in self.form.get_fields() {
             let res = match name {
-                "kernel" => validate_expr(
-                    name,
-                    val,
-                    Some(&["x", "s"]),
-                    &DefaultRuntime::default(),
-                    &mut kernel,
-                ),
-                "right_side" => validate_expr(
-                    name,
-                    val,
-                    Some(&["x"]),
-                    &DefaultRuntime::default(),
-                    &mut right_side,
-                ),
+                "kernel" => validate_expr(name, val, Some(&["x", "s"]), runtime, &mut kernel),
+                "right_side" => validate_expr(name, val, Some(&["x"]), runtime, &mut right_side),
                 "from" => validate_from_str::<f64>(name, val, &mut from),
                 "to" => validate_from_str::<f64>(name, val, &mut to),
-                "eps" => validate_from_str::<f64>(name, val, &mut eps),
-                "n" => validate_from_str::<usize>(name, val, &mut n),
-                "max_iter_count" => validate_from_str::<usize>(name, val, &mut max_iter_count),
+                "eps" => validate_positive::<f64>(name, val, &mut eps),
+                "n" => validate_positive::<usize>(name, val, &mut n),
+                "max_iter_count" => validate_positive::<usize>(name, val, &mut max_iter_count),
                 "dest_file" => Ok(()),
+                "adaptive" => validate_from_str::<bool>(name, val, &mut adaptive),
+                "max_n" => validate_positive::<usize>(name, val, &mut max_n),
+                "tol" => validate_positive::<f64>(name, val, &mut tol),
+                "symmetrize" => validate_from_str::<bool>(name, val, &mut symmetrize),
                 _ => Err(ValidationError(format!(
                     "{name} - no such field (probably a devs error)"
                 ))),
@@ -160,6 +217,10 @@ impl ProblemCreator for Fredholm1stProblemCreator {
             }
         }
 
+        if matches!(n, Some(n) if n < 2) {
+            errors.push(ValidationError("n - need at least 2 points".to_string()));
+        }
+
         if !errors.is_empty() {
             return Err(errors);
         }
@@ -193,6 +254,21 @@ impl ProblemCreator for Fredholm1stProblemCreator {
                 "field was not supplied: dest_file".to_string(),
             ))
         });
+        let adaptive = adaptive.ok_or_else(|| {
+            errors.push(ValidationError(
+                "field was not supplied: adaptive".to_string(),
+            ))
+        });
+        let max_n = max_n.ok_or_else(|| {
+            errors.push(ValidationError("field was not supplied: max_n".to_string()))
+        });
+        let tol = tol
+            .ok_or_else(|| errors.push(ValidationError("field was not supplied: tol".to_string())));
+        let symmetrize = symmetrize.ok_or_else(|| {
+            errors.push(ValidationError(
+                "field was not supplied: symmetrize".to_string(),
+            ))
+        });
 
         if errors.is_empty() {
             Ok(Box::new(Fredholm1stProblem {
@@ -203,7 +279,11 @@ impl ProblemCreator for Fredholm1stProblemCreator {
                 eps: eps.unwrap(),
                 n: n.unwrap(),
                 max_iter_count: max_iter_count.unwrap(),
-                dest_file: dest_file.cloned().unwrap(),
+                dest_file: resolve_output_path(&self.output_dir, dest_file.unwrap()),
+                adaptive: adaptive.unwrap(),
+                max_n: max_n.unwrap(),
+                tol: tol.unwrap(),
+                symmetrize: symmetrize.unwrap(),
             }))
         } else {
             Err(errors)
@@ -217,4 +297,47 @@ impl ProblemCreator for Fredholm1stProblemCreator {
     fn set_field(&mut self, name: &str, val: String) {
         self.form.set(name, val)
     }
+
+    fn reset(&mut self) {
+        self.form = Self::default().form;
+    }
+
+    fn set_output_dir(&mut self, dir: &FilePath) {
+        self.output_dir = dir.to_path_buf();
+    }
+}
+
+#[test]
+fn rejects_non_positive() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = Fredholm1stProblemCreator::default();
+    assert!(creator.try_create(&runtime).is_ok());
+
+    creator.set_field("n", "0".to_string());
+    creator.set_field("eps", "-1".to_string());
+    let errors = creator
+        .try_create(&runtime)
+        .err()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.0)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![
+            "eps - must be positive, got -1".to_string(),
+            "n - must be positive, got 0".to_string()
+        ]
+    );
+
+    creator.set_field("n", "1".to_string());
+    creator.set_field("eps", "1e-8".to_string());
+    let errors = creator
+        .try_create(&runtime)
+        .err()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.0)
+        .collect::<Vec<_>>();
+    assert_eq!(errors, vec!["n - need at least 2 points".to_string()]);
 }
@@ -2,20 +2,23 @@ use std::collections::HashMap;
 
 use crate::{
     functions::function::FunctionNd,
-    mathparse::{parse, DefaultRuntime, Error, Expression},
-    min_find::gradients_min::gradients_min,
+    iteration_limit::IterationLimit,
+    mathparse::{parse_opt, DefaultRuntime, Error, Expression, Runtime},
+    min_find::gradients_min::{gradients_min, gradients_min_numeric, Error as GradientsMinError},
 };
 
 use super::{
+    csv_download,
     form::Form,
     graph::{Graph, Path},
-    validate_expr, validate_from_str, Problem, ProblemCreator, Solution, SolutionParagraph,
-    ValidationError,
+    validate_expr, validate_from_str, validate_positive, Problem, ProblemCreator, Solution,
+    SolutionParagraph, ValidationError,
 };
 
 struct GradientsMinProblem {
     ordered_vars: Vec<String>,
     f: Box<dyn Expression>,
+    /// Empty when the gradient is estimated numerically.
     grad: Vec<Box<dyn Expression>>,
     x0: Vec<f64>,
     eps: f64,
@@ -52,16 +55,25 @@ impl Problem for GradientsMinProblem {
             })
             .collect::<Vec<_>>();
 
-        let res = gradients_min(
-            &f,
-            &grad
-                .iter()
-                .map(|f| f as &dyn FunctionNd<Error = Error>)
-                .collect::<Vec<_>>(),
-            &self.x0,
-            self.eps,
-            self.max_iter_count,
-        );
+        let res = if self.grad.is_empty() {
+            gradients_min_numeric(
+                &f,
+                &self.x0,
+                self.eps,
+                IterationLimit::new(self.max_iter_count),
+            )
+        } else {
+            gradients_min(
+                &f,
+                &grad
+                    .iter()
+                    .map(|f| f as &dyn FunctionNd<Error = Error>)
+                    .collect::<Vec<_>>(),
+                &self.x0,
+                self.eps,
+                IterationLimit::new(self.max_iter_count),
+            )
+        };
 
         match res {
             Ok(res) => {
@@ -85,37 +97,75 @@ impl Problem for GradientsMinProblem {
 
                 if self.x0.len() == 1 {
                     let x = res.x[0];
-                    let pts = f.sample(&[x - 2.0], &[x + 2.0], &[20]);
+                    let pts = f
+                        .sample(&[x - 2.0], &[x + 2.0], &[20])
+                        .map(|pts| pts.iter().map(|p| (p[0], p[1])).collect::<Vec<_>>());
                     match pts {
-                        Ok(pts) => match Graph::new(vec![
-                            Path {
-                                pts: pts.iter().map(|p| (p[0], p[1])).collect(),
-                                kind: super::graph::PathKind::Line,
-                                color: (1.0, 0.0, 0.0),
-                            },
-                            Path {
-                                pts: vec![(res.x[0], res.y)],
-                                kind: super::graph::PathKind::Dot,
-                                color: (0.0, 0.0, 1.0),
-                            },
-                        ]) {
-                            Some(g) => paragraphs.push(SolutionParagraph::Graph(g)),
-                            None => paragraphs.push(SolutionParagraph::RuntimeError(
-                                "Could not create graph".to_string(),
-                            )),
-                        },
+                        Ok(pts) => {
+                            paragraphs.push(csv_download("f.csv", &pts));
+                            match Graph::new(vec![
+                                Path {
+                                    pts,
+                                    kind: super::graph::PathKind::Line,
+                                    color: (1.0, 0.0, 0.0),
+                                    label: None,
+                                },
+                                Path {
+                                    pts: vec![(res.x[0], res.y)],
+                                    kind: super::graph::PathKind::Dot,
+                                    color: (0.0, 0.0, 1.0),
+                                    label: None,
+                                },
+                            ]) {
+                                Some(g) => paragraphs.push(SolutionParagraph::Graph(g)),
+                                None => paragraphs.push(SolutionParagraph::RuntimeError(
+                                    "Could not create graph".to_string(),
+                                )),
+                            }
+                        }
                         Err(e) => {
                             paragraphs.push(SolutionParagraph::RuntimeError(format!("{:?}", e)))
                         }
                     }
                 }
 
+                paragraphs.push(SolutionParagraph::Downloadable {
+                    filename: "min.csv".to_string(),
+                    contents: format!(
+                        "{},f\n{},{}\n",
+                        self.ordered_vars.join(","),
+                        res.x
+                            .iter()
+                            .map(|x| x.to_string())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                        res.y
+                    ),
+                });
+
+                let mut values = self
+                    .ordered_vars
+                    .iter()
+                    .zip(res.x.iter())
+                    .map(|(var, x)| (format!("min_{var}"), *x))
+                    .collect::<Vec<_>>();
+                values.push(("min_f".to_string(), res.y));
+
                 Solution {
                     explanation: paragraphs,
+                    values,
                 }
             }
+            Err(GradientsMinError::LineSearchStalled(m, iterations)) => Solution {
+                explanation: vec![SolutionParagraph::RuntimeError(format!(
+                    "The line search stalled after {iterations} iterations at {:?}, try a larger eps",
+                    m.x
+                ))],
+                values: vec![],
+            },
             Err(e) => Solution {
                 explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                values: vec![],
             },
         }
     }
@@ -132,6 +182,7 @@ impl Default for GradientsMinProblemCreator {
             "f".to_string(),
             "eps".to_string(),
             "max_iter_count".to_string(),
+            "numeric_gradient".to_string(),
             "df/dx".to_string(),
             "df/dy".to_string(),
             "x0".to_string(),
@@ -141,6 +192,7 @@ impl Default for GradientsMinProblemCreator {
         form.set("f", "10pow(y-x*x,2)+pow(1-x,2)".to_string());
         form.set("eps", "0.00001".to_string());
         form.set("max_iter_count", "10000".to_string());
+        form.set("numeric_gradient", "false".to_string());
         form.set("df/dx", "-40x*y+40pow(x,3)+2x-2".to_string());
         form.set("df/dy", "20y-20*x*x".to_string());
         form.set("x0", "3".to_string());
@@ -160,7 +212,7 @@ impl ProblemCreator for GradientsMinProblemCreator {
 
     fn set_field(&mut self, name: &str, val: String) {
         if name == "f" {
-            if let Some(expr) = parse(&val, &DefaultRuntime::default()) {
+            if let Some(expr) = parse_opt(&val, &DefaultRuntime::default()) {
                 let new_vars =
                     Vec::from_iter(expr.query_vars().iter().map(|name| name.to_string()));
 
@@ -168,6 +220,7 @@ impl ProblemCreator for GradientsMinProblemCreator {
                     "f".to_string(),
                     "eps".to_string(),
                     "max_iter_count".to_string(),
+                    "numeric_gradient".to_string(),
                 ]);
 
                 if let Some(val) = self.form.get("f") {
@@ -179,6 +232,9 @@ impl ProblemCreator for GradientsMinProblemCreator {
                 if let Some(val) = self.form.get("max_iter_count") {
                     new_form.set("max_iter_count", val.clone())
                 }
+                if let Some(val) = self.form.get("numeric_gradient") {
+                    new_form.set("numeric_gradient", val.clone())
+                }
 
                 for name in &new_vars {
                     new_form.add_field(format!("{name}0"));
@@ -195,57 +251,77 @@ impl ProblemCreator for GradientsMinProblemCreator {
         self.form.set(name, val);
     }
 
-    fn try_create(&self) -> Result<Box<dyn Problem>, Vec<super::ValidationError>> {
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn try_create(
+        &self,
+        runtime: &dyn Runtime,
+    ) -> Result<Box<dyn Problem>, Vec<super::ValidationError>> {
         let mut f = None;
         let mut eps = None;
         let mut max_iter_count = None;
+        let mut numeric_gradient = None;
         let mut x0 = HashMap::new();
         let mut grad = HashMap::new();
 
-        let mut errors = vec![];
-        let allowed_vars = self
+        // The derivative fields are ignored when the gradient is estimated numerically
+        let skip_derivatives = matches!(
+            self.form.get("numeric_gradient").map(|v| v.trim().parse()),
+            Some(Ok(true))
+        );
+
+        // stored values are constants of f, not coordinates to search over
+        let ordered_vars = self
             .ordered_vars
+            .iter()
+            .filter(|name| runtime.get_var(name).is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut errors = vec![];
+        let allowed_vars = ordered_vars
             .iter()
             .map(|name| name.as_str())
             .collect::<Vec<_>>();
 
         for (name, val) in self.fields() {
-            let res =
-                match name {
-                    "f" => validate_expr(
-                        name,
-                        val,
-                        Some(&allowed_vars),
-                        &DefaultRuntime::default(),
-                        &mut f,
-                    ),
-                    "eps" => validate_from_str::<f64>(name, val, &mut eps),
-                    "max_iter_count" => validate_from_str::<usize>(name, val, &mut max_iter_count),
-                    _ => {
-                        if let Some(var_name) = name.strip_suffix('0') {
-                            let mut var_value = None;
-                            validate_from_str::<f64>(name, val, &mut var_value).and_then(|_| {
-                                match self.ordered_vars.iter().find(|name| name.eq(&var_name)) {
-                                    Some(_) => {
-                                        x0.insert(var_name.to_string(), var_value.unwrap());
-                                        Ok(())
-                                    }
-                                    None => Err(ValidationError(format!(
-                                        "{name} - no such field (probably a devs error) "
-                                    ))),
+            let res = match name {
+                "f" => validate_expr(name, val, Some(&allowed_vars), runtime, &mut f),
+                "eps" => validate_positive::<f64>(name, val, &mut eps),
+                "max_iter_count" => validate_positive::<usize>(name, val, &mut max_iter_count),
+                "numeric_gradient" => validate_from_str::<bool>(name, val, &mut numeric_gradient),
+                _ if name
+                    .strip_suffix('0')
+                    .or_else(|| name.strip_prefix("df/d"))
+                    .and_then(|var| runtime.get_var(var))
+                    .is_some() =>
+                {
+                    continue
+                }
+                _ => {
+                    if let Some(var_name) = name.strip_suffix('0') {
+                        let mut var_value = None;
+                        validate_from_str::<f64>(name, val, &mut var_value).and_then(|_| {
+                            match ordered_vars.iter().find(|name| name.eq(&var_name)) {
+                                Some(_) => {
+                                    x0.insert(var_name.to_string(), var_value.unwrap());
+                                    Ok(())
                                 }
-                            })
-                        } else if let Some(var_name) = name.strip_prefix("df/d") {
-                            let mut var_value = None;
-                            validate_expr(
-                                name,
-                                val,
-                                Some(&allowed_vars),
-                                &DefaultRuntime::default(),
-                                &mut var_value,
-                            )
+                                None => Err(ValidationError(format!(
+                                    "{name} - no such field (probably a devs error) "
+                                ))),
+                            }
+                        })
+                    } else if let Some(var_name) = name.strip_prefix("df/d") {
+                        if skip_derivatives || val.trim().is_empty() {
+                            continue;
+                        }
+                        let mut var_value = None;
+                        validate_expr(name, val, Some(&allowed_vars), runtime, &mut var_value)
                             .and_then(|_| {
-                                match self.ordered_vars.iter().find(|name| name.eq(&var_name)) {
+                                match ordered_vars.iter().find(|name| name.eq(&var_name)) {
                                     Some(_) => {
                                         grad.insert(var_name.to_string(), var_value.unwrap());
                                         Ok(())
@@ -255,13 +331,13 @@ impl ProblemCreator for GradientsMinProblemCreator {
                                     ))),
                                 }
                             })
-                        } else {
-                            Err(ValidationError(format!(
-                                "{name} - no such field (probably a devs error)"
-                            )))
-                        }
+                    } else {
+                        Err(ValidationError(format!(
+                            "{name} - no such field (probably a devs error)"
+                        )))
                     }
-                };
+                }
+            };
 
             match res {
                 Ok(_) => {}
@@ -273,6 +349,24 @@ impl ProblemCreator for GradientsMinProblemCreator {
             return Err(errors);
         }
 
+        // Empty gradient fields are derived from f
+        if let (Some(f), false) = (&f, skip_derivatives) {
+            for var in &ordered_vars {
+                if grad.contains_key(var) {
+                    continue;
+                }
+                match f.derivative(var) {
+                    Ok(df) => {
+                        grad.insert(var.clone(), df);
+                    }
+                    Err(e) => errors.push(ValidationError(format!(
+                        "df/d{var} - could not differentiate f: {:?}",
+                        e
+                    ))),
+                }
+            }
+        }
+
         let f =
             f.ok_or_else(|| errors.push(ValidationError("field f was not supplied".to_string())));
         let eps = eps
@@ -283,10 +377,11 @@ impl ProblemCreator for GradientsMinProblemCreator {
             ))
         });
 
-        if !grad
-            .keys()
-            .all(|name| allowed_vars.iter().any(|allowed_name| allowed_name == name))
-            || grad.len() != allowed_vars.len()
+        if !skip_derivatives
+            && (!grad
+                .keys()
+                .all(|name| allowed_vars.iter().any(|allowed_name| allowed_name == name))
+                || grad.len() != allowed_vars.len())
         {
             errors.push(ValidationError(
                 "Not all derivatives were supplied".to_string(),
@@ -307,17 +402,72 @@ impl ProblemCreator for GradientsMinProblemCreator {
             Err(errors)
         } else {
             Ok(Box::new(GradientsMinProblem {
-                ordered_vars: self.ordered_vars.clone(),
+                ordered_vars: ordered_vars.clone(),
                 f: f.unwrap(),
-                grad: self
-                    .ordered_vars
-                    .iter()
-                    .map(|var_name| grad.remove(var_name).unwrap())
-                    .collect(),
-                x0: x0.values().cloned().collect(),
+                grad: if skip_derivatives {
+                    vec![]
+                } else {
+                    ordered_vars
+                        .iter()
+                        .map(|var_name| grad.remove(var_name).unwrap())
+                        .collect()
+                },
+                x0: ordered_vars.iter().map(|var_name| x0[var_name]).collect(),
                 eps: eps.unwrap(),
                 max_iter_count: max_iter_count.unwrap(),
             }))
         }
     }
 }
+
+#[test]
+fn derived_gradient() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = GradientsMinProblemCreator::default();
+    creator.set_field("df/dx", String::new());
+    creator.set_field("df/dy", String::new());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    let (x, y) = (solution.values[0].1, solution.values[1].1);
+    assert!(
+        (x - 1.0).abs() < 0.01 && (y - 1.0).abs() < 0.01,
+        "({x}, {y})"
+    );
+}
+
+#[test]
+fn numeric_gradient() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = GradientsMinProblemCreator::default();
+    creator.set_field("numeric_gradient", "true".to_string());
+    creator.set_field("df/dx", "not an expression (".to_string());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    let (x, y) = (solution.values[0].1, solution.values[1].1);
+    assert!(
+        (x - 1.0).abs() < 0.01 && (y - 1.0).abs() < 0.01,
+        "({x}, {y})"
+    );
+    assert_eq!(solution.values[2].0, "min_f");
+}
+
+#[test]
+fn stored_value_is_not_a_variable() {
+    let runtime = DefaultRuntime::new(&[("a", 2.5)]);
+    let mut creator = GradientsMinProblemCreator::default();
+    creator.set_field("f", "pow(x-a,2)+2pow(y,2)".to_string());
+    creator.set_field("x0", "0".to_string());
+    creator.set_field("y0", "1".to_string());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    let value = |name: &str| {
+        solution
+            .values
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| *v)
+            .unwrap()
+    };
+    assert!(solution.values.iter().all(|(n, _)| n != "min_a"));
+    assert!((value("min_x") - 2.5).abs() < 0.01 && value("min_y").abs() < 0.01);
+}
@@ -1,3 +1,5 @@
+use crate::functions::function::Function;
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum PathKind {
     Line,
@@ -10,6 +12,62 @@ pub struct Path {
     pub pts: Vec<(f64, f64)>,
     pub kind: PathKind,
     pub color: (f32, f32, f32),
+    /// Shown in the legend, unlabeled paths are left out of it.
+    pub label: Option<String>,
+}
+
+/// Vertical tolerance of `plot_points`, relative to the height of the graph.
+const PLOT_TOL: f64 = 0.002;
+const PLOT_MAX_POINTS: usize = 400;
+
+/// Points to draw `f` on `[from, to]` with, denser where it bends.
+pub fn plot_points<E>(
+    f: &dyn Function<Error = E>,
+    from: f64,
+    to: f64,
+) -> Result<Vec<(f64, f64)>, E> {
+    let (bottom, top) = f
+        .sample(from, to, 20)?
+        .iter()
+        .filter(|(_, y)| y.is_finite())
+        .fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |(bottom, top), (_, y)| (bottom.min(*y), top.max(*y)),
+        );
+    let height = if top > bottom { top - bottom } else { 1.0 };
+    f.sample_adaptive(from, to, height * PLOT_TOL, PLOT_MAX_POINTS)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AxisScale {
+    Linear,
+    Log10,
+}
+
+impl AxisScale {
+    /// Non-positive values are clamped to the smallest positive float under `Log10`,
+    /// which puts them far outside of any sensible viewport.
+    fn apply(self, v: f64) -> f64 {
+        match self {
+            AxisScale::Linear => v,
+            AxisScale::Log10 => v.max(f64::MIN_POSITIVE).log10(),
+        }
+    }
+
+    fn invert(self, v: f64) -> f64 {
+        match self {
+            AxisScale::Linear => v,
+            AxisScale::Log10 => 10f64.powf(v),
+        }
+    }
+
+    /// Where to put gridlines on `[from, to]`: integers for `Linear`, powers of 10 for `Log10`.
+    pub fn grid(self, from: f64, to: f64) -> Vec<f64> {
+        let (from, to) = (self.apply(from.min(to)), self.apply(from.max(to)));
+        (from.floor() as i32..=to.ceil() as i32)
+            .map(|i| self.invert(i as f64))
+            .collect()
+    }
 }
 
 #[derive(Debug)]
@@ -18,6 +76,8 @@ pub struct Viewport {
     pub right: f64,
     pub bottom: f64,
     pub top: f64,
+    pub x_scale: AxisScale,
+    pub y_scale: AxisScale,
 }
 
 impl Viewport {
@@ -27,18 +87,57 @@ impl Viewport {
             right,
             bottom,
             top,
+            x_scale: AxisScale::Linear,
+            y_scale: AxisScale::Linear,
+        }
+    }
+
+    pub fn with_scales(self, x_scale: AxisScale, y_scale: AxisScale) -> Self {
+        Self {
+            x_scale,
+            y_scale,
+            ..self
         }
     }
 
     pub fn convert(from: &Viewport, to: &Viewport, pt: (f64, f64)) -> (f64, f64) {
+        let axis = |v: f64,
+                    [from_lo, from_hi, to_lo, to_hi]: [f64; 4],
+                    [from_scale, to_scale]: [AxisScale; 2]| {
+            let (from_lo, from_hi) = (from_scale.apply(from_lo), from_scale.apply(from_hi));
+            let (to_lo, to_hi) = (to_scale.apply(to_lo), to_scale.apply(to_hi));
+            to_scale.invert(
+                (from_scale.apply(v) - from_lo) / (from_hi - from_lo) * (to_hi - to_lo) + to_lo,
+            )
+        };
+
         let (x, y) = pt;
         (
-            (x - from.left) / (from.right - from.left) * (to.right - to.left) + to.left,
-            (y - from.bottom) / (from.top - from.bottom) * (to.top - to.bottom) + to.bottom,
+            axis(
+                x,
+                [from.left, from.right, to.left, to.right],
+                [from.x_scale, to.x_scale],
+            ),
+            axis(
+                y,
+                [from.bottom, from.top, to.bottom, to.top],
+                [from.y_scale, to.y_scale],
+            ),
         )
     }
+
+    pub fn x_grid(&self) -> Vec<f64> {
+        self.x_scale.grid(self.left, self.right)
+    }
+
+    pub fn y_grid(&self) -> Vec<f64> {
+        self.y_scale.grid(self.bottom, self.top)
+    }
 }
 
+/// Margin around the points of a `Graph`, relative to their span.
+const GRAPH_PADDING: f64 = 0.05;
+
 #[derive(Debug)]
 pub struct Graph {
     pub paths: Vec<Path>,
@@ -46,38 +145,262 @@ pub struct Graph {
 }
 
 impl Graph {
+    /// Fits the viewport around the finite points of `paths` with a 5% margin,
+    /// `None` if there are none.
     pub fn new(paths: Vec<Path>) -> Option<Self> {
-        let left = paths
+        let (left, right, bottom, top) = paths
             .iter()
-            .filter_map(|p| p.pts.iter().map(|(x, _)| *x).reduce(f64::min))
-            .reduce(f64::min)?;
+            .flat_map(|p| p.pts.iter())
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .fold(None, |bounds, (x, y)| match bounds {
+                Some((left, right, bottom, top)) => Some((
+                    f64::min(left, *x),
+                    f64::max(right, *x),
+                    f64::min(bottom, *y),
+                    f64::max(top, *y),
+                )),
+                None => Some((*x, *x, *y, *y)),
+            })?;
 
-        let right = paths
-            .iter()
-            .filter_map(|p| p.pts.iter().map(|(x, _)| *x).reduce(f64::max))
-            .reduce(f64::max)?;
+        // a single point or a flat line has nothing to take 5% of
+        let pad = |from: f64, to: f64| {
+            if to > from {
+                (to - from) * GRAPH_PADDING
+            } else {
+                1.0
+            }
+        };
+        let (dx, dy) = (pad(left, right), pad(bottom, top));
 
-        let bottom = paths
-            .iter()
-            .filter_map(|p| p.pts.iter().map(|(_, x)| *x).reduce(f64::min))
-            .reduce(f64::min)?;
-
-        let top = paths
-            .iter()
-            .filter_map(|p| p.pts.iter().map(|(_, x)| *x).reduce(f64::max))
-            .reduce(f64::max)?;
-
-        // if paths
-        //     .iter()
-        //     .filter(|p| p.kind == PathKind::Dot)
-        //     .all(|p| p.pts.len() == 1)
-        // {
         Some(Self {
             paths,
-            viewport: Viewport::new(left - 1.0, right + 1.0, bottom - 1.0, top + 1.0),
+            viewport: Viewport::new(left - dx, right + dx, bottom - dy, top + dy),
         })
-        // } else {
-        //     None
-        // }
     }
+
+    /// Switches the axes to the given scales. The bounds of a `Log10` axis are
+    /// recomputed from the positive coordinates only, padded by a factor of 2.
+    pub fn with_scales(self, x_scale: AxisScale, y_scale: AxisScale) -> Self {
+        let positive_bounds = |coord: fn(&(f64, f64)) -> f64| {
+            self.paths
+                .iter()
+                .flat_map(|p| p.pts.iter().map(coord))
+                .filter(|v| *v > 0.0)
+                .fold(None, |bounds: Option<(f64, f64)>, v| match bounds {
+                    Some((lo, hi)) => Some((lo.min(v), hi.max(v))),
+                    None => Some((v, v)),
+                })
+                .map(|(lo, hi)| (lo / 2.0, hi * 2.0))
+                .unwrap_or((0.1, 10.0))
+        };
+
+        let Viewport {
+            mut left,
+            mut right,
+            mut bottom,
+            mut top,
+            ..
+        } = self.viewport;
+        if x_scale == AxisScale::Log10 {
+            (left, right) = positive_bounds(|(x, _)| *x);
+        }
+        if y_scale == AxisScale::Log10 {
+            (bottom, top) = positive_bounds(|(_, y)| *y);
+        }
+
+        Self {
+            viewport: Viewport::new(left, right, bottom, top).with_scales(x_scale, y_scale),
+            paths: self.paths,
+        }
+    }
+
+    /// Labels of the paths with their colors, in drawing order.
+    pub fn legend(&self) -> Vec<(&str, (f32, f32, f32))> {
+        self.paths
+            .iter()
+            .filter_map(|p| p.label.as_deref().map(|label| (label, p.color)))
+            .collect()
+    }
+
+    /// Draws the graph as a standalone SVG document, with the axes that fall into the viewport.
+    pub fn to_svg(&self, width: u32, height: u32) -> String {
+        let screen = Viewport::new(0.0, width as f64, height as f64, 0.0);
+        let convert = |pt| Viewport::convert(&self.viewport, &screen, pt);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n"
+        );
+        svg += &format!("<rect width=\"{width}\" height=\"{height}\" fill=\"white\"/>\n");
+
+        if (self.viewport.left..=self.viewport.right).contains(&0.0) {
+            let (x, _) = convert((0.0, 0.0));
+            svg += &format!(
+                "<line x1=\"{x:.2}\" y1=\"0\" x2=\"{x:.2}\" y2=\"{height}\" stroke=\"black\"/>\n"
+            );
+        }
+        if (self.viewport.bottom..=self.viewport.top).contains(&0.0) {
+            let (_, y) = convert((0.0, 0.0));
+            svg += &format!(
+                "<line x1=\"0\" y1=\"{y:.2}\" x2=\"{width}\" y2=\"{y:.2}\" stroke=\"black\"/>\n"
+            );
+        }
+
+        for p in &self.paths {
+            let color = svg_color(p.color);
+            let pts = p
+                .pts
+                .iter()
+                .filter(|(x, y)| x.is_finite() && y.is_finite())
+                .map(|pt| convert(*pt));
+
+            match p.kind {
+                PathKind::Line | PathKind::Filled => {
+                    let pts = pts
+                        .map(|(x, y)| format!("{x:.2},{y:.2}"))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let style = if p.kind == PathKind::Line {
+                        format!("fill=\"none\" stroke=\"{color}\" stroke-width=\"2\"")
+                    } else {
+                        format!("fill=\"{color}\"")
+                    };
+                    svg += &format!("<polyline points=\"{pts}\" {style}/>\n");
+                }
+                PathKind::Dot => {
+                    for (x, y) in pts {
+                        svg += &format!(
+                            "<circle cx=\"{x:.2}\" cy=\"{y:.2}\" r=\"3\" fill=\"{color}\"/>\n"
+                        );
+                    }
+                }
+            }
+        }
+
+        let legend = self.legend();
+        if !legend.is_empty() {
+            let rows = legend.len() as u32;
+            let box_width = 30
+                + 8 * legend
+                    .iter()
+                    .map(|(l, _)| l.chars().count())
+                    .max()
+                    .unwrap_or(0) as u32;
+            svg += &format!(
+                "<rect x=\"5\" y=\"5\" width=\"{box_width}\" height=\"{}\" fill=\"white\" stroke=\"black\"/>\n",
+                LEGEND_ROW * rows + 5
+            );
+            for (i, (label, color)) in legend.into_iter().enumerate() {
+                let y = 10 + LEGEND_ROW * i as u32;
+                svg += &format!(
+                    "<rect x=\"10\" y=\"{y}\" width=\"12\" height=\"12\" fill=\"{}\"/>\n",
+                    svg_color(color)
+                );
+                svg += &format!(
+                    "<text x=\"28\" y=\"{}\" font-size=\"12\" font-family=\"sans-serif\">{}</text>\n",
+                    y + 11,
+                    escape_xml(label)
+                );
+            }
+        }
+
+        svg + "</svg>\n"
+    }
+}
+
+/// Height of a row in the legend drawn by `Graph::to_svg`.
+const LEGEND_ROW: u32 = 18;
+
+fn svg_color((r, g, b): (f32, f32, f32)) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round();
+    format!("rgb({},{},{})", channel(r), channel(g), channel(b))
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[test]
+fn log_scale() {
+    let from =
+        Viewport::new(1.0, 1000.0, 0.1, 1000.0).with_scales(AxisScale::Log10, AxisScale::Log10);
+    let to = Viewport::new(0.0, 300.0, 400.0, 0.0);
+
+    let xs = [1.0, 10.0, 100.0, 1000.0].map(|x| Viewport::convert(&from, &to, (x, 1.0)).0);
+    for (x, expected) in xs.iter().zip([0.0, 100.0, 200.0, 300.0]) {
+        assert!((x - expected).abs() < 1e-9, "{xs:?}");
+    }
+    let ys = [1.0, 10.0, 100.0].map(|y| Viewport::convert(&from, &to, (1.0, y)).1);
+    assert!((ys[0] - ys[1] - (ys[1] - ys[2])).abs() < 1e-9, "{ys:?}");
+    // non-positive values end up far below the viewport
+    assert!(Viewport::convert(&from, &to, (1.0, 0.0)).1 > 1e4);
+
+    assert_eq!(from.x_grid(), vec![1.0, 10.0, 100.0, 1000.0]);
+    assert_eq!(
+        Viewport::new(-1.5, 1.0, 0.0, 1.0).x_grid(),
+        vec![-2.0, -1.0, 0.0, 1.0]
+    );
+}
+
+#[test]
+fn svg_paths() {
+    let graph = Graph::new(vec![
+        Path {
+            pts: vec![(0.0, 0.0), (1.0, 1.0), (2.0, 4.0)],
+            kind: PathKind::Line,
+            color: (1.0, 0.0, 0.0),
+            label: Some("f1".to_string()),
+        },
+        Path {
+            pts: vec![(0.0, 1.0), (2.0, 1.0), (2.0, 0.0)],
+            kind: PathKind::Filled,
+            color: (0.0, 0.0, 1.0),
+            label: Some("area < 1".to_string()),
+        },
+        Path {
+            pts: vec![(1.0, 1.0)],
+            kind: PathKind::Dot,
+            color: (0.0, 0.0, 0.0),
+            label: None,
+        },
+    ])
+    .unwrap();
+
+    let svg = graph.to_svg(400, 300);
+    assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+    assert_eq!(svg.matches("<polyline").count(), 2);
+    assert_eq!(svg.matches("<circle").count(), 1);
+    assert_eq!(svg.matches("<line").count(), 2);
+    assert!(svg.contains("stroke=\"rgb(255,0,0)\""));
+    assert!(svg.contains("fill=\"rgb(0,0,255)\""));
+    assert!(svg.contains(">f1</text>") && svg.contains(">area &lt; 1</text>"));
+    assert_eq!(svg.matches("<text").count(), 2);
+    // (1, 1) is in the middle horizontally, viewport is [-0.1, 2.1] x [-0.2, 4.2]
+    assert!(svg.contains("cx=\"200.00\" cy=\"218.18\""));
+}
+
+#[test]
+fn graph_bounds() {
+    let path = |pts| Path {
+        pts,
+        kind: PathKind::Line,
+        color: (0.0, 0.0, 0.0),
+        label: None,
+    };
+
+    assert!(Graph::new(vec![]).is_none());
+    assert!(Graph::new(vec![path(vec![(f64::NAN, 1.0), (0.0, f64::NAN)])]).is_none());
+
+    let g = Graph::new(vec![path(vec![(2.0, 3.0)])]).unwrap();
+    let v = g.viewport;
+    assert_eq!((v.left, v.right, v.bottom, v.top), (1.0, 3.0, 2.0, 4.0));
+
+    let g = Graph::new(vec![
+        path(vec![(0.0, 0.0), (1.0, f64::INFINITY), (2.0, 10.0)]),
+        path(vec![(f64::NAN, 5.0), (-f64::INFINITY, 0.0)]),
+    ])
+    .unwrap();
+    let v = g.viewport;
+    assert_eq!((v.left, v.right, v.bottom, v.top), (-0.1, 2.1, -0.5, 10.5));
 }
@@ -1,13 +1,32 @@
-use std::{fmt::Debug, str::FromStr};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use crate::mathparse::{parse, Expression, Runtime};
+use crate::{
+    functions::{
+        function::{Function, Function2d, NoError},
+        table_function::TableFunction,
+    },
+    mathparse::{
+        parse_with_limits, reserved_names_used, unknown_calls, BoundExpression, Expression,
+        ParseError, ParseLimits, Runtime,
+    },
+};
 
 use self::{form::FieldsIter, graph::Graph};
 
 pub mod area_calc;
 pub mod fredholm_1st;
 pub mod gradients_min;
+pub mod nelder_mead;
+pub mod ode;
+pub mod parametric;
 pub mod penalty_min;
+pub mod polyfit;
+pub mod root_find;
 pub mod spline;
 pub mod volterra_2nd;
 
@@ -20,11 +39,66 @@ pub enum SolutionParagraph {
     Graph(Graph),
     RuntimeError(String),
     Latex(String),
+    /// A file the user can save, `filename` is relative to the output directory.
+    Downloadable {
+        filename: String,
+        contents: String,
+    },
+    /// `z[j][i]` is the value at `(x[i], y[j])`, sampled on `[from, to]^2`.
+    Heatmap {
+        x: Vec<f64>,
+        y: Vec<f64>,
+        z: Vec<Vec<f64>>,
+        from: f64,
+        to: f64,
+    },
 }
 
 #[derive(Debug)]
 pub struct Solution {
     pub explanation: Vec<SolutionParagraph>,
+    /// Named results that can be stored and used in later expressions.
+    pub values: Vec<(String, f64)>,
+}
+
+impl Solution {
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        self.explanation.iter().filter_map(|p| match p {
+            SolutionParagraph::Text(t) => Some(t.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn graphs(&self) -> impl Iterator<Item = &Graph> {
+        self.explanation.iter().filter_map(|p| match p {
+            SolutionParagraph::Graph(g) => Some(g),
+            _ => None,
+        })
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &str> {
+        self.explanation.iter().filter_map(|p| match p {
+            SolutionParagraph::RuntimeError(e) => Some(e.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn latex(&self) -> impl Iterator<Item = &str> {
+        self.explanation.iter().filter_map(|p| match p {
+            SolutionParagraph::Latex(s) => Some(s.as_str()),
+            _ => None,
+        })
+    }
+
+    /// `(filename, contents)` of every `Downloadable` paragraph.
+    pub fn downloads(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.explanation.iter().filter_map(|p| match p {
+            SolutionParagraph::Downloadable { filename, contents } => {
+                Some((filename.as_str(), contents.as_str()))
+            }
+            _ => None,
+        })
+    }
 }
 
 pub mod form;
@@ -36,7 +110,100 @@ pub trait Problem {
 pub trait ProblemCreator {
     fn fields(&self) -> FieldsIter;
     fn set_field(&mut self, name: &str, val: String);
-    fn try_create(&self) -> Result<Box<dyn Problem>, Vec<ValidationError>>;
+    /// Variables defined in `runtime` may be used in expressions as constants.
+    fn try_create(&self, runtime: &dyn Runtime) -> Result<Box<dyn Problem>, Vec<ValidationError>>;
+    fn set_output_dir(&mut self, _dir: &Path) {}
+    /// Puts every field back to its default value.
+    fn reset(&mut self);
+}
+
+/// `~/Documents` if it exists, otherwise the current directory.
+pub fn default_output_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join("Documents"))
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Relative paths are put under `dir`, absolute ones are kept as is.
+pub fn resolve_output_path(dir: &Path, file: &str) -> PathBuf {
+    let file = Path::new(file);
+    if file.is_absolute() {
+        file.to_path_buf()
+    } else {
+        dir.join(file)
+    }
+}
+
+/// Number of sign changes in `vals`, exact zeros are skipped.
+fn sign_changes(vals: impl Iterator<Item = f64>) -> usize {
+    vals.filter(|v| *v != 0.0)
+        .map(|v| v > 0.0)
+        .fold((0, None), |(count, prev), cur| match prev {
+            Some(prev) if prev != cur => (count + 1, Some(cur)),
+            _ => (count, Some(cur)),
+        })
+        .0
+}
+
+/// Counts the zero crossings and local extrema of a sampled function.
+pub fn shape_summary(pts: &[(f64, f64)]) -> SolutionParagraph {
+    let crossings = sign_changes(pts.iter().map(|(_, y)| *y));
+    let extrema = sign_changes(pts.windows(2).map(|w| w[1].1 - w[0].1));
+
+    SolutionParagraph::Text(format!(
+        "{crossings} zero crossings, {extrema} local extrema"
+    ))
+}
+
+/// A `Downloadable` with `pts` as `x,y` rows, the format `TableFunction::from_file` reads.
+pub fn csv_download(filename: &str, pts: &[(f64, f64)]) -> SolutionParagraph {
+    SolutionParagraph::Downloadable {
+        filename: filename.to_string(),
+        contents: pts.iter().map(|(x, y)| format!("{x},{y}\n")).collect(),
+    }
+}
+
+/// `n` points read off `table` (interpolated) at the Chebyshev nodes of its x range.
+/// With `n == 0` the table is returned as it is.
+fn chebyshev_resample(table: Vec<(f64, f64)>, n: usize) -> Result<Vec<(f64, f64)>, String> {
+    if n == 0 {
+        return Ok(table);
+    }
+
+    let func = TableFunction::from_table(table);
+    match (func.min_x(), func.max_x()) {
+        (Some(from), Some(to)) => func
+            .sample_chebyshev(from, to, n)
+            .map_err(|e| format!("{:?}", e)),
+        _ => Err("No points given".to_string()),
+    }
+}
+
+/// Points per axis of `kernel_heatmap`.
+const HEATMAP_N: usize = 40;
+
+/// A `Heatmap` of `kernel(x, s)` on `[from, to]^2`. Points where the kernel fails are NaN,
+/// a Volterra kernel for example may only be defined for `s <= x`.
+pub fn kernel_heatmap<E>(
+    kernel: &dyn Function2d<Error = E>,
+    from: f64,
+    to: f64,
+) -> SolutionParagraph
+where
+    E: Debug,
+{
+    let total = |x, s| -> Result<f64, NoError> { Ok(kernel.apply(x, s).unwrap_or(f64::NAN)) };
+    match total.sample_grid(from, to, from, to, HEATMAP_N, HEATMAP_N) {
+        Ok((x, y, z)) if z.iter().flatten().any(|k| k.is_finite()) => {
+            SolutionParagraph::Heatmap { x, y, z, from, to }
+        }
+        Ok(_) => SolutionParagraph::RuntimeError(format!(
+            "The kernel is not finite anywhere on [{from}, {to}]^2"
+        )),
+        Err(e) => SolutionParagraph::RuntimeError(format!("Could not sample the kernel: {:?}", e)),
+    }
 }
 
 fn validate_expr(
@@ -46,21 +213,59 @@ fn validate_expr(
     runtime: &dyn Runtime,
     expr: &mut Option<Box<dyn Expression>>,
 ) -> Result<(), ValidationError> {
-    let res = match parse(contents, runtime) {
-        Some(expr) => {
+    let reserved = reserved_names_used(contents, runtime);
+    if !reserved.is_empty() {
+        return Err(ValidationError(format!(
+            "{field_name} - {:?} are function names and can not be used as variables",
+            reserved
+        )));
+    }
+
+    let res = match parse_with_limits(contents, runtime, ParseLimits::default()) {
+        Ok(expr) => {
             let vars = expr.query_vars();
-            if !vars.iter().all(|v| {
-                allowed_vars.map_or(true, |allowed_vars| allowed_vars.iter().any(|a| a == v))
-            }) {
+            let is_arg = |v: &str| allowed_vars.map_or(true, |allowed| allowed.contains(&v));
+            // any variable that is not an argument may name a stored value
+            let bound = vars
+                .iter()
+                .filter(|v| allowed_vars.is_none() || !is_arg(v))
+                .filter_map(|v| runtime.get_var(v).map(|val| (v.to_string(), val)))
+                .collect::<HashMap<_, _>>();
+            // `foo(x)` parses as `foo * x`, so a typo shows up as a variable before a bracket
+            let mut unknown = expr
+                .query_funcs()
+                .into_iter()
+                .filter(|f| !runtime.has_func(f))
+                .map(|f| f.to_string())
+                .chain(
+                    unknown_calls(contents, runtime)
+                        .into_iter()
+                        .filter(|v| !bound.contains_key(v)),
+                )
+                .collect::<Vec<_>>();
+            unknown.sort();
+            unknown.dedup();
+
+            if !unknown.is_empty() {
+                Err(ValidationError(format!(
+                    "{field_name} - unknown functions {:?}",
+                    unknown
+                )))
+            } else if !vars.iter().all(|v| is_arg(v) || bound.contains_key(*v)) {
                 Err(ValidationError(format!(
                     "{field_name} - vars {:?} not allowed, expected {:?}",
                     vars, allowed_vars
                 )))
-            } else {
+            } else if bound.is_empty() {
                 Ok(expr)
+            } else {
+                Ok(BoundExpression::new_expression(expr, bound))
             }
         }
-        None => Err(ValidationError(format!("{field_name} - could not parse"))),
+        Err(ParseError { message, position }) => Err(ValidationError(format!(
+            "{field_name} - could not parse at column {}: {message}",
+            contents[..position].chars().count() + 1
+        ))),
     };
 
     match res {
@@ -97,3 +302,133 @@ where
         Err(e) => Err(e),
     }
 }
+
+/// Like `validate_from_str`, but the value also has to be greater than zero,
+/// for counts that means at least 1.
+fn validate_positive<T>(
+    field_name: &str,
+    contents: &str,
+    val: &mut Option<T>,
+) -> Result<(), ValidationError>
+where
+    T: FromStr + PartialOrd + Default + Display,
+    <T as std::str::FromStr>::Err: Debug,
+{
+    let mut parsed = None;
+    validate_from_str(field_name, contents, &mut parsed)?;
+
+    match parsed {
+        Some(v) if v > T::default() => {
+            *val = Some(v);
+            Ok(())
+        }
+        Some(v) => Err(ValidationError(format!(
+            "{field_name} - must be positive, got {v}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[test]
+fn output_path() {
+    let dir = Path::new("out");
+    assert_eq!(
+        resolve_output_path(dir, "y.csv"),
+        Path::new("out").join("y.csv")
+    );
+
+    let absolute = std::env::temp_dir().join("y.csv");
+    assert_eq!(
+        resolve_output_path(dir, absolute.to_str().unwrap()),
+        absolute
+    );
+}
+
+#[test]
+fn reserved_var_names() {
+    use crate::mathparse::DefaultRuntime;
+
+    let runtime = DefaultRuntime::default();
+    let mut expr = None;
+
+    let err = validate_expr("f", "2sin + x", None, &runtime, &mut expr).unwrap_err();
+    assert!(err.0.contains("\"sin\"") && err.0.contains("function names"));
+    assert!(expr.is_none());
+
+    assert!(validate_expr("f", "2sin(x) + x", None, &runtime, &mut expr).is_ok());
+    assert!(expr.is_some());
+}
+
+#[test]
+fn unknown_functions() {
+    use crate::mathparse::DefaultRuntime;
+
+    let runtime = DefaultRuntime::default();
+    let mut expr = None;
+
+    let err = validate_expr("f", "foo(x) + 1", Some(&["x"]), &runtime, &mut expr).unwrap_err();
+    assert!(err.0.contains("unknown functions [\"foo\"]"), "{}", err.0);
+    assert!(expr.is_none());
+
+    // an argument before a bracket is still a product
+    assert!(validate_expr("f", "x(x+1)", Some(&["x"]), &runtime, &mut expr).is_ok());
+    // and so is a constant
+    assert!(validate_expr("f", "pi(x+1)", Some(&["x"]), &runtime, &mut expr).is_ok());
+    assert!(validate_expr("f", "e(x+1)", Some(&["x"]), &runtime, &mut expr).is_ok());
+
+    let parsed = crate::mathparse::parse_opt("sin(x)*max(1,cos(x))", &runtime).unwrap();
+    let mut funcs = parsed.query_funcs().into_iter().collect::<Vec<_>>();
+    funcs.sort();
+    assert_eq!(funcs, vec!["cos", "max", "sin"]);
+}
+
+#[test]
+fn solution_accessors() {
+    use self::graph::{Path, PathKind};
+
+    let graph = |x: f64| {
+        SolutionParagraph::Graph(
+            Graph::new(vec![Path {
+                pts: vec![(x, 0.0), (x + 1.0, 1.0)],
+                kind: PathKind::Line,
+                color: (0.0, 0.0, 0.0),
+                label: None,
+            }])
+            .unwrap(),
+        )
+    };
+
+    let solution = Solution {
+        explanation: vec![
+            SolutionParagraph::Text("a".to_string()),
+            graph(0.0),
+            SolutionParagraph::Latex("x^2".to_string()),
+            SolutionParagraph::RuntimeError("e".to_string()),
+            graph(5.0),
+            SolutionParagraph::Text("b".to_string()),
+        ],
+        values: vec![],
+    };
+
+    let graphs = solution.graphs().collect::<Vec<_>>();
+    assert_eq!(graphs.len(), 2);
+    assert_eq!(graphs[0].paths[0].pts[0], (0.0, 0.0));
+    assert_eq!(graphs[1].paths[0].pts[0], (5.0, 0.0));
+
+    assert_eq!(solution.texts().collect::<Vec<_>>(), vec!["a", "b"]);
+    assert_eq!(solution.errors().collect::<Vec<_>>(), vec!["e"]);
+    assert_eq!(solution.latex().collect::<Vec<_>>(), vec!["x^2"]);
+}
+
+#[test]
+fn shape_of_sin() {
+    let pts = (0..=400)
+        .map(|i| (i as f64) * 4.0 * std::f64::consts::PI / 400.0)
+        .map(|x| (x, x.sin()))
+        .collect::<Vec<_>>();
+
+    match shape_summary(&pts) {
+        SolutionParagraph::Text(t) => assert_eq!(t, "3 zero crossings, 4 local extrema"),
+        p => panic!("expected text, got {:?}", p),
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{
+    mathparse::{parse_opt, DefaultRuntime, Expression, Runtime},
+    min_find::nelder_mead::nelder_mead,
+};
+
+use super::{
+    form::Form, validate_expr, validate_from_str, validate_positive, Problem, ProblemCreator,
+    Solution, SolutionParagraph, ValidationError,
+};
+
+struct NelderMeadProblem {
+    ordered_vars: Vec<String>,
+    f: Box<dyn Expression>,
+    x0: Vec<f64>,
+    step: f64,
+    eps: f64,
+    max_iter_count: usize,
+}
+
+impl Problem for NelderMeadProblem {
+    fn solve(&self) -> Solution {
+        let f = |x: &[f64]| {
+            self.f.eval(&DefaultRuntime::new(
+                &self
+                    .ordered_vars
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| (name.as_str(), x[i]))
+                    .collect::<Vec<_>>(),
+            ))
+        };
+
+        match nelder_mead(&f, &self.x0, self.step, self.eps, self.max_iter_count) {
+            Ok(res) => {
+                let mut values = self
+                    .ordered_vars
+                    .iter()
+                    .zip(res.x.iter())
+                    .map(|(var, x)| (format!("min_{var}"), *x))
+                    .collect::<Vec<_>>();
+                values.push(("min_f".to_string(), res.y));
+
+                Solution {
+                    explanation: vec![
+                        SolutionParagraph::Text(format!("Min at ({:?}, {:.4})", res.x, res.y)),
+                        SolutionParagraph::Latex(format!(
+                            "f(x)={{{}}}",
+                            self.f
+                                .to_latex(&DefaultRuntime::default())
+                                .unwrap_or_else(|_| String::new())
+                        )),
+                    ],
+                    values,
+                }
+            }
+            Err(e) => Solution {
+                explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                values: vec![],
+            },
+        }
+    }
+}
+
+pub struct NelderMeadProblemCreator {
+    form: Form,
+    ordered_vars: Vec<String>,
+}
+
+impl Default for NelderMeadProblemCreator {
+    fn default() -> Self {
+        let mut form = Form::new(vec![
+            "f".to_string(),
+            "eps".to_string(),
+            "step".to_string(),
+            "max_iter_count".to_string(),
+            "x0".to_string(),
+            "y0".to_string(),
+        ]);
+
+        form.set("f", "abs(x-1)+pow(y+2,2)".to_string());
+        form.set("eps", "1e-8".to_string());
+        form.set("step", "1".to_string());
+        form.set("max_iter_count", "10000".to_string());
+        form.set("x0", "0".to_string());
+        form.set("y0", "0".to_string());
+
+        Self {
+            form,
+            ordered_vars: vec!["x".to_string(), "y".to_string()],
+        }
+    }
+}
+
+impl ProblemCreator for NelderMeadProblemCreator {
+    fn fields(&self) -> super::form::FieldsIter {
+        self.form.get_fields()
+    }
+
+    fn set_field(&mut self, name: &str, val: String) {
+        if name == "f" {
+            if let Some(expr) = parse_opt(&val, &DefaultRuntime::default()) {
+                let new_vars =
+                    Vec::from_iter(expr.query_vars().iter().map(|name| name.to_string()));
+
+                let mut new_form = Form::new(vec![
+                    "f".to_string(),
+                    "eps".to_string(),
+                    "step".to_string(),
+                    "max_iter_count".to_string(),
+                ]);
+                for name in ["f", "eps", "step", "max_iter_count"] {
+                    if let Some(val) = self.form.get(name) {
+                        new_form.set(name, val.clone())
+                    }
+                }
+                for name in &new_vars {
+                    new_form.add_field(format!("{name}0"));
+                    if let Some(val) = self.form.get(&format!("{name}0")) {
+                        new_form.set(&format!("{name}0"), val.clone())
+                    }
+                }
+
+                self.form = new_form;
+                self.ordered_vars = new_vars;
+            }
+        }
+        self.form.set(name, val);
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn try_create(&self, runtime: &dyn Runtime) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
+        let mut f = None;
+        let mut eps = None;
+        let mut step = None;
+        let mut max_iter_count = None;
+
+        // stored values are constants of f, not coordinates to search over
+        let ordered_vars = self
+            .ordered_vars
+            .iter()
+            .filter(|name| runtime.get_var(name).is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut x0 = vec![None; ordered_vars.len()];
+
+        let mut errors = vec![];
+        let allowed_vars = ordered_vars
+            .iter()
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>();
+
+        for (name, val) in self.fields() {
+            let res = match name {
+                "f" => validate_expr(name, val, Some(&allowed_vars), runtime, &mut f),
+                "eps" => validate_positive::<f64>(name, val, &mut eps),
+                "step" => validate_positive::<f64>(name, val, &mut step),
+                "max_iter_count" => validate_positive::<usize>(name, val, &mut max_iter_count),
+                _ if name
+                    .strip_suffix('0')
+                    .and_then(|var| runtime.get_var(var))
+                    .is_some() =>
+                {
+                    continue
+                }
+                _ => match name
+                    .strip_suffix('0')
+                    .and_then(|var| ordered_vars.iter().position(|name| name == var))
+                {
+                    Some(i) => validate_from_str::<f64>(name, val, &mut x0[i]),
+                    None => Err(ValidationError(format!(
+                        "{name} - no such field (probably a devs error)"
+                    ))),
+                },
+            };
+
+            match res {
+                Ok(_) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+
+        let f =
+            f.ok_or_else(|| errors.push(ValidationError("field f was not supplied".to_string())));
+        let eps = eps
+            .ok_or_else(|| errors.push(ValidationError("field eps was not supplied".to_string())));
+        let step = step
+            .ok_or_else(|| errors.push(ValidationError("field step was not supplied".to_string())));
+        let max_iter_count = max_iter_count.ok_or_else(|| {
+            errors.push(ValidationError(
+                "field max_iter_count was not supplied".to_string(),
+            ))
+        });
+        let x0 = x0.into_iter().collect::<Option<Vec<_>>>().ok_or_else(|| {
+            errors.push(ValidationError(
+                "Not all x0 coordinates were supplied".to_string(),
+            ))
+        });
+
+        if !errors.is_empty() {
+            Err(errors)
+        } else {
+            Ok(Box::new(NelderMeadProblem {
+                ordered_vars: ordered_vars.clone(),
+                f: f.unwrap(),
+                x0: x0.unwrap(),
+                step: step.unwrap(),
+                eps: eps.unwrap(),
+                max_iter_count: max_iter_count.unwrap(),
+            }))
+        }
+    }
+}
+
+#[test]
+fn nelder_mead_problem() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = NelderMeadProblemCreator::default();
+    creator.set_field("f", "pow(x-3,2)+pow(y+1,2)".to_string());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    let value = |name: &str| {
+        solution
+            .values
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| *v)
+            .unwrap()
+    };
+    let (x, y) = (value("min_x"), value("min_y"));
+    assert!(
+        (x - 3.0).abs() < 0.01 && (y + 1.0).abs() < 0.01,
+        "({x}, {y})"
+    );
+}
+
+#[test]
+fn nelder_mead_min_f() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = NelderMeadProblemCreator::default();
+    creator.set_field("f", "pow(x-3,2)+pow(y+1,2)+2".to_string());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    let names = solution
+        .values
+        .iter()
+        .map(|(n, _)| n.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names.iter().filter(|n| **n == "min_y").count(), 1);
+    let (_, min_f) = solution.values.iter().find(|(n, _)| n == "min_f").unwrap();
+    assert!((min_f - 2.0).abs() < 1e-4, "{min_f}");
+}
+
+#[test]
+fn stored_value_is_not_a_variable() {
+    let runtime = DefaultRuntime::new(&[("a", 2.5)]);
+    let mut creator = NelderMeadProblemCreator::default();
+    creator.set_field("f", "pow(x-a,2)".to_string());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    let names = solution
+        .values
+        .iter()
+        .map(|(n, _)| n.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["min_x", "min_f"]);
+    assert!((solution.values[0].1 - 2.5).abs() < 0.01);
+}
+
+#[test]
+fn unknown_function() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = NelderMeadProblemCreator::default();
+    creator.set_field("f", "sinn(x)".to_string());
+    creator.set_field("x0", "0".to_string());
+
+    let errors = creator
+        .try_create(&runtime)
+        .err()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.0)
+        .collect::<Vec<_>>();
+    assert!(
+        errors.contains(&"f - unknown functions [\"sinn\"]".to_string()),
+        "{errors:?}"
+    );
+}
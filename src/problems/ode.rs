@@ -0,0 +1,228 @@
+use crate::{
+    integral_eq::ode::{solve_ivp, OdeMethod},
+    mathparse::{DefaultRuntime, Expression, PositionalRuntime, Runtime},
+};
+
+use super::{
+    csv_download,
+    form::Form,
+    graph::{Graph, Path, PathKind},
+    shape_summary, validate_expr, validate_from_str, validate_positive, Problem, ProblemCreator,
+    Solution, SolutionParagraph, ValidationError,
+};
+
+struct OdeProblem {
+    f: Box<dyn Expression>,
+    x0: f64,
+    y0: f64,
+    to: f64,
+    n: usize,
+    method: OdeMethod,
+}
+
+impl Problem for OdeProblem {
+    fn solve(&self) -> Solution {
+        let compiled = match self.f.compile(&DefaultRuntime::default()) {
+            Ok(f) => f,
+            Err(e) => {
+                return Solution {
+                    explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                    values: vec![],
+                }
+            }
+        };
+        let runtime = PositionalRuntime::new(&["x", "y"]);
+        let res = solve_ivp(
+            &|x, y| {
+                runtime.set(&[x, y]);
+                compiled.eval(&runtime)
+            },
+            self.x0,
+            self.y0,
+            self.to,
+            self.n,
+            self.method,
+        );
+
+        match res {
+            Ok(res) => {
+                let pts = res.into_table();
+                let y_end = if self.to < self.x0 {
+                    pts.first()
+                } else {
+                    pts.last()
+                }
+                .map(|(_, y)| *y)
+                .unwrap_or(self.y0);
+
+                let mut explanation = vec![];
+                if let Ok(latex) = self.f.to_latex(&DefaultRuntime::default()) {
+                    explanation.push(SolutionParagraph::Latex(format!(
+                        "y'={{{}}},\\ y({})={}",
+                        latex, self.x0, self.y0
+                    )));
+                }
+                explanation.push(SolutionParagraph::Text(format!(
+                    "{:?}: y({}) = {:.6}",
+                    self.method, self.to, y_end
+                )));
+                explanation.push(shape_summary(&pts));
+                explanation.push(csv_download("y.csv", &pts));
+                explanation.push(
+                    match Graph::new(vec![Path {
+                        pts,
+                        kind: PathKind::Line,
+                        color: (1.0, 0.0, 0.0),
+                        label: None,
+                    }]) {
+                        Some(g) => SolutionParagraph::Graph(g),
+                        None => {
+                            SolutionParagraph::RuntimeError("Could not draw a graph".to_string())
+                        }
+                    },
+                );
+
+                Solution {
+                    explanation,
+                    values: vec![("y_end".to_string(), y_end)],
+                }
+            }
+            Err(e) => Solution {
+                explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                values: vec![],
+            },
+        }
+    }
+}
+
+pub struct OdeProblemCreator {
+    form: Form,
+}
+
+impl Default for OdeProblemCreator {
+    fn default() -> Self {
+        let mut form = Form::new(vec![
+            "y'".to_string(),
+            "x0".to_string(),
+            "y0".to_string(),
+            "to".to_string(),
+            "n".to_string(),
+            "method".to_string(),
+        ]);
+
+        form.set("y'", "y*cos(x)".to_string());
+        form.set("x0", "0".to_string());
+        form.set("y0", "1".to_string());
+        form.set("to", "10".to_string());
+        form.set("n", "200".to_string());
+        form.set("method", "rk4".to_string());
+
+        Self { form }
+    }
+}
+
+impl ProblemCreator for OdeProblemCreator {
+    fn fields(&self) -> super::form::FieldsIter {
+        self.form.get_fields()
+    }
+
+    fn set_field(&mut self, name: &str, val: String) {
+        self.form.set(name, val)
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn try_create(&self, runtime: &dyn Runtime) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
+        let mut f = None;
+        let mut x0 = None;
+        let mut y0 = None;
+        let mut to = None;
+        let mut n = None;
+        let mut method = None;
+
+        let mut errors = vec![];
+        for (name, val) in self.form.get_fields() {
+            let res = match name {
+                "y'" => validate_expr(name, val, Some(&["x", "y"]), runtime, &mut f),
+                "x0" => validate_from_str::<f64>(name, val, &mut x0),
+                "y0" => validate_from_str::<f64>(name, val, &mut y0),
+                "to" => validate_from_str::<f64>(name, val, &mut to),
+                "n" => validate_positive::<usize>(name, val, &mut n),
+                "method" => validate_from_str::<OdeMethod>(name, val, &mut method),
+                _ => Err(ValidationError(format!(
+                    "{name} - no such field (probably a devs error)"
+                ))),
+            };
+
+            match res {
+                Ok(_) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+
+        if matches!(n, Some(n) if n < 2) {
+            errors.push(ValidationError("n - need at least 2 points".to_string()));
+        }
+        if matches!((x0, to), (Some(x0), Some(to)) if x0 == to) {
+            errors.push(ValidationError("to - must differ from x0".to_string()));
+        }
+
+        let f =
+            f.ok_or_else(|| errors.push(ValidationError("field was not supplied: y'".to_string())));
+        let x0 = x0
+            .ok_or_else(|| errors.push(ValidationError("field was not supplied: x0".to_string())));
+        let y0 = y0
+            .ok_or_else(|| errors.push(ValidationError("field was not supplied: y0".to_string())));
+        let to = to
+            .ok_or_else(|| errors.push(ValidationError("field was not supplied: to".to_string())));
+        let n =
+            n.ok_or_else(|| errors.push(ValidationError("field was not supplied: n".to_string())));
+        let method = method.ok_or_else(|| {
+            errors.push(ValidationError(
+                "field was not supplied: method".to_string(),
+            ))
+        });
+
+        if errors.is_empty() {
+            Ok(Box::new(OdeProblem {
+                f: f.unwrap(),
+                x0: x0.unwrap(),
+                y0: y0.unwrap(),
+                to: to.unwrap(),
+                n: n.unwrap(),
+                method: method.unwrap(),
+            }))
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[test]
+fn ode_problem() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = OdeProblemCreator::default();
+    creator.set_field("y'", "y".to_string());
+    creator.set_field("to", "1".to_string());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    let (_, y_end) = solution.values[0];
+    assert!((y_end - std::f64::consts::E).abs() < 1e-8, "{y_end}");
+    assert_eq!(solution.graphs().count(), 1);
+
+    creator.set_field("method", "midpoint".to_string());
+    assert!(creator.try_create(&runtime).is_err());
+
+    creator.set_field("method", "rk4".to_string());
+    creator.set_field("to", "0".to_string());
+    let errors = creator
+        .try_create(&runtime)
+        .err()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.0)
+        .collect::<Vec<_>>();
+    assert_eq!(errors, vec!["to - must differ from x0".to_string()]);
+}
@@ -0,0 +1,193 @@
+use crate::{
+    functions::function::sample_parametric,
+    mathparse::{DefaultRuntime, Expression, Runtime},
+};
+
+use super::{
+    form::Form,
+    graph::{Graph, Path, PathKind},
+    validate_expr, validate_from_str, validate_positive, Problem, ProblemCreator, Solution,
+    SolutionParagraph, ValidationError,
+};
+
+struct PlotParametricProblem {
+    x_of_t: Box<dyn Expression>,
+    y_of_t: Box<dyn Expression>,
+    t_from: f64,
+    t_to: f64,
+    n: usize,
+}
+
+impl Problem for PlotParametricProblem {
+    fn solve(&self) -> Solution {
+        let x = |t| self.x_of_t.eval(&DefaultRuntime::new(&[("t", t)]));
+        let y = |t| self.y_of_t.eval(&DefaultRuntime::new(&[("t", t)]));
+
+        match sample_parametric(&x, &y, self.t_from, self.t_to, self.n) {
+            Ok(pts) => {
+                let mut solution = vec![
+                    SolutionParagraph::Latex(format!(
+                        "x(t)={{{}}}",
+                        self.x_of_t
+                            .to_latex(&DefaultRuntime::default())
+                            .unwrap_or_else(|_| String::new())
+                    )),
+                    SolutionParagraph::Latex(format!(
+                        "y(t)={{{}}}",
+                        self.y_of_t
+                            .to_latex(&DefaultRuntime::default())
+                            .unwrap_or_else(|_| String::new())
+                    )),
+                ];
+
+                match Graph::new(vec![Path {
+                    pts,
+                    kind: PathKind::Line,
+                    color: (1.0, 0.0, 0.0),
+                    label: None,
+                }]) {
+                    Some(g) => solution.push(SolutionParagraph::Graph(g)),
+                    None => solution.push(SolutionParagraph::RuntimeError(
+                        "Could not draw a graph".to_string(),
+                    )),
+                }
+
+                Solution {
+                    explanation: solution,
+                    values: vec![],
+                }
+            }
+            Err(e) => Solution {
+                explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                values: vec![],
+            },
+        }
+    }
+}
+
+pub struct PlotParametricProblemCreator {
+    form: Form,
+}
+
+impl Default for PlotParametricProblemCreator {
+    fn default() -> Self {
+        let mut form = Form::new(vec![
+            "x_of_t".to_string(),
+            "y_of_t".to_string(),
+            "t_from".to_string(),
+            "t_to".to_string(),
+            "n".to_string(),
+        ]);
+
+        form.set("x_of_t", "cos(t)".to_string());
+        form.set("y_of_t", "sin(t)".to_string());
+        form.set("t_from", "0".to_string());
+        form.set("t_to", "6.2832".to_string());
+        form.set("n", "100".to_string());
+
+        Self { form }
+    }
+}
+
+impl ProblemCreator for PlotParametricProblemCreator {
+    fn try_create(&self, runtime: &dyn Runtime) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
+        let mut x_of_t = None;
+        let mut y_of_t = None;
+        let mut t_from = None;
+        let mut t_to = None;
+        let mut n = None;
+
+        let mut errors = vec![];
+        for (name, val) in self.form.get_fields() {
+            let res = match name {
+                "x_of_t" => validate_expr(name, val, Some(&["t"]), runtime, &mut x_of_t),
+                "y_of_t" => validate_expr(name, val, Some(&["t"]), runtime, &mut y_of_t),
+                "t_from" => validate_from_str::<f64>(name, val, &mut t_from),
+                "t_to" => validate_from_str::<f64>(name, val, &mut t_to),
+                "n" => validate_positive::<usize>(name, val, &mut n),
+                _ => Err(ValidationError(format!(
+                    "{name} - no such field (probably a devs error)"
+                ))),
+            };
+
+            match res {
+                Ok(_) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+
+        if matches!(n, Some(n) if n < 2) {
+            errors.push(ValidationError("n - need at least 2 points".to_string()));
+        }
+
+        let x_of_t = x_of_t.ok_or_else(|| {
+            errors.push(ValidationError(
+                "field was not supplied: x_of_t".to_string(),
+            ))
+        });
+        let y_of_t = y_of_t.ok_or_else(|| {
+            errors.push(ValidationError(
+                "field was not supplied: y_of_t".to_string(),
+            ))
+        });
+        let t_from = t_from.ok_or_else(|| {
+            errors.push(ValidationError(
+                "field was not supplied: t_from".to_string(),
+            ))
+        });
+        let t_to = t_to.ok_or_else(|| {
+            errors.push(ValidationError("field was not supplied: t_to".to_string()))
+        });
+        let n =
+            n.ok_or_else(|| errors.push(ValidationError("field was not supplied: n".to_string())));
+
+        if errors.is_empty() {
+            Ok(Box::new(PlotParametricProblem {
+                x_of_t: x_of_t.unwrap(),
+                y_of_t: y_of_t.unwrap(),
+                t_from: t_from.unwrap(),
+                t_to: t_to.unwrap(),
+                n: n.unwrap(),
+            }))
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn fields(&self) -> super::form::FieldsIter {
+        self.form.get_fields()
+    }
+
+    fn set_field(&mut self, name: &str, val: String) {
+        self.form.set(name, val)
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[test]
+fn unit_circle() {
+    let problem =
+        match PlotParametricProblemCreator::default().try_create(&DefaultRuntime::default()) {
+            Ok(p) => p,
+            Err(_) => panic!("default fields should be valid"),
+        };
+
+    let solution = problem.solve();
+    let graph = solution
+        .explanation
+        .iter()
+        .find_map(|p| match p {
+            SolutionParagraph::Graph(g) => Some(g),
+            _ => None,
+        })
+        .unwrap();
+
+    assert_eq!(graph.paths[0].pts.len(), 101);
+    assert!(graph.paths[0]
+        .pts
+        .iter()
+        .all(|(x, y)| (x * x + y * y - 1.0).abs() < 1e-9));
+}
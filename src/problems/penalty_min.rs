@@ -2,17 +2,27 @@ use std::collections::HashMap;
 
 use crate::{
     functions::function::Function,
-    mathparse::{DefaultRuntime, Error, Expression},
-    min_find::penalty_min::penalty_min,
+    mathparse::{DefaultRuntime, Error, Expression, Runtime},
+    min_find::penalty_min::{penalized, penalty_min, Error as PenaltyMinError},
 };
 
 use super::{
     form::Form,
-    graph::{Graph, Path},
-    validate_expr, validate_from_str, Problem, ProblemCreator, Solution, SolutionParagraph,
-    ValidationError,
+    graph::{plot_points, Graph, Path},
+    validate_expr, validate_from_str, validate_positive, Problem, ProblemCreator, Solution,
+    SolutionParagraph, ValidationError,
 };
 
+/// Colours of the constraint graphs, red, orange and blue are taken
+/// by f, the penalized f and the minimum.
+const CONSTRAINT_COLORS: [(f32, f32, f32); 5] = [
+    (0.0, 1.0, 0.0),
+    (0.6, 0.0, 0.8),
+    (0.0, 0.7, 0.7),
+    (1.0, 0.0, 1.0),
+    (0.4, 0.4, 0.0),
+];
+
 struct PenaltyMinProblem {
     f: Box<dyn Expression>,
     constraints: Vec<Box<dyn Expression>>,
@@ -32,11 +42,13 @@ impl Problem for PenaltyMinProblem {
             .collect::<Vec<_>>();
 
         let f = |x| self.f.eval(&DefaultRuntime::new(&[("x", x)]));
+        let constraints = c
+            .iter()
+            .map(|f| f as &dyn Function<Error = Error>)
+            .collect::<Vec<_>>();
         let res = penalty_min(
             &f,
-            &c.iter()
-                .map(|f| f as &dyn Function<Error = Error>)
-                .collect::<Vec<_>>(),
+            &constraints,
             self.from,
             self.to,
             self.start_eps,
@@ -44,30 +56,49 @@ impl Problem for PenaltyMinProblem {
             self.max_iter_count,
         );
         match res {
-            Ok(res) => {
+            Ok((res, eps)) => {
                 let graphs = c
                     .iter()
-                    .map(|c| c.sample(self.from, self.to, 20))
-                    .map(|pts| {
-                        pts.map(|p| Path {
+                    .enumerate()
+                    .map(|(i, c)| {
+                        plot_points(c, self.from, self.to).map(|p| Path {
                             pts: p,
                             kind: super::graph::PathKind::Line,
-                            color: (0.0, 1.0, 0.0),
+                            color: CONSTRAINT_COLORS[i % CONSTRAINT_COLORS.len()],
+                            label: Some(format!("g_{i}")),
                         })
                     })
                     .collect::<Result<Vec<_>, _>>();
                 let graphs = graphs
                     .and_then(|mut g| {
-                        f.sample(self.from, self.to, 20).map(|f_pts| {
+                        plot_points(&f, self.from, self.to).map(|f_pts| {
+                            // the penalty grows like 1/eps, keep it from flattening the rest of the graph
+                            let top = f_pts.iter().map(|(_, y)| *y).fold(f64::MIN, f64::max);
+                            let penalized_pts = f_pts
+                                .iter()
+                                .map(|(x, _)| {
+                                    penalized(&f, &constraints, eps, *x).map(|y| (*x, y.min(top)))
+                                })
+                                .collect::<Result<Vec<_>, _>>();
                             g.push(Path {
                                 pts: f_pts,
                                 kind: super::graph::PathKind::Line,
                                 color: (1.0, 0.0, 0.0),
+                                label: Some("f".to_string()),
                             });
+                            if let Ok(pts) = penalized_pts {
+                                g.push(Path {
+                                    pts,
+                                    kind: super::graph::PathKind::Line,
+                                    color: (1.0, 0.5, 0.0),
+                                    label: Some("penalized f".to_string()),
+                                });
+                            }
                             g.push(Path {
                                 pts: vec![(res.x, res.y)],
                                 kind: super::graph::PathKind::Dot,
                                 color: (0.0, 0.0, 1.0),
+                                label: Some("min".to_string()),
                             });
                             g
                         })
@@ -101,15 +132,52 @@ impl Problem for PenaltyMinProblem {
                     Err(e) => SolutionParagraph::RuntimeError(e),
                 });
 
-                Solution { explanation: expl }
+                Solution {
+                    explanation: expl,
+                    values: vec![("min_x".to_string(), res.x), ("min_y".to_string(), res.y)],
+                }
             }
+            Err(PenaltyMinError::LineSearchStalled(m, iterations)) => Solution {
+                explanation: vec![SolutionParagraph::RuntimeError(format!(
+                    "The line search stalled after {iterations} iterations near x = {:.4}, try a larger min_step",
+                    m.x
+                ))],
+                values: vec![],
+            },
             Err(e) => Solution {
                 explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                values: vec![],
             },
         }
     }
 }
 
+/// Several constraints in one field, separated by `;`.
+fn validate_constraint_list(
+    field_name: &str,
+    contents: &str,
+    runtime: &dyn Runtime,
+    constraints: &mut Vec<Box<dyn Expression>>,
+) -> Result<(), ValidationError> {
+    for (i, c) in contents
+        .split(';')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .enumerate()
+    {
+        let mut expr = None;
+        validate_expr(
+            &format!("{field_name}[{i}]"),
+            c,
+            Some(&["x"]),
+            runtime,
+            &mut expr,
+        )?;
+        constraints.extend(expr);
+    }
+    Ok(())
+}
+
 pub struct PenaltyMinProblemCreator {
     form: Form,
     constraint_count: usize,
@@ -124,6 +192,7 @@ impl Default for PenaltyMinProblemCreator {
             "start_eps".to_string(),
             "min_step".to_string(),
             "max_iter_count".to_string(),
+            "constraints".to_string(),
             "constraint1".to_string(),
             "constraint2".to_string(),
         ]);
@@ -144,7 +213,10 @@ impl Default for PenaltyMinProblemCreator {
 }
 
 impl ProblemCreator for PenaltyMinProblemCreator {
-    fn try_create(&self) -> Result<Box<dyn Problem>, Vec<super::ValidationError>> {
+    fn try_create(
+        &self,
+        runtime: &dyn Runtime,
+    ) -> Result<Box<dyn Problem>, Vec<super::ValidationError>> {
         let mut f = None;
         let mut from = None;
         let mut to = None;
@@ -153,16 +225,18 @@ impl ProblemCreator for PenaltyMinProblemCreator {
         let mut max_iter_count = None;
 
         let mut constraints: HashMap<usize, Option<Box<dyn Expression>>> = HashMap::new();
+        let mut constraint_list = vec![];
         let mut errors = vec![];
 
         for (name, val) in self.fields() {
             let res = match name {
-                "f" => validate_expr("f", val, Some(&["x"]), &DefaultRuntime::default(), &mut f),
+                "f" => validate_expr("f", val, Some(&["x"]), runtime, &mut f),
                 "from" => validate_from_str("from", val, &mut from),
                 "to" => validate_from_str("to", val, &mut to),
-                "start_eps" => validate_from_str("start_eps", val, &mut start_eps),
-                "min_step" => validate_from_str("min_step", val, &mut min_step),
-                "max_iter_count" => validate_from_str("max_iter_count", val, &mut max_iter_count),
+                "start_eps" => validate_positive("start_eps", val, &mut start_eps),
+                "min_step" => validate_positive("min_step", val, &mut min_step),
+                "max_iter_count" => validate_positive("max_iter_count", val, &mut max_iter_count),
+                "constraints" => validate_constraint_list(name, val, runtime, &mut constraint_list),
                 _ => {
                     if let Some(index) = name.strip_prefix("constraint") {
                         index
@@ -182,7 +256,7 @@ impl ProblemCreator for PenaltyMinProblemCreator {
                                         name,
                                         val,
                                         Some(&["x"]),
-                                        &DefaultRuntime::default(),
+                                        runtime,
                                         constraints.entry(i).or_insert(None),
                                     )
                                 }
@@ -232,7 +306,11 @@ impl ProblemCreator for PenaltyMinProblemCreator {
                 start_eps: start_eps.unwrap(),
                 min_step: min_step.unwrap(),
                 max_iter_count: max_iter_count.unwrap(),
-                constraints: constraints.into_values().flatten().collect(),
+                constraints: constraints
+                    .into_values()
+                    .flatten()
+                    .chain(constraint_list)
+                    .collect(),
             }))
         } else {
             Err(errors)
@@ -244,7 +322,9 @@ impl ProblemCreator for PenaltyMinProblemCreator {
     }
 
     fn set_field(&mut self, name: &str, val: String) {
-        if let Some(index) = name.strip_prefix("constraint") {
+        if name == "constraints" {
+            self.form.set(name, val);
+        } else if let Some(index) = name.strip_prefix("constraint") {
             if let Ok(i) = index.parse::<usize>() {
                 if i == self.constraint_count {
                     self.constraint_count += 1;
@@ -258,4 +338,70 @@ impl ProblemCreator for PenaltyMinProblemCreator {
             self.form.set(name, val);
         }
     }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[test]
+fn plots_penalized() {
+    let creator = PenaltyMinProblemCreator::default();
+    let solution = creator
+        .try_create(&DefaultRuntime::default())
+        .ok()
+        .unwrap()
+        .solve();
+
+    let graph = solution.graphs().next().unwrap();
+    // one constraint, f, the penalized f and the minimum
+    assert_eq!(graph.paths.len(), 4);
+    let penalized = &graph.paths[2];
+    assert_eq!(penalized.color, (1.0, 0.5, 0.0));
+    assert!(penalized
+        .pts
+        .iter()
+        .zip(graph.paths[1].pts.iter())
+        .all(|((_, p), (_, f))| p >= f));
+}
+
+#[test]
+fn constraint_list() {
+    let mut constraints = vec![];
+    validate_constraint_list(
+        "constraints",
+        "-x-1; x-2",
+        &DefaultRuntime::default(),
+        &mut constraints,
+    )
+    .ok()
+    .unwrap();
+    assert_eq!(constraints.len(), 2);
+    assert_eq!(
+        constraints
+            .iter()
+            .map(|c| c.eval(&DefaultRuntime::new(&[("x", 0.0)])))
+            .collect::<Vec<_>>(),
+        vec![Ok(-1.0), Ok(-2.0)]
+    );
+
+    let mut creator = PenaltyMinProblemCreator::default();
+    creator.set_field("constraint1", String::new());
+    creator.set_field("constraints", "-x-1; x-2".to_string());
+    let solution = creator
+        .try_create(&DefaultRuntime::default())
+        .ok()
+        .unwrap()
+        .solve();
+    // two constraints, f, the penalized f and the minimum
+    let graph = solution.graphs().next().unwrap();
+    assert_eq!(graph.paths.len(), 5);
+    assert_ne!(graph.paths[0].color, graph.paths[1].color);
+
+    creator.set_field("constraints", "-x-1; x-".to_string());
+    let errors = creator
+        .try_create(&DefaultRuntime::default())
+        .err()
+        .unwrap();
+    assert!(errors[0].0.starts_with("constraints[1]"));
 }
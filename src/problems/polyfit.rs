@@ -0,0 +1,222 @@
+use std::path::Path as FilePath;
+
+use crate::{
+    functions::{polynomial::Polynomial, table_function::TableFunction},
+    mathparse::Runtime,
+};
+
+use super::{
+    chebyshev_resample,
+    form::Form,
+    graph::{plot_points, Graph, Path, PathKind},
+    validate_from_str, Problem, ProblemCreator, Solution, SolutionParagraph, ValidationError,
+};
+
+struct PolyFitProblem {
+    src_file: String,
+    degree: usize,
+    chebyshev_n: usize,
+}
+
+impl PolyFitProblem {
+    fn fit(&self, table: Vec<(f64, f64)>) -> Result<Solution, String> {
+        let poly = Polynomial::fit(&table, self.degree).map_err(|e| format!("{:?}", e))?;
+        let (from, to) = (table[0].0, table[table.len() - 1].0);
+        let fitted = plot_points(&poly, from, to).map_err(|e| format!("{:?}", e))?;
+
+        let latex = poly
+            .coefs
+            .iter()
+            .enumerate()
+            .map(|(i, c)| match i {
+                0 => format!("{c:.6}"),
+                1 => format!("{c:+.6}x"),
+                _ => format!("{c:+.6}x^{{{i}}}"),
+            })
+            .collect::<String>();
+        let graph = Graph::new(vec![
+            Path {
+                pts: fitted,
+                kind: PathKind::Line,
+                color: (1.0, 0.0, 0.0),
+                label: Some(format!("degree {}", self.degree)),
+            },
+            Path {
+                pts: table,
+                kind: PathKind::Dot,
+                color: (0.0, 0.0, 1.0),
+                label: Some(self.src_file.clone()),
+            },
+        ])
+        .ok_or_else(|| "Could not create graph".to_string())?;
+
+        Ok(Solution {
+            explanation: vec![
+                SolutionParagraph::Latex(format!("p(x)={latex}")),
+                SolutionParagraph::Graph(graph),
+            ],
+            values: poly
+                .coefs
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (format!("c{i}"), *c))
+                .collect(),
+        })
+    }
+}
+
+impl Problem for PolyFitProblem {
+    fn solve(&self) -> Solution {
+        let res = TableFunction::from_file(FilePath::new(&self.src_file))
+            .map_err(|e| format!("{:?}", e))
+            .and_then(|table| chebyshev_resample(table.into_table(), self.chebyshev_n))
+            .and_then(|table| self.fit(table));
+
+        match res {
+            Ok(solution) => solution,
+            Err(e) => Solution {
+                explanation: vec![SolutionParagraph::RuntimeError(e)],
+                values: vec![],
+            },
+        }
+    }
+}
+
+pub struct PolyFitProblemCreator {
+    form: Form,
+}
+
+impl Default for PolyFitProblemCreator {
+    fn default() -> Self {
+        let mut form = Form::new(vec![
+            "src_file".to_string(),
+            "degree".to_string(),
+            "chebyshev_n".to_string(),
+        ]);
+        form.set("src_file", "pts.csv".to_string());
+        form.set("degree", "2".to_string());
+        form.set("chebyshev_n", "0".to_string());
+
+        Self { form }
+    }
+}
+
+impl ProblemCreator for PolyFitProblemCreator {
+    fn fields(&self) -> super::form::FieldsIter {
+        self.form.get_fields()
+    }
+
+    fn set_field(&mut self, name: &str, val: String) {
+        self.form.set(name, val)
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn try_create(&self, _runtime: &dyn Runtime) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
+        let mut src_file = None;
+        let mut degree = None;
+        let mut chebyshev_n = None;
+
+        let mut errors = vec![];
+        for (name, val) in self.form.get_fields() {
+            let res = match name {
+                "src_file" => {
+                    src_file = Some(val);
+                    Ok(())
+                }
+                "degree" => validate_from_str::<usize>(name, val, &mut degree),
+                "chebyshev_n" => validate_from_str::<usize>(name, val, &mut chebyshev_n),
+                _ => Err(ValidationError(format!(
+                    "{name} - no such field (probably a devs error)"
+                ))),
+            };
+
+            match res {
+                Ok(_) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+
+        let src_file = src_file.ok_or_else(|| {
+            errors.push(ValidationError(
+                "field was not supplied - src_file".to_string(),
+            ))
+        });
+        let degree = degree.ok_or_else(|| {
+            errors.push(ValidationError(
+                "field was not supplied - degree".to_string(),
+            ))
+        });
+
+        if errors.is_empty() {
+            Ok(Box::new(PolyFitProblem {
+                src_file: src_file.unwrap().to_string(),
+                degree: degree.unwrap(),
+                chebyshev_n: chebyshev_n.unwrap_or(0),
+            }))
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[test]
+fn polyfit_problem() {
+    use crate::mathparse::DefaultRuntime;
+
+    let path = std::env::temp_dir().join("prac_polyfit_test.csv");
+    std::fs::write(&path, "-1,4\n0,1\n1,0\n2,1\n3,4\n").unwrap();
+
+    let mut creator = PolyFitProblemCreator::default();
+    creator.set_field("src_file", path.to_string_lossy().to_string());
+    let solution = creator
+        .try_create(&DefaultRuntime::default())
+        .ok()
+        .unwrap()
+        .solve();
+    std::fs::remove_file(&path).unwrap();
+
+    // (x - 1)^2
+    assert_eq!(solution.values.len(), 3);
+    for ((_, c), expected) in solution.values.iter().zip([1.0, -2.0, 1.0]) {
+        assert!((c - expected).abs() < 1e-9, "{:?}", solution.values);
+    }
+    let graph = solution.graphs().next().unwrap();
+    assert_eq!(graph.paths[1].pts.len(), 5);
+}
+
+#[test]
+fn polyfit_chebyshev_resample() {
+    use crate::{functions::function::Function, mathparse::DefaultRuntime};
+
+    // Runge's function, a degree 10 fit through equidistant points swings near the ends
+    let path = std::env::temp_dir().join("prac_polyfit_chebyshev_test.csv");
+    let pts = (0..=400)
+        .map(|i| -1.0 + i as f64 / 200.0)
+        .map(|x| format!("{x},{}\n", 1.0 / (1.0 + 25.0 * x * x)))
+        .collect::<String>();
+    std::fs::write(&path, pts).unwrap();
+
+    let mut creator = PolyFitProblemCreator::default();
+    creator.set_field("src_file", path.to_string_lossy().to_string());
+    creator.set_field("degree", "10".to_string());
+    creator.set_field("chebyshev_n", "11".to_string());
+    let solution = creator
+        .try_create(&DefaultRuntime::default())
+        .ok()
+        .unwrap()
+        .solve();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(solution.errors().count(), 0);
+    let graph = solution.graphs().next().unwrap();
+    assert_eq!(graph.paths[1].pts.len(), 11);
+    // interpolates the 11 nodes
+    let coefs = solution.values.iter().map(|(_, c)| *c).collect::<Vec<_>>();
+    let poly = Polynomial::new(coefs);
+    for (x, y) in &graph.paths[1].pts {
+        assert!((poly.apply(*x).unwrap() - y).abs() < 1e-6);
+    }
+}
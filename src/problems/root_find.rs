@@ -0,0 +1,257 @@
+use crate::{
+    area_calc::{newton_root, root, RootMethod},
+    mathparse::{DefaultRuntime, Error, Expression, Runtime},
+};
+
+use super::{
+    form::Form,
+    graph::{plot_points, Graph, Path, PathKind},
+    validate_expr, validate_from_str, validate_positive, Problem, ProblemCreator, Solution,
+    SolutionParagraph, ValidationError,
+};
+
+struct RootFindProblem {
+    f: Box<dyn Expression>,
+    from: f64,
+    to: f64,
+    eps: f64,
+    max_iter_count: usize,
+    method: RootMethod,
+}
+
+impl Problem for RootFindProblem {
+    fn solve(&self) -> Solution {
+        let f = |x| self.f.eval(&DefaultRuntime::new(&[("x", x)]));
+        let zero = |_: f64| -> Result<f64, Error> { Ok(0.0) };
+        let mut notes = vec![];
+
+        let derivative = match self.method {
+            RootMethod::Secant => None,
+            RootMethod::Newton => match self.f.derivative("x") {
+                Ok(df) => Some(df),
+                Err(e) => {
+                    notes.push(SolutionParagraph::Text(format!(
+                        "No derivative of f ({:?}), used the secant method",
+                        e
+                    )));
+                    None
+                }
+            },
+        };
+        let res = match derivative {
+            Some(df) => newton_root(
+                &f,
+                &|x| df.eval(&DefaultRuntime::new(&[("x", x)])),
+                (self.from + self.to) / 2.0,
+                [self.from, self.to],
+                self.eps,
+                self.max_iter_count.into(),
+            ),
+            None => root(
+                &f,
+                &zero,
+                self.from,
+                self.to,
+                self.eps,
+                self.max_iter_count.into(),
+            )
+            .map(|(x, _)| x),
+        };
+
+        match res {
+            Ok(x) => {
+                let mut explanation = notes;
+                explanation.extend([
+                    SolutionParagraph::Latex(format!(
+                        "{{{}}}=0",
+                        self.f
+                            .to_latex(&DefaultRuntime::default())
+                            .unwrap_or_else(|_| String::new())
+                    )),
+                    SolutionParagraph::Text(format!("Root at x = {x:.8}")),
+                ]);
+
+                let graph = plot_points(&f, self.from, self.to)
+                    .map_err(|e| format!("{:?}", e))
+                    .and_then(|pts| {
+                        Graph::new(vec![
+                            Path {
+                                pts: vec![(self.from, 0.0), (self.to, 0.0)],
+                                kind: PathKind::Line,
+                                color: (0.0, 0.0, 0.0),
+                                label: Some("y = 0".to_string()),
+                            },
+                            Path {
+                                pts,
+                                kind: PathKind::Line,
+                                color: (1.0, 0.0, 0.0),
+                                label: Some("f".to_string()),
+                            },
+                            Path {
+                                pts: vec![(x, 0.0)],
+                                kind: PathKind::Dot,
+                                color: (0.0, 0.0, 1.0),
+                                label: Some("root".to_string()),
+                            },
+                        ])
+                        .ok_or_else(|| "Could not create graph".to_string())
+                    });
+                explanation.push(match graph {
+                    Ok(g) => SolutionParagraph::Graph(g),
+                    Err(e) => SolutionParagraph::RuntimeError(e),
+                });
+
+                Solution {
+                    explanation,
+                    values: vec![("root".to_string(), x)],
+                }
+            }
+            Err(e) => Solution {
+                explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                values: vec![],
+            },
+        }
+    }
+}
+
+pub struct RootFindProblemCreator {
+    form: Form,
+}
+
+impl Default for RootFindProblemCreator {
+    fn default() -> Self {
+        let mut form = Form::new(vec![
+            "f".to_string(),
+            "from".to_string(),
+            "to".to_string(),
+            "eps".to_string(),
+            "max_iter_count".to_string(),
+            "method".to_string(),
+        ]);
+
+        form.set("f", "cos(x)-x".to_string());
+        form.set("from", "0".to_string());
+        form.set("to", "1".to_string());
+        form.set("eps", "1e-10".to_string());
+        form.set("max_iter_count", "1000".to_string());
+        form.set("method", "secant".to_string());
+
+        Self { form }
+    }
+}
+
+impl ProblemCreator for RootFindProblemCreator {
+    fn fields(&self) -> super::form::FieldsIter {
+        self.form.get_fields()
+    }
+
+    fn set_field(&mut self, name: &str, val: String) {
+        self.form.set(name, val)
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn try_create(&self, runtime: &dyn Runtime) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
+        let mut f = None;
+        let mut from = None;
+        let mut to = None;
+        let mut eps = None;
+        let mut max_iter_count = None;
+        let mut method = None;
+
+        let mut errors = vec![];
+        for (name, val) in self.form.get_fields() {
+            let res = match name {
+                "f" => validate_expr(name, val, Some(&["x"]), runtime, &mut f),
+                "from" => validate_from_str::<f64>(name, val, &mut from),
+                "to" => validate_from_str::<f64>(name, val, &mut to),
+                "eps" => validate_positive::<f64>(name, val, &mut eps),
+                "max_iter_count" => validate_positive::<usize>(name, val, &mut max_iter_count),
+                "method" => validate_from_str::<RootMethod>(name, val, &mut method),
+                _ => Err(ValidationError(format!(
+                    "{name} - no such field (probably a devs error)"
+                ))),
+            };
+
+            match res {
+                Ok(_) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+
+        let f =
+            f.ok_or_else(|| errors.push(ValidationError("field was not supplied: f".to_string())));
+        let from = from.ok_or_else(|| {
+            errors.push(ValidationError("field was not supplied: from".to_string()))
+        });
+        let to = to
+            .ok_or_else(|| errors.push(ValidationError("field was not supplied: to".to_string())));
+        let eps = eps
+            .ok_or_else(|| errors.push(ValidationError("field was not supplied: eps".to_string())));
+        let max_iter_count = max_iter_count.ok_or_else(|| {
+            errors.push(ValidationError(
+                "field was not supplied: max_iter_count".to_string(),
+            ))
+        });
+
+        if errors.is_empty() {
+            Ok(Box::new(RootFindProblem {
+                f: f.unwrap(),
+                from: from.unwrap(),
+                to: to.unwrap(),
+                eps: eps.unwrap(),
+                max_iter_count: max_iter_count.unwrap(),
+                method: method.unwrap_or(RootMethod::Secant),
+            }))
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[test]
+fn root_find_problem() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = RootFindProblemCreator::default();
+    creator.set_field("f", "x^2-2".to_string());
+    creator.set_field("to", "2".to_string());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    let (_, x) = solution.values[0];
+    assert!((x - std::f64::consts::SQRT_2).abs() < 1e-8, "{x}");
+    let graph = solution.graphs().next().unwrap();
+    assert_eq!(graph.paths[2].pts, vec![(x, 0.0)]);
+
+    // x^2 - 2 has the same sign on both ends
+    creator.set_field("from", "-2".to_string());
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    assert!(solution.values.is_empty());
+}
+
+#[test]
+fn root_find_newton() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = RootFindProblemCreator::default();
+    creator.set_field("method", "newton".to_string());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    let (_, x) = solution.values[0];
+    assert!((x - 0.739085133215161).abs() < 1e-10, "{x}");
+    assert_eq!(solution.texts().count(), 1);
+
+    // no derivative for %, the secant method takes over
+    creator.set_field("f", "x%2-0.5".to_string());
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    let (_, x) = solution.values[0];
+    assert!((x - 0.5).abs() < 1e-9, "{x}");
+    assert!(solution
+        .texts()
+        .next()
+        .unwrap()
+        .starts_with("No derivative"));
+
+    creator.set_field("method", "bisection".to_string());
+    assert!(creator.try_create(&runtime).is_err());
+}
@@ -1,49 +1,87 @@
-use std::{fs::File, io::Write, path::Path as FilePath};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path as FilePath, PathBuf},
+};
 
 use crate::{
     functions::{function::Function, table_function::TableFunction},
-    spline::Spline,
+    mathparse::Runtime,
+    spline::{Pchip, Spline, SplineMethod},
 };
 
 use super::{
+    chebyshev_resample, default_output_dir,
     form::Form,
     graph::{Graph, Path},
-    Problem, ProblemCreator, Solution, SolutionParagraph, ValidationError,
+    resolve_output_path, validate_from_str, Problem, ProblemCreator, Solution, SolutionParagraph,
+    ValidationError,
 };
 
 struct SplieProblem {
     src_file: String,
-    dest_file: String,
+    dest_file: PathBuf,
+    x_scale: f64,
+    x_offset: f64,
+    y_scale: f64,
+    y_offset: f64,
+    lambda: f64,
+    method: SplineMethod,
+    chebyshev_n: usize,
+}
+
+impl SplieProblem {
+    fn transform(&self, table: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+        table
+            .into_iter()
+            .map(|(x, y)| {
+                (
+                    x * self.x_scale + self.x_offset,
+                    y * self.y_scale + self.y_offset,
+                )
+            })
+            .collect()
+    }
 }
 
 impl Problem for SplieProblem {
     fn solve(&self) -> super::Solution {
-        let func = TableFunction::from_file(FilePath::new(&self.src_file));
+        let func = TableFunction::from_file(FilePath::new(&self.src_file))
+            .map_err(|e| format!("{:?}", e))
+            .and_then(|func| {
+                chebyshev_resample(self.transform(func.into_table()), self.chebyshev_n)
+            })
+            .map(TableFunction::from_table);
         let dest_file = File::create(&self.dest_file);
         let res = func
-            .map_err(|e| format!("{:?}", e))
             .and_then(|func| {
                 dest_file
                     .map_err(|e| format!("{:?}", e))
                     .map(|dest| (func, dest))
             })
             .and_then(|(func, mut dest)| {
-                let spline = Spline::new(func.to_table());
-                spline
-                    .write_coefs()
+                let (from, to) = (func.min_x(), func.max_x());
+                let table = func.into_table();
+                let (min, max) = match (from, to) {
+                    (Some(min), Some(max)) => (min, max),
+                    _ => return Err("No points given".to_string()),
+                };
+                let (coefs, pts) = match self.method {
+                    SplineMethod::Cubic => {
+                        let spline = Spline::smoothing(table.clone(), self.lambda)
+                            .map_err(|e| format!("{:?}", e))?;
+                        (spline.write_coefs(), spline.sample(min, max, 50))
+                    }
+                    SplineMethod::Pchip => {
+                        let pchip = Pchip::new(table.clone()).map_err(|e| format!("{:?}", e))?;
+                        (pchip.write_coefs(), pchip.sample(min, max, 50))
+                    }
+                };
+                coefs
                     .map_err(|e| format!("{:?}", e))
-                    .and_then(|coefs| write!(dest, "{}", coefs).map_err(|e| format!("{:?}", e)))
-                    .map(|()| (func.to_table(), spline, func.min_x(), func.max_x()))
-            })
-            .and_then(|(table, spline, from, to)| {
-                if let (Some(min), Some(max)) = (from, to) {
-                    spline
-                        .sample(min, max, 50)
-                        .map_err(|e| format!("{:?}", e))
-                        .map(|spline| (table, spline))
-                } else {
-                    Err("No points given".to_string())
-                }
+                    .and_then(|coefs| write!(dest, "{}", coefs).map_err(|e| format!("{:?}", e)))?;
+                pts.map_err(|e| format!("{:?}", e))
+                    .map(|spline| (table, spline))
             })
             .and_then(|(table_pts, spline_pts)| {
                 Graph::new(vec![
@@ -51,11 +89,13 @@ impl Problem for SplieProblem {
                         pts: spline_pts,
                         kind: super::graph::PathKind::Line,
                         color: (1.0, 0.0, 0.0),
+                        label: None,
                     },
                     Path {
                         pts: table_pts,
                         kind: super::graph::PathKind::Dot,
                         color: (0.0, 0.0, 1.0),
+                        label: None,
                     },
                 ])
                 .ok_or_else(|| "Could not create graph".to_string())
@@ -66,13 +106,16 @@ impl Problem for SplieProblem {
                 explanation: vec![
                     SolutionParagraph::Text(format!(
                         "{} saved in {}",
-                        self.src_file, self.dest_file
+                        self.src_file,
+                        self.dest_file.display()
                     )),
                     SolutionParagraph::Graph(res),
                 ],
+                values: vec![],
             },
             Err(e) => Solution {
                 explanation: vec![SolutionParagraph::RuntimeError(e)],
+                values: vec![],
             },
         }
     }
@@ -80,15 +123,36 @@ impl Problem for SplieProblem {
 
 pub struct SplineProblemCreator {
     form: Form,
+    output_dir: PathBuf,
 }
 
 impl Default for SplineProblemCreator {
     fn default() -> Self {
-        let mut form = Form::new(vec!["src_file".to_string(), "dest_file".to_string()]);
+        let mut form = Form::new(vec![
+            "src_file".to_string(),
+            "dest_file".to_string(),
+            "x_scale".to_string(),
+            "x_offset".to_string(),
+            "y_scale".to_string(),
+            "y_offset".to_string(),
+            "lambda".to_string(),
+            "method".to_string(),
+            "chebyshev_n".to_string(),
+        ]);
         form.set("src_file", "pts.csv".to_string());
         form.set("dest_file", "spline.csv".to_string());
+        form.set("x_scale", "1".to_string());
+        form.set("x_offset", "0".to_string());
+        form.set("y_scale", "1".to_string());
+        form.set("y_offset", "0".to_string());
+        form.set("lambda", "0".to_string());
+        form.set("method", "cubic".to_string());
+        form.set("chebyshev_n", "0".to_string());
 
-        Self { form }
+        Self {
+            form,
+            output_dir: default_output_dir(),
+        }
     }
 }
 
@@ -101,21 +165,63 @@ impl ProblemCreator for SplineProblemCreator {
         self.form.set(name, val)
     }
 
-    fn try_create(&self) -> Result<Box<dyn Problem>, Vec<super::ValidationError>> {
+    fn reset(&mut self) {
+        self.form = Self::default().form;
+    }
+
+    fn set_output_dir(&mut self, dir: &FilePath) {
+        self.output_dir = dir.to_path_buf();
+    }
+
+    fn try_create(
+        &self,
+        _runtime: &dyn Runtime,
+    ) -> Result<Box<dyn Problem>, Vec<super::ValidationError>> {
         let mut src_file = None;
         let mut dest_file = None;
+        let mut x_scale = None;
+        let mut x_offset = None;
+        let mut y_scale = None;
+        let mut y_offset = None;
+        let mut lambda = None;
+        let mut method = None;
+        let mut chebyshev_n = None;
 
         let mut errors = vec![];
         for (name, val) in self.form.get_fields() {
-            match name {
-                "src_file" => src_file = Some(val),
-                "dest_file" => dest_file = Some(val),
-                _ => errors.push(ValidationError(format!(
+            let res = match name {
+                "src_file" => {
+                    src_file = Some(val);
+                    Ok(())
+                }
+                "dest_file" => {
+                    dest_file = Some(val);
+                    Ok(())
+                }
+                "x_scale" => validate_from_str::<f64>(name, val, &mut x_scale),
+                "x_offset" => validate_from_str::<f64>(name, val, &mut x_offset),
+                "y_scale" => validate_from_str::<f64>(name, val, &mut y_scale),
+                "y_offset" => validate_from_str::<f64>(name, val, &mut y_offset),
+                "lambda" => validate_from_str::<f64>(name, val, &mut lambda),
+                "method" => validate_from_str::<SplineMethod>(name, val, &mut method),
+                "chebyshev_n" => validate_from_str::<usize>(name, val, &mut chebyshev_n),
+                _ => Err(ValidationError(format!(
                     "{name} - no such field (probably a devs error)"
                 ))),
+            };
+
+            match res {
+                Ok(_) => {}
+                Err(e) => errors.push(e),
             }
         }
 
+        if method == Some(SplineMethod::Pchip) && matches!(lambda, Some(l) if l != 0.0) {
+            errors.push(ValidationError(
+                "lambda - only the cubic spline can be smoothed".to_string(),
+            ));
+        }
+
         let src_file = src_file.ok_or_else(|| {
             errors.push(ValidationError(
                 "field was not supplied - src_file".to_string(),
@@ -130,10 +236,42 @@ impl ProblemCreator for SplineProblemCreator {
         if errors.is_empty() {
             Ok(Box::new(SplieProblem {
                 src_file: src_file.unwrap().to_string(),
-                dest_file: dest_file.unwrap().to_string(),
+                dest_file: resolve_output_path(&self.output_dir, dest_file.unwrap()),
+                x_scale: x_scale.unwrap_or(1.0),
+                x_offset: x_offset.unwrap_or(0.0),
+                y_scale: y_scale.unwrap_or(1.0),
+                y_offset: y_offset.unwrap_or(0.0),
+                lambda: lambda.unwrap_or(0.0),
+                method: method.unwrap_or(SplineMethod::Cubic),
+                chebyshev_n: chebyshev_n.unwrap_or(0),
             }))
         } else {
             Err(errors)
         }
     }
 }
+
+#[test]
+fn spline_transform() -> Result<(), crate::spline::Error> {
+    let problem = SplieProblem {
+        src_file: String::new(),
+        dest_file: PathBuf::new(),
+        x_scale: 1.0,
+        x_offset: 0.0,
+        y_scale: 2.0,
+        y_offset: 0.0,
+        lambda: 0.0,
+        method: SplineMethod::Cubic,
+        chebyshev_n: 0,
+    };
+
+    let pts = vec![(-1.0, 0.0), (-0.5, 1.0), (0.5, -1.0), (1.0, 0.0)];
+    let spline = Spline::new(pts.clone())?;
+    let scaled = Spline::new(problem.transform(pts))?;
+
+    for (x, y) in spline.sample(-1.0, 1.0, 20)? {
+        assert!((scaled.apply(x)? - 2.0 * y).abs() < 1e-9);
+    }
+
+    Ok(())
+}
@@ -1,14 +1,15 @@
 use crate::{
     integral_eq::volterra_second_kind::volterra_2nd_system,
-    mathparse::{DefaultRuntime, Expression},
+    mathparse::{DefaultRuntime, Expression, PositionalRuntime, Runtime},
 };
-use std::{fs::File, io::Write};
+use std::path::{Path as FilePath, PathBuf};
 
 use super::{
+    default_output_dir,
     form::Form,
     graph::{Graph, Path, PathKind},
-    validate_expr, validate_from_str, Problem, ProblemCreator, Solution, SolutionParagraph,
-    ValidationError,
+    kernel_heatmap, resolve_output_path, shape_summary, validate_expr, validate_from_str,
+    validate_positive, Problem, ProblemCreator, Solution, SolutionParagraph, ValidationError,
 };
 
 struct Volterra2ndProblem {
@@ -18,16 +19,27 @@ struct Volterra2ndProblem {
     to: f64,
     lambda: f64,
     n: usize,
-    dest_file: String,
+    dest_file: PathBuf,
 }
 
 impl Problem for Volterra2ndProblem {
     fn solve(&self) -> Solution {
+        let compiled_kernel = match self.kernel.compile(&DefaultRuntime::default()) {
+            Ok(kernel) => kernel,
+            Err(e) => {
+                return Solution {
+                    explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                    values: vec![],
+                }
+            }
+        };
+        let kernel_runtime = PositionalRuntime::new(&["x", "s"]);
+        let kernel = |x, s| {
+            kernel_runtime.set(&[x, s]);
+            compiled_kernel.eval(&kernel_runtime)
+        };
         let res = volterra_2nd_system(
-            &|x, s| {
-                self.kernel
-                    .eval(&DefaultRuntime::new(&[("x", x), ("s", s)]))
-            },
+            &kernel,
             &|x| self.right_side.eval(&DefaultRuntime::new(&[("x", x)])),
             self.from,
             self.to,
@@ -49,22 +61,20 @@ impl Problem for Volterra2ndProblem {
                     solution.push(latex);
                 }
 
-                let pts = res.to_table();
-                let write_res = match File::create(&self.dest_file) {
-                    Ok(mut file) => pts
-                        .iter()
-                        .try_for_each(|(x, y)| writeln!(file, "{},{}", x, y)),
-                    Err(e) => Err(e),
-                };
+                let write_res = res.to_file(&self.dest_file);
+                let pts = res.into_table();
 
                 let _ = write_res.map_err(|e| {
                     solution.push(SolutionParagraph::RuntimeError(format!("{:?}", e)))
                 });
 
+                solution.push(shape_summary(&pts));
+                solution.push(kernel_heatmap(&kernel, self.from, self.to));
                 match Graph::new(vec![Path {
                     pts,
                     kind: PathKind::Line,
                     color: (1.0, 0.0, 0.0),
+                    label: None,
                 }]) {
                     Some(g) => solution.push(SolutionParagraph::Graph(g)),
                     None => solution.push(SolutionParagraph::RuntimeError(
@@ -74,10 +84,12 @@ impl Problem for Volterra2ndProblem {
 
                 Solution {
                     explanation: solution,
+                    values: vec![],
                 }
             }
             Err(e) => Solution {
                 explanation: vec![SolutionParagraph::RuntimeError(format!("{:?}", e))],
+                values: vec![],
             },
         }
     }
@@ -85,6 +97,7 @@ impl Problem for Volterra2ndProblem {
 
 pub struct Volterra2ndProblemCreator {
     form: Form,
+    output_dir: PathBuf,
 }
 
 impl Default for Volterra2ndProblemCreator {
@@ -107,12 +120,15 @@ impl Default for Volterra2ndProblemCreator {
         form.set("n", "50".to_string());
         form.set("dest_file", "y.csv".to_string());
 
-        Self { form }
+        Self {
+            form,
+            output_dir: default_output_dir(),
+        }
     }
 }
 
 impl ProblemCreator for Volterra2ndProblemCreator {
-    fn try_create(&self) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
+    fn try_create(&self, runtime: &dyn Runtime) -> Result<Box<dyn Problem>, Vec<ValidationError>> {
         let mut kernel = None;
         let mut right_side = None;
         let mut from = None;
@@ -123,23 +139,11 @@ impl ProblemCreator for Volterra2ndProblemCreator {
         let mut errors = vec![];
         for (name, val) in self.form.get_fields() {
             let res = match name {
-                "kernel" => validate_expr(
-                    name,
-                    val,
-                    Some(&["x", "s"]),
-                    &DefaultRuntime::default(),
-                    &mut kernel,
-                ),
-                "right_side" => validate_expr(
-                    name,
-                    val,
-                    Some(&["x"]),
-                    &DefaultRuntime::default(),
-                    &mut right_side,
-                ),
+                "kernel" => validate_expr(name, val, Some(&["x", "s"]), runtime, &mut kernel),
+                "right_side" => validate_expr(name, val, Some(&["x"]), runtime, &mut right_side),
                 "from" => validate_from_str::<f64>(name, val, &mut from),
                 "to" => validate_from_str::<f64>(name, val, &mut to),
-                "n" => validate_from_str::<usize>(name, val, &mut n),
+                "n" => validate_positive::<usize>(name, val, &mut n),
                 "lambda" => validate_from_str::<f64>(name, val, &mut lambda),
                 "dest_file" => Ok(()),
                 _ => Err(ValidationError(format!(
@@ -153,6 +157,10 @@ impl ProblemCreator for Volterra2ndProblemCreator {
             }
         }
 
+        if matches!(n, Some(n) if n < 2) {
+            errors.push(ValidationError("n - need at least 2 points".to_string()));
+        }
+
         if !errors.is_empty() {
             return Err(errors);
         }
@@ -193,7 +201,7 @@ impl ProblemCreator for Volterra2ndProblemCreator {
                 to: to.unwrap(),
                 n: n.unwrap(),
                 lambda: lambda.unwrap(),
-                dest_file: dest_file.cloned().unwrap(),
+                dest_file: resolve_output_path(&self.output_dir, dest_file.unwrap()),
             }))
         } else {
             Err(errors)
@@ -207,4 +215,74 @@ impl ProblemCreator for Volterra2ndProblemCreator {
     fn set_field(&mut self, name: &str, val: String) {
         self.form.set(name, val)
     }
+
+    fn reset(&mut self) {
+        self.form = Self::default().form;
+    }
+
+    fn set_output_dir(&mut self, dir: &FilePath) {
+        self.output_dir = dir.to_path_buf();
+    }
+}
+
+#[test]
+fn kernel_heatmap_paragraph() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = Volterra2ndProblemCreator::default();
+    creator.set_output_dir(&std::env::temp_dir());
+    creator.set_field("dest_file", "prac_volterra_heatmap_test.csv".to_string());
+    creator.set_field("from", "-1".to_string());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    assert_eq!(solution.errors().count(), 0);
+    let (x, y, z, from, to) = solution
+        .explanation
+        .iter()
+        .find_map(|p| match p {
+            SolutionParagraph::Heatmap { x, y, z, from, to } => Some((x, y, z, *from, *to)),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!((from, to), (-1.0, 1.0));
+    assert_eq!((x.len(), y.len(), z.len()), (40, 40, 40));
+    assert!(z.iter().all(|row| row.len() == x.len()));
+    // exp(x - s) at the corners
+    assert!((z[0][x.len() - 1] - 2f64.exp()).abs() < 1e-12);
+    assert!((z[y.len() - 1][0] - (-2f64).exp()).abs() < 1e-12);
+
+    std::fs::remove_file(std::env::temp_dir().join("prac_volterra_heatmap_test.csv")).unwrap();
+}
+
+#[test]
+fn kernel_heatmap_partial() {
+    let runtime = DefaultRuntime::default();
+    let mut creator = Volterra2ndProblemCreator::default();
+    creator.set_output_dir(&std::env::temp_dir());
+    creator.set_field("dest_file", "prac_volterra_partial_test.csv".to_string());
+    // only defined for s <= x, which is all the equation needs
+    creator.set_field("kernel", "sqrt(x-s)".to_string());
+
+    let solution = creator.try_create(&runtime).ok().unwrap().solve();
+    std::fs::remove_file(std::env::temp_dir().join("prac_volterra_partial_test.csv")).unwrap();
+
+    assert_eq!(solution.errors().count(), 0);
+    let z = solution
+        .explanation
+        .iter()
+        .find_map(|p| match p {
+            SolutionParagraph::Heatmap { z, .. } => Some(z),
+            _ => None,
+        })
+        .unwrap();
+    let n = z.len();
+    // z[j][i] is at (x[i], s[j])
+    assert_eq!(z[0][n - 1], 1.0);
+    assert!(z[n - 1][0].is_nan());
+    assert!(z.iter().all(|row| row.len() == n));
+
+    let nowhere = |_: f64, _: f64| -> Result<f64, String> { Err("no".to_string()) };
+    assert!(matches!(
+        super::kernel_heatmap(&nowhere, 0.0, 1.0),
+        SolutionParagraph::RuntimeError(_)
+    ));
 }
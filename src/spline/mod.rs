@@ -1,11 +1,29 @@
-use crate::functions::function::Function;
-use std::fmt::Write;
+use crate::{
+    functions::function::Function,
+    linalg::{lu_solve, thomas_solve},
+};
+use std::{fmt::Write, str::FromStr};
+
+mod pchip;
+
+pub use pchip::Pchip;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     Io(String),
-    PointOutOfBounds { x: f64, min: f64, max: f64 },
+    PointOutOfBounds {
+        x: f64,
+        min: f64,
+        max: f64,
+    },
     NoKnownPoints,
+    /// The spline equations have no unique solution, e.g. two points share an `x`.
+    SingularSystem(String),
+    /// `Spline::from_parts` needs one set of coefficients per segment.
+    CoefCountMismatch {
+        expected: usize,
+        got: usize,
+    },
 }
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
@@ -19,17 +37,171 @@ impl From<std::fmt::Error> for Error {
     }
 }
 
+/// What the spline returns outside of its knots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SplineExtrapolation {
+    Error,
+    /// Continues the cubic of the nearest segment.
+    Extend,
+    /// Continues the nearest cubic, but keeps it within the y-range of the data.
+    ClampToDataRange,
+}
+
+/// Which interpolant to build through the knots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplineMethod {
+    Cubic,
+    /// Monotone, see `Pchip`.
+    Pchip,
+}
+
+impl FromStr for SplineMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "cubic" => Ok(SplineMethod::Cubic),
+            "pchip" => Ok(SplineMethod::Pchip),
+            _ => Err(format!("{s} - expected cubic or pchip")),
+        }
+    }
+}
+
+/// End conditions of an interpolating spline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplineBoundary {
+    /// Zero second derivative at both ends.
+    Natural,
+    /// Given first derivatives at the ends.
+    Clamped { left: f64, right: f64 },
+    /// The first two and the last two segments are the same cubic.
+    NotAKnot,
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SplineParts")
+)]
 pub struct Spline {
     pts: Vec<(f64, f64)>,
     coefs: Vec<(f64, f64, f64, f64)>,
+    extrapolation: SplineExtrapolation,
 }
 
 impl Spline {
-    pub fn new(known_points: Vec<(f64, f64)>) -> Self {
-        Self {
-            coefs: calc_spline_params(&known_points),
+    pub fn new(known_points: Vec<(f64, f64)>) -> Result<Self, Error> {
+        Self::with_boundary(known_points, SplineBoundary::Natural)
+    }
+
+    pub fn with_boundary(
+        known_points: Vec<(f64, f64)>,
+        boundary: SplineBoundary,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            coefs: calc_spline_params(&known_points, boundary)?,
             pts: known_points,
+            extrapolation: SplineExtrapolation::Error,
+        })
+    }
+
+    /// Natural smoothing spline, minimizes `sum (y_i - s(x_i))^2 + lambda * int s''^2`.
+    /// `lambda = 0` interpolates, larger values give a smoother curve further from the points.
+    pub fn smoothing(known_points: Vec<(f64, f64)>, lambda: f64) -> Result<Self, Error> {
+        if lambda == 0.0 || known_points.len() < 3 {
+            return Self::new(known_points);
+        }
+
+        check_knots(&known_points)?;
+        let pts = smoothed_values(&known_points, lambda)?;
+        Self::new(pts)
+    }
+
+    /// Rebuilds a spline from its knots and the `a + bx + cx^2 + dx^3` of every segment,
+    /// e.g. as saved by `write_coefs`. The knots must be sorted by x.
+    pub fn from_parts(
+        pts: Vec<(f64, f64)>,
+        coefs: Vec<(f64, f64, f64, f64)>,
+    ) -> Result<Self, Error> {
+        check_knots(&pts)?;
+        if coefs.len() != pts.len() - 1 {
+            return Err(Error::CoefCountMismatch {
+                expected: pts.len() - 1,
+                got: coefs.len(),
+            });
+        }
+
+        Ok(Self {
+            pts,
+            coefs,
+            extrapolation: SplineExtrapolation::Error,
+        })
+    }
+
+    pub fn with_extrapolation(mut self, extrapolation: SplineExtrapolation) -> Self {
+        self.extrapolation = extrapolation;
+        self
+    }
+
+    pub fn derivative(&self, x: f64) -> Result<f64, Error> {
+        let (_, b, c, d) = self.segment_coefs(x)?;
+        Ok(3.0 * d * x * x + 2.0 * c * x + b)
+    }
+
+    pub fn second_derivative(&self, x: f64) -> Result<f64, Error> {
+        let (_, _, c, d) = self.segment_coefs(x)?;
+        Ok(6.0 * d * x + 2.0 * c)
+    }
+
+    /// Exact integral of the spline, `from` and `to` must be within the knots.
+    pub fn integrate(&self, from: f64, to: f64) -> Result<f64, Error> {
+        if from > to {
+            return self.integrate(to, from).map(|s| -s);
+        }
+        for x in [from, to] {
+            self.segment_coefs(x)?;
+        }
+
+        let antiderivative = |(a, b, c, d): (f64, f64, f64, f64), x: f64| {
+            ((((d / 4.0) * x + c / 3.0) * x + b / 2.0) * x + a) * x
+        };
+        Ok(self
+            .pts
+            .windows(2)
+            .zip(self.coefs.iter())
+            .filter(|(w, _)| w[1].0 > from && w[0].0 < to)
+            .map(|(w, coefs)| {
+                antiderivative(*coefs, w[1].0.min(to)) - antiderivative(*coefs, w[0].0.max(from))
+            })
+            .sum())
+    }
+
+    /// Index of the first segment that contains `x`.
+    fn segment(&self, x: f64) -> Option<usize> {
+        let i = self.pts.partition_point(|(px, _)| *px < x).max(1);
+        if i < self.pts.len() && self.pts[i - 1].0 <= x {
+            Some(i - 1)
+        } else {
+            None
+        }
+    }
+
+    fn segment_coefs(&self, x: f64) -> Result<(f64, f64, f64, f64), Error> {
+        if self.pts.is_empty() {
+            return Err(Error::NoKnownPoints);
+        }
+        self.segment(x)
+            .map(|i| self.coefs[i])
+            .ok_or_else(|| self.out_of_bounds(x))
+    }
+
+    fn out_of_bounds(&self, x: f64) -> Error {
+        Error::PointOutOfBounds {
+            x,
+            min: self.pts.first().cloned().unwrap_or_default().0,
+            max: self.pts.last().cloned().unwrap_or_default().0,
         }
     }
 
@@ -55,6 +227,25 @@ impl Spline {
     }
 }
 
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SplineParts {
+    pts: Vec<(f64, f64)>,
+    coefs: Vec<(f64, f64, f64, f64)>,
+    extrapolation: SplineExtrapolation,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SplineParts> for Spline {
+    type Error = String;
+
+    fn try_from(parts: SplineParts) -> Result<Self, Self::Error> {
+        Spline::from_parts(parts.pts, parts.coefs)
+            .map(|s| s.with_extrapolation(parts.extrapolation))
+            .map_err(|e| format!("{:?}", e))
+    }
+}
+
 impl Function for Spline {
     type Error = Error;
 
@@ -63,26 +254,116 @@ impl Function for Spline {
             return Err(Error::NoKnownPoints);
         }
 
-        for i in 1..self.pts.len() {
-            let (cur_x, _) = self.pts[i];
-            let (prev_x, _) = self.pts[i - 1];
+        if let Some(i) = self.segment(x) {
+            let (a, b, c, d) = self.coefs[i];
+            let val = d * x * x * x + c * x * x + b * x + a;
+            return Ok(val);
+        }
+
+        let out_of_bounds = self.out_of_bounds(x);
+        let (a, b, c, d) = match (self.coefs.first(), self.coefs.last()) {
+            (Some(first), _) if x < self.pts[0].0 => *first,
+            (_, Some(last)) => *last,
+            _ => return Err(out_of_bounds),
+        };
+        let val = d * x * x * x + c * x * x + b * x + a;
 
-            if prev_x <= x && cur_x >= x {
-                let (a, b, c, d) = self.coefs[i - 1];
-                let val = d * x * x * x + c * x * x + b * x + a;
-                return Ok(val);
+        match self.extrapolation {
+            SplineExtrapolation::Error => Err(out_of_bounds),
+            SplineExtrapolation::Extend => Ok(val),
+            SplineExtrapolation::ClampToDataRange => {
+                let min = self
+                    .pts
+                    .iter()
+                    .map(|(_, y)| *y)
+                    .fold(f64::INFINITY, f64::min);
+                let max = self
+                    .pts
+                    .iter()
+                    .map(|(_, y)| *y)
+                    .fold(f64::NEG_INFINITY, f64::max);
+                Ok(val.clamp(min, max))
             }
         }
+    }
+}
 
-        Err(Error::PointOutOfBounds {
-            x,
-            min: self.pts.first().cloned().unwrap_or_default().0,
-            max: self.pts.last().cloned().unwrap_or_default().0,
-        })
+/// Values of the smoothing spline at the knots (Reinsch):
+/// `(R + lambda Q^T Q) gamma = Q^T y`, then `g = y - lambda Q gamma`.
+/// The knots of a natural spline through the returned points are the smoothing spline.
+fn smoothed_values(pts: &[(f64, f64)], lambda: f64) -> Result<Vec<(f64, f64)>, Error> {
+    let n = pts.len();
+    let m = n - 2;
+    let h = pts.windows(2).map(|w| w[1].0 - w[0].0).collect::<Vec<_>>();
+
+    // column j of Q is nonzero in rows j, j + 1, j + 2
+    let q = (0..m)
+        .map(|j| [1.0 / h[j], -1.0 / h[j] - 1.0 / h[j + 1], 1.0 / h[j + 1]])
+        .collect::<Vec<_>>();
+
+    let mut a = (0..m * m).map(|_| 0.0).collect::<Vec<_>>();
+    for j in 0..m {
+        a[j * m + j] = (h[j] + h[j + 1]) / 3.0;
+        if j + 1 < m {
+            a[j * m + j + 1] = h[j + 1] / 6.0;
+            a[(j + 1) * m + j] = h[j + 1] / 6.0;
+        }
+        for k in j..m.min(j + 3) {
+            // columns j and k share the rows k..=j + 2
+            let qtq = (k..=j + 2)
+                .map(|row| q[j][row - j] * q[k][row - k])
+                .sum::<f64>();
+            a[j * m + k] += lambda * qtq;
+            if k != j {
+                a[k * m + j] += lambda * qtq;
+            }
+        }
+    }
+
+    let qty = q
+        .iter()
+        .enumerate()
+        .map(|(j, col)| (0..3).map(|r| col[r] * pts[j + r].1).sum())
+        .collect::<Vec<f64>>();
+    let gamma = lu_solve(&a, &qty, m)
+        .map_err(|e| Error::SingularSystem(format!("smoothing system: {:?}", e)))?;
+
+    let mut g = pts.to_vec();
+    for (j, col) in q.iter().enumerate() {
+        for r in 0..3 {
+            g[j + r].1 -= lambda * col[r] * gamma[j];
+        }
+    }
+    Ok(g)
+}
+
+/// The spline needs at least two knots with strictly increasing `x`.
+fn check_knots(pts: &[(f64, f64)]) -> Result<(), Error> {
+    if pts.len() < 2 {
+        return Err(Error::SingularSystem(format!(
+            "need at least 2 points, got {}",
+            pts.len()
+        )));
+    }
+    match pts.windows(2).find(|w| w[1].0 <= w[0].0) {
+        Some(w) if w[1].0 == w[0].0 => Err(Error::SingularSystem(format!(
+            "x = {} appears more than once",
+            w[0].0
+        ))),
+        Some(w) => Err(Error::SingularSystem(format!(
+            "x = {} comes after x = {}, points must be sorted",
+            w[1].0, w[0].0
+        ))),
+        None => Ok(()),
     }
 }
 
-fn calc_spline_params(pts: &[(f64, f64)]) -> Vec<(f64, f64, f64, f64)> {
+/// Solves for the slopes at the knots, then converts every segment to `a + bx + cx^2 + dx^3`.
+fn calc_spline_params(
+    pts: &[(f64, f64)],
+    boundary: SplineBoundary,
+) -> Result<Vec<(f64, f64, f64, f64)>, Error> {
+    check_knots(pts)?;
     let n = pts.len();
     let mut b = (0..n).map(|_| 0.0).collect::<Vec<_>>();
     let mut d = (0..n).map(|_| 0.0).collect::<Vec<_>>();
@@ -101,34 +382,61 @@ fn calc_spline_params(pts: &[(f64, f64)]) -> Vec<(f64, f64, f64, f64)> {
         c[i] = mui;
     }
 
-    d[0] = 3.0 * (pts[1].1 - pts[0].1) / (pts[1].0 - pts[0].0);
-    d[n - 1] = 3.0 * (pts[n - 1].1 - pts[n - 2].1) / (pts[n - 1].0 - pts[n - 2].0);
-    b[0] = 2.0;
-    c[0] = 1.0;
-    a[n - 2] = 1.0;
-    b[n - 1] = 2.0;
-
-    let mut y = (0..n).map(|_| 0.0).collect::<Vec<_>>();
-    let mut alpha = (0..n).map(|_| 0.0).collect::<Vec<_>>();
-    let mut beta = (0..n).map(|_| 0.0).collect::<Vec<_>>();
+    let h = |i: usize| pts[i + 1].0 - pts[i].0;
+    let slope = |i: usize| (pts[i + 1].1 - pts[i].1) / h(i);
+    match boundary {
+        SplineBoundary::Natural => {
+            d[0] = 3.0 * slope(0);
+            d[n - 1] = 3.0 * slope(n - 2);
+            b[0] = 2.0;
+            c[0] = 1.0;
+            a[n - 2] = 1.0;
+            b[n - 1] = 2.0;
+        }
+        SplineBoundary::Clamped { left, right } => {
+            d[0] = left;
+            d[n - 1] = right;
+            b[0] = 1.0;
+            b[n - 1] = 1.0;
+        }
+        SplineBoundary::NotAKnot if n == 2 => {
+            d[0] = slope(0);
+            d[1] = slope(0);
+            b[0] = 1.0;
+            b[1] = 1.0;
+        }
+        // the only cubic through 3 points with one piece is the parabola
+        SplineBoundary::NotAKnot if n == 3 => {
+            let curvature = (slope(1) - slope(0)) / (h(0) + h(1));
+            d[0] = slope(0) - curvature * h(0);
+            d[2] = slope(0) + curvature * (h(0) + 2.0 * h(1));
+            b[0] = 1.0;
+            b[2] = 1.0;
+        }
+        SplineBoundary::NotAKnot => {
+            let (h0, h1) = (h(0), h(1));
+            b[0] = h1;
+            c[0] = h0 + h1;
+            d[0] = ((h0 + 2.0 * (h0 + h1)) * h1 * slope(0) + h0 * h0 * slope(1)) / (h0 + h1);
 
-    y[0] = b[0];
-    alpha[0] = -c[0] / y[0];
-    beta[0] = d[0] / y[0];
-    for i in 1..n - 1 {
-        y[i] = b[i] + a[i - 1] * alpha[i - 1];
-        alpha[i] = -c[i] / y[i];
-        beta[i] = (d[i] - a[i - 1] * beta[i - 1]) / y[i];
+            let (h0, h1) = (h(n - 3), h(n - 2));
+            a[n - 2] = h0 + h1;
+            b[n - 1] = h0;
+            d[n - 1] =
+                (h1 * h1 * slope(n - 3) + (2.0 * (h0 + h1) + h1) * h0 * slope(n - 2)) / (h0 + h1);
+        }
     }
 
-    let mut m = (0..n).map(|_| 0.0).collect::<Vec<_>>();
-    m[n - 1] = beta[n - 1];
-    for i in 1..n - 1 {
-        let j = n - i - 1;
-        m[j] = alpha[j] * m[j + 1] + beta[j];
+    let m = thomas_solve(&a, &b, &c, &d)
+        .map_err(|e| Error::SingularSystem(format!("spline system: {:?}", e)))?;
+    if let Some(i) = m.iter().position(|m| !m.is_finite()) {
+        return Err(Error::SingularSystem(format!(
+            "no finite slope at x = {}",
+            pts[i].0
+        )));
     }
 
-    (0..n - 1)
+    Ok((0..n - 1)
         .map(|i| {
             let a = pts[i].1;
             let b = pts[i + 1].1;
@@ -150,7 +458,7 @@ fn calc_spline_params(pts: &[(f64, f64)]) -> Vec<(f64, f64, f64, f64)> {
                 (2.0 * a - 2.0 * b) / div1 + (m + n) / div2,
             )
         })
-        .collect()
+        .collect())
 }
 
 #[test]
@@ -167,7 +475,7 @@ fn spline() -> Result<(), Error> {
         })
         .collect::<Vec<_>>();
 
-    let spline = Spline::new(pts);
+    let spline = Spline::new(pts)?;
     spline.write_coefs()?;
 
     let check_n = n * 10;
@@ -188,3 +496,246 @@ fn spline() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn spline_clamp() -> Result<(), Error> {
+    let pts = vec![(0.0, 0.0), (1.0, 2.0), (2.0, -1.0), (3.0, 1.0)];
+
+    let spline = Spline::new(pts.clone())?;
+    assert!(spline.apply(10.0).is_err());
+
+    let extended = Spline::new(pts.clone())?.with_extrapolation(SplineExtrapolation::Extend);
+    assert!(extended.apply(100.0)?.abs() > 2.0);
+    assert_eq!(extended.apply(1.5)?, spline.apply(1.5)?);
+
+    let clamped = Spline::new(pts)?.with_extrapolation(SplineExtrapolation::ClampToDataRange);
+    for x in [-1000.0, -5.0, 5.0, 1000.0] {
+        let y = clamped.apply(x)?;
+        assert!((-1.0..=2.0).contains(&y));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn spline_smoothing() -> Result<(), Error> {
+    let pts = (0..=40)
+        .map(|i| {
+            let x = (i as f64) * 0.25;
+            let noise = ((i * 7919) % 13) as f64 / 13.0 - 0.5;
+            (x, x.sin() + 0.3 * noise)
+        })
+        .collect::<Vec<_>>();
+
+    let roughness = |spline: &Spline| -> Result<f64, Error> {
+        let ys = spline.sample(0.0, 10.0, 400)?;
+        Ok(ys
+            .windows(3)
+            .map(|w| w[2].1 - 2.0 * w[1].1 + w[0].1)
+            .map(|d| d * d)
+            .sum())
+    };
+
+    let interpolating = Spline::new(pts.clone())?;
+    let zero = Spline::smoothing(pts.clone(), 0.0)?;
+    assert_eq!(zero, interpolating);
+
+    let mut prev = roughness(&interpolating)?;
+    for lambda in [0.01, 0.1, 1.0, 10.0] {
+        let smooth = Spline::smoothing(pts.clone(), lambda)?;
+        let cur = roughness(&smooth)?;
+        assert!(cur < prev);
+        prev = cur;
+    }
+
+    // a tiny lambda stays close to interpolation
+    let almost = Spline::smoothing(pts.clone(), 1e-9)?;
+    for (x, y) in &pts {
+        assert!((almost.apply(*x)? - y).abs() < 1e-6);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn spline_singular() {
+    let repeated = vec![(0.0, 0.0), (1.0, 1.0), (1.0, 2.0), (2.0, 0.0)];
+    assert!(matches!(
+        Spline::new(repeated.clone()),
+        Err(Error::SingularSystem(msg)) if msg.contains("x = 1")
+    ));
+    assert!(matches!(
+        Spline::smoothing(repeated, 0.5),
+        Err(Error::SingularSystem(_))
+    ));
+
+    let unsorted = vec![(0.0, 0.0), (2.0, 1.0), (1.0, 2.0)];
+    assert!(matches!(
+        Spline::new(unsorted),
+        Err(Error::SingularSystem(_))
+    ));
+    assert!(matches!(
+        Spline::new(vec![(0.0, 1.0)]),
+        Err(Error::SingularSystem(_))
+    ));
+}
+
+#[test]
+fn spline_between_knots() -> Result<(), Error> {
+    let step = 0.1;
+    let pts = (0..=100)
+        .map(|i| (i as f64 * step, (i as f64 * step).sin()))
+        .collect::<Vec<_>>();
+    let spline = Spline::new(pts)?;
+
+    for i in 0..100 {
+        let x = (i as f64 + 0.3) * step;
+        let diff = (spline.apply(x)? - x.sin()).abs();
+        // the natural end condition only hurts near x = 10, where sin'' is not 0
+        let eps = if i < 90 { 1e-5 } else { 1e-3 };
+        assert!(diff < eps, "at {x}: {diff}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn spline_boundary() -> Result<(), Error> {
+    let f = |x: f64| x * x * x - 2.0 * x;
+    let df = |x: f64| 3.0 * x * x - 2.0;
+    let pts = [-1.0, -0.2, 0.5, 1.0, 2.0]
+        .into_iter()
+        .map(|x| (x, f(x)))
+        .collect::<Vec<_>>();
+
+    let clamped = Spline::with_boundary(
+        pts.clone(),
+        SplineBoundary::Clamped {
+            left: df(-1.0),
+            right: df(2.0),
+        },
+    )?;
+    let not_a_knot = Spline::with_boundary(pts.clone(), SplineBoundary::NotAKnot)?;
+    let natural = Spline::with_boundary(pts.clone(), SplineBoundary::Natural)?;
+    assert_eq!(natural, Spline::new(pts)?);
+
+    // both reproduce a cubic exactly, the natural spline can not bend at the ends
+    for (x, y) in clamped.sample(-1.0, 2.0, 50)? {
+        assert!((y - f(x)).abs() < 1e-9, "clamped at {x}: {y}");
+        assert!(
+            (not_a_knot.apply(x)? - f(x)).abs() < 1e-9,
+            "not-a-knot at {x}"
+        );
+    }
+    assert!((natural.apply(1.5)? - f(1.5)).abs() > 1e-3);
+
+    let parabola = Spline::with_boundary(
+        vec![(0.0, 0.0), (1.0, 1.0), (3.0, 9.0)],
+        SplineBoundary::NotAKnot,
+    )?;
+    assert!((parabola.apply(2.0)? - 4.0).abs() < 1e-12);
+
+    Ok(())
+}
+
+#[test]
+fn spline_derivatives() -> Result<(), Error> {
+    let pts = (0..=40)
+        .map(|i| i as f64 * 0.1 - 2.0)
+        .map(|x| (x, x * x))
+        .collect::<Vec<_>>();
+    let spline = Spline::new(pts)?;
+
+    // the natural end condition bends the ends, so stay away from them
+    for i in 0..=30 {
+        let x = i as f64 * 0.1 - 1.5 + 0.03;
+        assert!((spline.derivative(x)? - 2.0 * x).abs() < 1e-3, "at {x}");
+        assert!((spline.second_derivative(x)? - 2.0).abs() < 0.05, "at {x}");
+    }
+    assert!(matches!(
+        spline.derivative(2.5),
+        Err(Error::PointOutOfBounds { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn spline_integral() -> Result<(), Error> {
+    use std::f64::consts::PI;
+
+    let pts = (0..=50)
+        .map(|i| i as f64 * PI / 50.0)
+        .map(|x| (x, x.sin()))
+        .collect::<Vec<_>>();
+    let spline = Spline::new(pts)?;
+
+    assert!((spline.integrate(0.0, PI)? - 2.0).abs() < 1e-5);
+    // partial first and last segments
+    assert!((spline.integrate(0.1, 3.0)? - (0.1f64.cos() - 3f64.cos())).abs() < 1e-5);
+    assert!((spline.integrate(3.0, 0.1)? + spline.integrate(0.1, 3.0)?).abs() < 1e-12);
+    assert_eq!(spline.integrate(1.0, 1.0)?, 0.0);
+    assert!(matches!(
+        spline.integrate(-1.0, 1.0),
+        Err(Error::PointOutOfBounds { .. })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn spline_lookup_speed() -> Result<(), Error> {
+    let pts = (0..5000)
+        .map(|i| i as f64 * 0.01)
+        .map(|x| (x, x.sin()))
+        .collect::<Vec<_>>();
+    let spline = Spline::new(pts)?;
+
+    let start = std::time::Instant::now();
+    let ys = spline.sample(0.0, 49.99, 5000)?;
+    assert!(start.elapsed() < std::time::Duration::from_millis(200));
+    assert!(ys.iter().all(|(x, y)| (y - x.sin()).abs() < 1e-3));
+
+    Ok(())
+}
+
+#[test]
+fn spline_from_parts() -> Result<(), Error> {
+    let spline = Spline::new(vec![(0.0, 0.0), (1.0, 2.0), (2.0, -1.0), (3.0, 1.0)])?;
+    let rebuilt = Spline::from_parts(spline.pts.clone(), spline.coefs.clone())?;
+    assert_eq!(rebuilt, spline);
+
+    assert_eq!(
+        Spline::from_parts(spline.pts.clone(), spline.coefs[1..].to_vec()),
+        Err(Error::CoefCountMismatch {
+            expected: 3,
+            got: 2
+        })
+    );
+    assert!(matches!(
+        Spline::from_parts(vec![(1.0, 0.0), (0.0, 0.0)], vec![(0.0, 0.0, 0.0, 0.0)]),
+        Err(Error::SingularSystem(_))
+    ));
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn spline_serde() -> Result<(), Error> {
+    let spline = Spline::new(vec![(0.0, 0.0), (1.0, 2.0), (2.0, -1.0), (3.0, 1.0)])?
+        .with_extrapolation(SplineExtrapolation::Extend);
+    let json = serde_json::to_string(&spline).unwrap();
+    let loaded: Spline = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, spline);
+    for x in [-0.5, 0.0, 0.3, 1.7, 3.0, 4.0] {
+        assert_eq!(loaded.apply(x), spline.apply(x));
+    }
+
+    // an extra knot leaves the coefficients one segment short
+    let broken = json.replace("\"pts\":[", "\"pts\":[[-1.0,0.0],");
+    let err = serde_json::from_str::<Spline>(&broken).unwrap_err();
+    assert!(err.to_string().contains("CoefCountMismatch"), "{err}");
+
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use std::fmt::Write;
+
+use super::{check_knots, Error};
+use crate::functions::function::Function;
+
+/// Monotone piecewise cubic Hermite interpolation (Fritsch-Carlson).
+/// Unlike `Spline` it only has a continuous first derivative, but it never overshoots:
+/// between two knots the curve stays within their y's.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Vec<(f64, f64)>", into = "Vec<(f64, f64)>")
+)]
+pub struct Pchip {
+    pts: Vec<(f64, f64)>,
+    /// The first derivative at every knot.
+    slopes: Vec<f64>,
+}
+
+impl Pchip {
+    /// The knots must be sorted by x.
+    pub fn new(known_points: Vec<(f64, f64)>) -> Result<Self, Error> {
+        check_knots(&known_points)?;
+        Ok(Self {
+            slopes: calc_slopes(&known_points),
+            pts: known_points,
+        })
+    }
+
+    /// Writes every segment as `a,b,c,d` of `a + bx + cx^2 + dx^3`, same as `Spline::write_coefs`.
+    pub fn write_coefs(&self) -> Result<String, Error> {
+        let mut s = String::new();
+        for i in 0..self.pts.len() - 1 {
+            let (a, b, c, d) = self.segment_coefs(i);
+            writeln!(s, "{},{},{},{}", a, b, c, d)?;
+        }
+
+        Ok(s)
+    }
+
+    /// Expands the Hermite form of segment `i` around 0.
+    fn segment_coefs(&self, i: usize) -> (f64, f64, f64, f64) {
+        let ((x0, y0), (x1, y1)) = (self.pts[i], self.pts[i + 1]);
+        let (m0, m1) = (self.slopes[i], self.slopes[i + 1]);
+        let h = x1 - x0;
+        let delta = (y1 - y0) / h;
+
+        // p(t) = y0 + m0 t + c2 t^2 + c3 t^3 with t = x - x0
+        let c2 = (3.0 * delta - 2.0 * m0 - m1) / h;
+        let c3 = (m0 + m1 - 2.0 * delta) / (h * h);
+        (
+            y0 - m0 * x0 + c2 * x0 * x0 - c3 * x0 * x0 * x0,
+            m0 - 2.0 * c2 * x0 + 3.0 * c3 * x0 * x0,
+            c2 - 3.0 * c3 * x0,
+            c3,
+        )
+    }
+}
+
+/// Three point estimates, zeroed at local extrema, then limited so that
+/// `alpha^2 + beta^2 <= 9` on every segment.
+fn calc_slopes(pts: &[(f64, f64)]) -> Vec<f64> {
+    let n = pts.len();
+    let deltas = pts
+        .windows(2)
+        .map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0))
+        .collect::<Vec<_>>();
+
+    let mut m = vec![0.0; n];
+    m[0] = deltas[0];
+    m[n - 1] = deltas[n - 2];
+    for i in 1..n - 1 {
+        if deltas[i - 1] * deltas[i] > 0.0 {
+            m[i] = (deltas[i - 1] + deltas[i]) / 2.0;
+        }
+    }
+
+    for (i, delta) in deltas.iter().enumerate() {
+        if *delta == 0.0 {
+            m[i] = 0.0;
+            m[i + 1] = 0.0;
+            continue;
+        }
+        let (alpha, beta) = (m[i] / delta, m[i + 1] / delta);
+        let r = alpha.hypot(beta);
+        if r > 3.0 {
+            m[i] = 3.0 / r * alpha * delta;
+            m[i + 1] = 3.0 / r * beta * delta;
+        }
+    }
+
+    m
+}
+
+impl Function for Pchip {
+    type Error = Error;
+
+    fn apply(&self, x: f64) -> Result<f64, Self::Error> {
+        let i = self.pts.partition_point(|(px, _)| *px < x).max(1);
+        if i >= self.pts.len() || self.pts[i - 1].0 > x {
+            return Err(Error::PointOutOfBounds {
+                x,
+                min: self.pts[0].0,
+                max: self.pts[self.pts.len() - 1].0,
+            });
+        }
+
+        let ((x0, y0), (x1, y1)) = (self.pts[i - 1], self.pts[i]);
+        let (m0, m1) = (self.slopes[i - 1], self.slopes[i]);
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+
+        let h00 = (1.0 + 2.0 * t) * (1.0 - t) * (1.0 - t);
+        let h10 = t * (1.0 - t) * (1.0 - t);
+        let h01 = t * t * (3.0 - 2.0 * t);
+        let h11 = t * t * (t - 1.0);
+        Ok(h00 * y0 + h10 * h * m0 + h01 * y1 + h11 * h * m1)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Vec<(f64, f64)>> for Pchip {
+    type Error = String;
+
+    fn try_from(pts: Vec<(f64, f64)>) -> Result<Self, Self::Error> {
+        Pchip::new(pts).map_err(|e| format!("{:?}", e))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Pchip> for Vec<(f64, f64)> {
+    fn from(pchip: Pchip) -> Self {
+        pchip.pts
+    }
+}
+
+#[test]
+fn pchip_monotone() -> Result<(), Error> {
+    use super::Spline;
+
+    let pts = vec![
+        (0.0, 0.0),
+        (1.0, 0.0),
+        (2.0, 0.1),
+        (3.0, 0.9),
+        (4.0, 1.0),
+        (5.0, 1.0),
+        (6.0, 1.0),
+    ];
+    let pchip = Pchip::new(pts.clone())?;
+    let spline = Spline::new(pts.clone())?;
+
+    for w in pts.windows(2) {
+        let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+        let (lo, hi) = (y0.min(y1), y0.max(y1));
+        for (x, y) in pchip.sample(x0, x1, 20)? {
+            assert!(lo <= y && y <= hi, "{y} at {x} outside of [{lo}, {hi}]");
+        }
+    }
+    // the cubic spline wiggles on the same data
+    assert!(spline.sample(0.0, 6.0, 120)?.iter().any(|(_, y)| *y < 0.0));
+
+    // the written coefficients describe the same curve
+    let coefs = pchip.write_coefs()?;
+    for (line, w) in coefs.lines().zip(pts.windows(2)) {
+        let c = line
+            .split(',')
+            .map(|c| c.parse::<f64>().unwrap())
+            .collect::<Vec<_>>();
+        let x = (w[0].0 + w[1].0) / 2.0;
+        let y = c[0] + c[1] * x + c[2] * x * x + c[3] * x * x * x;
+        assert!((y - pchip.apply(x)?).abs() < 1e-9);
+    }
+
+    assert!(matches!(
+        pchip.apply(6.5),
+        Err(Error::PointOutOfBounds { .. })
+    ));
+    assert!(matches!(
+        Pchip::new(vec![(1.0, 0.0), (0.0, 1.0)]),
+        Err(Error::SingularSystem(_))
+    ));
+
+    Ok(())
+}